@@ -0,0 +1,31 @@
+//! Boots a ROM without a window and prints the hash of the screen it shows.
+//!
+//!     $ cargo run --example headless_boot -- ROMv6.rom 200
+
+use gigatron_example::{GigatronBuilder, CLOCK_HZ, FRAME_CYCLES};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let (Some(rom), frames) = (args.next(), args.next()) else {
+        eprintln!("Usage: headless_boot <ROM> [FRAMES]");
+        std::process::exit(2);
+    };
+    let frames: u64 = frames.map_or(Ok(200), |n| n.parse()).unwrap_or_else(|_| {
+        eprintln!("FRAMES must be a number");
+        std::process::exit(2);
+    });
+
+    // Same garble every run, so the same ROM gives the same hash
+    let mut gt = GigatronBuilder::new().rom_file(&rom).seed(0).build().unwrap_or_else(|e| {
+        eprintln!("Can't load ROM {}: {}", rom, e);
+        std::process::exit(1);
+    });
+    // Booting takes a few frames' worth of cycles before video starts, so
+    // allow a second for that and twice the frames' length after it
+    let max_cycles = CLOCK_HZ as u64 + 2 * frames * FRAME_CYCLES;
+    if let Err(e) = gt.run_frames(frames, max_cycles) {
+        eprintln!("No video from the ROM: {}", e);
+        std::process::exit(1);
+    }
+    println!("{:016x}", gt.frame_hash());
+}
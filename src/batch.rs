@@ -0,0 +1,140 @@
+use gigatron_example::error::GigatronError;
+use gigatron_example::{GigatronBuilder, CLOCK_HZ, FRAME_CYCLES};
+use std::path::Path;
+
+/// File names taken as ROM images in a --batch directory.
+const SUFFIXES: [&str; 3] = [".rom", ".rom.gz", ".bin"];
+
+/// Frames at the end of a run whose lengths must all be the same for the
+/// video to count as stable.
+const STEADY_FRAMES: usize = 10;
+
+/// How a ROM fared.
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    Booted,          // Steady vSync and something on the screen
+    Blank,           // Steady vSync, but every pixel the same color
+    Unsteady,        // vSync came, at varying intervals
+    NoVsync,         // Not all the frames came before the cycle limit
+    Unloadable(String),
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Booted => "booted",
+            Status::Blank => "blank",
+            Status::Unsteady => "unsteady",
+            Status::NoVsync => "no-vsync",
+            Status::Unloadable(_) => "load-error",
+        }
+    }
+}
+
+/// One ROM's line of the report.
+pub struct Report {
+    pub file: String,
+    pub status: Status,
+    pub rom_type: Option<u8>,
+    pub frames: u64,               // Completed in the run
+    pub frame_cycles: Option<u64>, // Of the last frame
+    pub hash: Option<u64>,         // Of the last screen, once there was video
+}
+
+/// Runs every ROM image in `dir`, in name order, for `frames` frames each
+/// from the same power-on (`seed`), without a window.
+pub fn run(dir: &str, frames: u64, seed: u64) -> Result<Vec<Report>, GigatronError> {
+    let mut files: Vec<String> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| SUFFIXES.iter().any(|suffix| name.to_ascii_lowercase().ends_with(suffix)))
+        .collect();
+    files.sort();
+    Ok(files.into_iter().map(|file| run_one(&Path::new(dir).join(&file), file, frames, seed)).collect())
+}
+
+fn run_one(path: &Path, file: String, frames: u64, seed: u64) -> Report {
+    let mut gt = match GigatronBuilder::new().rom_file(&path.to_string_lossy()).seed(seed).build() {
+        Ok(gt) => gt,
+        Err(e) => {
+            let status = Status::Unloadable(e.to_string());
+            return Report { file, status, rom_type: None, frames: 0, frame_cycles: None, hash: None };
+        }
+    };
+    // As in examples/headless_boot.rs: a second to boot, then twice the
+    // frames' length
+    let max_cycles = CLOCK_HZ as u64 + 2 * frames * FRAME_CYCLES;
+    let mut vsyncs = Vec::new();
+    while gt.frames() < frames && gt.cycles() < max_cycles {
+        if gt.step().vSync {
+            vsyncs.push(gt.cycles());
+        }
+    }
+    let lengths: Vec<u64> = vsyncs.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let last = &lengths[lengths.len().saturating_sub(STEADY_FRAMES)..];
+    let screen = gt.frame();
+    let status = if gt.frames() < frames {
+        Status::NoVsync
+    } else if last.iter().any(|&n| n != last[0]) {
+        Status::Unsteady
+    } else if screen.iter().all(|&pixel| pixel == screen[0]) {
+        Status::Blank
+    } else {
+        Status::Booted
+    };
+    Report {
+        file,
+        status,
+        rom_type: Some(gt.rom_type()),
+        frames: gt.frames(),
+        frame_cycles: lengths.last().copied(),
+        hash: (!vsyncs.is_empty()).then(|| gt.frame_hash()),
+    }
+}
+
+/// The reports as an aligned table, with what went wrong loading a ROM
+/// after it.
+pub fn table(reports: &[Report]) -> String {
+    let width = reports.iter().map(|r| r.file.len()).max().unwrap_or(0).max("ROM".len());
+    let mut out = format!("{:<width$}  {:<10}  romType  frames  cycles/frame  hash\n", "ROM", "status");
+    for r in reports {
+        let fields = fields(r);
+        let why = match &r.status {
+            Status::Unloadable(why) => why.as_str(),
+            _ => "",
+        };
+        let line = format!(
+            "{:<width$}  {:<10}  {:<7}  {:>6}  {:>12}  {:<16}  {}",
+            r.file, fields[0], fields[1], fields[2], fields[3], fields[4], why
+        );
+        out += line.trim_end();
+        out += "\n";
+    }
+    out
+}
+
+/// The reports as CSV, one row per ROM after a header row.
+pub fn csv(reports: &[Report]) -> String {
+    let mut out = "rom,status,rom_type,frames,frame_cycles,hash,error\n".to_string();
+    for r in reports {
+        let error = match &r.status {
+            Status::Unloadable(why) => format!("\"{}\"", why.replace('"', "\"\"")),
+            _ => String::new(),
+        };
+        let file = format!("\"{}\"", r.file.replace('"', "\"\""));
+        out += &format!("{},{},{}\n", file, fields(r).join(","), error);
+    }
+    out
+}
+
+/// Status, romType, frames, frame length and hash, as shown.
+fn fields(r: &Report) -> [String; 5] {
+    [
+        r.status.name().to_string(),
+        r.rom_type.map_or(String::new(), |t| format!("0x{:02x}", t)),
+        r.frames.to_string(),
+        r.frame_cycles.map_or(String::new(), |n| n.to_string()),
+        r.hash.map_or(String::new(), |h| format!("{:016x}", h)),
+    ]
+}
@@ -0,0 +1,146 @@
+use gigatron_example::{mem, menu, Edges, Gigatron, RASTER_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+use log::info;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Steps of a ROM's boot, in the order ROMv6 reaches them. Each is guessed
+/// from what the ROM stores and the sync it sends, not from where it runs,
+/// so other ROMs are traced as well.
+#[derive(Clone, Copy, PartialEq)]
+enum Milestone {
+    ResetReleased,
+    VideoTable,  // First store to the videoTable
+    VcpuStart,   // First store to vPC
+    InputPoll,   // First store to serialRaw
+    RomType,     // First store to romType
+    VideoLoop,   // Two hSyncs a line apart: the video loop is running
+    FirstVsync,
+    ScreenClear, // Every visible byte stored at least once
+    Menu,        // The menu's cursor is on the screen
+}
+
+const MILESTONES: [(Milestone, &str); 9] = [
+    (Milestone::ResetReleased, "reset released"),
+    (Milestone::VideoTable, "videoTable written"),
+    (Milestone::VcpuStart, "vCPU started"),
+    (Milestone::InputPoll, "first input poll"),
+    (Milestone::RomType, "romType set"),
+    (Milestone::VideoLoop, "video loop running"),
+    (Milestone::FirstVsync, "first vSync"),
+    (Milestone::ScreenClear, "screen cleared"),
+    (Milestone::Menu, "menu showing"),
+];
+
+/// Logs how far the ROM got booting (--boot-trace), one line per milestone
+/// the first time it is reached, and at the end the ones it never reached:
+///
+/// ```text
+/// Boot: cycle 1 (frame 0): reset released
+/// Boot: cycle 11 (frame 0): videoTable written
+/// ```
+pub struct BootTrace {
+    stores: Rc<RefCell<Vec<(u16, u8)>>>, // Filled by the write watches, drained every step
+    reached: Vec<Milestone>,
+    stored: Vec<bool>, // By visible pixel, whether its byte was stored
+    unstored: usize,
+    last_hsync: Option<u64>,
+}
+
+impl BootTrace {
+    pub fn new(gt: &mut Gigatron) -> Self {
+        let stores: Rc<RefCell<Vec<(u16, u8)>>> = Rc::default();
+        let watched = stores.clone();
+        gt.add_write_watch_range(
+            0x0000..=0x01ef, // Zero page and the videoTable
+            Box::new(move |addr, value| watched.borrow_mut().push((addr, value))),
+        );
+        let watched = stores.clone();
+        gt.add_write_watch_range(mem::VIDEO_BASE..=0x7fff, Box::new(move |addr, value| watched.borrow_mut().push((addr, value))));
+        BootTrace {
+            stores,
+            reached: Vec::new(),
+            stored: vec![false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            unstored: SCREEN_WIDTH * SCREEN_HEIGHT,
+            last_hsync: None,
+        }
+    }
+
+    /// Looks for milestones in the cycle `gt` just ran, and the sync it
+    /// sent.
+    pub fn record(&mut self, gt: &Gigatron, edges: &Edges) {
+        let stores: Vec<(u16, u8)> = self.stores.borrow_mut().drain(..).collect();
+        if self.reached.len() == MILESTONES.len() {
+            return;
+        }
+        if !gt.in_reset() {
+            self.reach(gt, Milestone::ResetReleased, String::new());
+        }
+        // The garbled OUT at power-on can make an edge of its own
+        if edges.hSync {
+            if self.last_hsync.is_some_and(|t| gt.cycles() - t == RASTER_WIDTH as u64) {
+                self.reach(gt, Milestone::VideoLoop, format!(", memSize ${:02x}", gt.RAM[1]));
+            }
+            self.last_hsync = Some(gt.cycles());
+        }
+        if edges.vSync {
+            self.reach(gt, Milestone::FirstVsync, String::new());
+            if !self.reached.contains(&Milestone::Menu)
+                && let Some(menu) = menu::for_rom(gt.rom_type())
+                && let Some((column, row)) = menu.selected(&gt.frame())
+            {
+                self.reach(gt, Milestone::Menu, format!(", cursor on {}", menu.columns[column][row]));
+            }
+        }
+        for (addr, value) in stores {
+            match addr {
+                mem::ROM_TYPE => self.reach(gt, Milestone::RomType, format!(" to ${:02x}", value)),
+                mem::KEYBOARD_CHAR => self.reach(gt, Milestone::InputPoll, format!(", ${:02x}", value)),
+                mem::VPC => self.reach(gt, Milestone::VcpuStart, String::new()),
+                mem::VIDEO_TABLE..=0x01ef => self.reach(gt, Milestone::VideoTable, String::new()),
+                mem::VIDEO_BASE.. => self.screen_store(gt, addr),
+                _ => {}
+            }
+        }
+    }
+
+    fn screen_store(&mut self, gt: &Gigatron, addr: u16) {
+        let (y, x) = (((addr >> 8) - (mem::VIDEO_BASE >> 8)) as usize, (addr & 0xff) as usize);
+        if x < SCREEN_WIDTH && !std::mem::replace(&mut self.stored[y * SCREEN_WIDTH + x], true) {
+            self.unstored -= 1;
+            if self.unstored == 0 {
+                self.reach(gt, Milestone::ScreenClear, String::new());
+            }
+        }
+    }
+
+    /// Logs `milestone` unless it was already reached, with `detail` after
+    /// its name.
+    fn reach(&mut self, gt: &Gigatron, milestone: Milestone, detail: String) {
+        if self.reached.contains(&milestone) {
+            return;
+        }
+        self.reached.push(milestone);
+        let cycle = gt.cycles() - 1;
+        info!("Boot: cycle {} (frame {}): {}{}", cycle, gt.frames(), name(milestone), detail);
+    }
+
+    /// Logs the milestones never reached, the menu only when the ROM has a
+    /// known one.
+    pub fn finish(&self, gt: &Gigatron) {
+        let missing: Vec<&str> = MILESTONES
+            .iter()
+            .filter(|(milestone, _)| !self.reached.contains(milestone))
+            .filter(|(milestone, _)| *milestone != Milestone::Menu || menu::for_rom(gt.rom_type()).is_some())
+            .map(|(_, name)| *name)
+            .collect();
+        if missing.is_empty() {
+            info!("Boot: every milestone reached");
+        } else {
+            info!("Boot: never reached {} (after {} cycles)", missing.join(", "), gt.cycles());
+        }
+    }
+}
+
+fn name(milestone: Milestone) -> &'static str {
+    MILESTONES.iter().find(|(m, _)| *m == milestone).map_or("", |(_, name)| name)
+}
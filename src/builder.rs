@@ -0,0 +1,197 @@
+//! Configuring a [`Gigatron`] in one expression, for embedders: everything
+//! [`Gigatron::new`] leaves at its default, set before the machine powers
+//! on.
+//!
+//! ```no_run
+//! use gigatron_example::{GigatronBuilder, ROM6};
+//!
+//! let mut gt = GigatronBuilder::new().rom_bytes(ROM6).seed(0).clean_boot(true).build()?;
+//! gt.run_frame();
+//! # Ok::<(), gigatron_example::error::GigatronError>(())
+//! ```
+
+use crate::error::GigatronError;
+use crate::input::Polarity;
+use crate::patch::Patch;
+use crate::peripheral::Peripheral;
+use crate::{reset, Gigatron, RamFill, RamSize};
+use rand::RngCore;
+
+enum Rom {
+    File(String),
+    Bytes(Vec<u8>),
+}
+
+/// Settings for a [`Gigatron`], applied by [`build`](Self::build). Each
+/// one not set stays as [`Gigatron::new`] has it.
+pub struct GigatronBuilder {
+    rom: Option<Rom>,
+    rom_byteswap: bool,
+    patches: Vec<Patch>,
+    seed: Option<u64>,
+    rng: Option<Box<dyn RngCore>>, // Wins over seed
+    clean_boot: bool,
+    ram_fill: RamFill,
+    ram_size: RamSize,
+    reset_hold: u64,
+    polarity: Polarity,
+    fast_load: bool,
+    rtc_addr: Option<u16>,
+    trace_sys: bool,
+    peripherals: Vec<Box<dyn Peripheral>>,
+}
+
+impl GigatronBuilder {
+    pub fn new() -> Self {
+        GigatronBuilder {
+            rom: None,
+            rom_byteswap: false,
+            patches: Vec::new(),
+            seed: None,
+            rng: None,
+            clean_boot: false,
+            ram_fill: RamFill::Random,
+            ram_size: RamSize::K32,
+            reset_hold: reset::DEFAULT_HOLD,
+            polarity: Polarity::ActiveLow,
+            fast_load: false,
+            rtc_addr: None,
+            trace_sys: false,
+            peripherals: Vec::new(),
+        }
+    }
+
+    /// The ROM image to load from `filename`, which may be gzip-compressed.
+    /// Without one (or [`rom_bytes`](Self::rom_bytes)) the ROM is all zeros.
+    pub fn rom_file(mut self, filename: &str) -> Self {
+        self.rom = Some(Rom::File(filename.to_string()));
+        self
+    }
+
+    /// The ROM image, already in memory, such as [`ROM6`](crate::ROM6).
+    pub fn rom_bytes(mut self, data: &[u8]) -> Self {
+        self.rom = Some(Rom::Bytes(data.to_vec()));
+        self
+    }
+
+    /// The image stores each word as [data, instruction].
+    pub fn rom_byteswap(mut self, byteswap: bool) -> Self {
+        self.rom_byteswap = byteswap;
+        self
+    }
+
+    /// ROM words to replace once the image is loaded, as
+    /// [`patch::read`](crate::patch::read) returns them.
+    pub fn patches(mut self, patches: Vec<Patch>) -> Self {
+        self.patches = patches;
+        self
+    }
+
+    /// Power-on garble and bus noise from a fixed seed, the same every
+    /// run, instead of from the OS.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Power-on garble and bus noise from `rng`; see
+    /// [`Gigatron::set_rng`].
+    pub fn rng(mut self, rng: Box<dyn RngCore>) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+
+    /// Zero the registers (and RAM, unless [`ram_fill`](Self::ram_fill) is
+    /// set) instead of garbling them.
+    pub fn clean_boot(mut self, clean: bool) -> Self {
+        self.clean_boot = clean;
+        self
+    }
+
+    pub fn ram_fill(mut self, fill: RamFill) -> Self {
+        self.ram_fill = fill;
+        self
+    }
+
+    /// RAM fitted; see [`Gigatron::set_ram_size`].
+    pub fn ram_size(mut self, size: RamSize) -> Self {
+        self.ram_size = size;
+        self
+    }
+
+    /// Cycles the MCP100 holds reset after power-on.
+    pub fn reset_hold(mut self, cycles: u64) -> Self {
+        self.reset_hold = cycles;
+        self
+    }
+
+    /// How the controller shows on IN.
+    pub fn polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Start GT1 programs at their entry once they are in RAM, the way the
+    /// Loader does; see [`Gigatron::load_gt1`].
+    pub fn fast_load(mut self, fast: bool) -> Self {
+        self.fast_load = fast;
+        self
+    }
+
+    /// Map the RTC into RAM at `addr`.
+    pub fn rtc_addr(mut self, addr: u16) -> Self {
+        self.rtc_addr = Some(addr);
+        self
+    }
+
+    /// Log every SYS function the vCPU calls.
+    pub fn trace_sys(mut self, trace: bool) -> Self {
+        self.trace_sys = trace;
+        self
+    }
+
+    /// A device to put on the bus; see [`Gigatron::attach`]. May be given
+    /// more than once.
+    pub fn peripheral(mut self, peripheral: Box<dyn Peripheral>) -> Self {
+        self.peripherals.push(peripheral);
+        self
+    }
+
+    /// Loads and patches the ROM, then powers on, so the first
+    /// [`step`](Gigatron::step) runs the ROM's reset code. Fails only when
+    /// the ROM can't be loaded.
+    pub fn build(self) -> Result<Gigatron, GigatronError> {
+        let mut gt = Gigatron::new();
+        gt.rom_byteswap = self.rom_byteswap;
+        match &self.rom {
+            Some(Rom::File(filename)) => gt.read_rom(filename)?,
+            Some(Rom::Bytes(data)) => gt.read_rom_bytes(data)?,
+            None => {}
+        }
+        gt.apply_patches(&self.patches);
+        if let Some(rng) = self.rng {
+            gt.set_rng(rng);
+        } else if let Some(seed) = self.seed {
+            gt.seed(seed);
+        }
+        gt.clean_boot = self.clean_boot;
+        gt.ram_fill = self.ram_fill;
+        gt.set_ram_size(self.ram_size);
+        gt.reset_hold = self.reset_hold;
+        gt.port.polarity = self.polarity;
+        gt.fast_load = self.fast_load;
+        gt.rtc_addr = self.rtc_addr;
+        gt.trace_sys = self.trace_sys;
+        for peripheral in self.peripherals {
+            gt.attach(peripheral);
+        }
+        gt.init();
+        Ok(gt)
+    }
+}
+
+impl Default for GigatronBuilder {
+    fn default() -> Self {
+        GigatronBuilder::new()
+    }
+}
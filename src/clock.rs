@@ -0,0 +1,75 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// Host time, as pacing and frame-rate decisions see it. Frontends use
+/// [`RealClock`]; tests use a [`ManualClock`] and advance it themselves, so
+/// no decision depends on how fast the test machine happens to be.
+pub trait Clock {
+    /// Time since the clock was created.
+    fn now(&self) -> Duration;
+
+    /// Waits for `duration` to pass.
+    fn sleep(&self, duration: Duration);
+}
+
+/// Waits shorter than this spin instead of sleeping, as the OS scheduler
+/// can't wake up that precisely.
+const SPIN: Duration = Duration::from_millis(1);
+
+pub struct RealClock {
+    start: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        RealClock { start: Instant::now() }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        RealClock::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        if duration >= SPIN {
+            return std::thread::sleep(duration);
+        }
+        let end = self.now() + duration;
+        while self.now() < end {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// A clock that only moves when told to; `sleep` advances it instantly.
+#[derive(Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::{disasm, CpuState, Gigatron};
+use std::fmt;
+use std::str::FromStr;
+
+pub const HELP: &str = "\
+Commands:
+    regs, r           Show the registers
+    step, s [N]       Run N cycles (default: 1) and show the registers
+    back, b [N]       Undo the last N cycles (default: 1) and show the registers, as
+                      far as --history keeps them; RAM gets back what they stored over
+    video, v          Show where each scanline comes from, per the videoTable
+    io, i             Show the ROM's input, video and vCPU variables in RAM
+    page, pg          Disassemble the 256-word ROM page PC is in, which branches
+                      can't leave
+    poke ADDR VALUE   Store VALUE at RAM address ADDR (decimal, or hex with 0x or $)
+    checkpoint, cp    Remember the registers and cycle count (not RAM)
+    rollback, rb      Go back to them; RAM keeps any stores made since
+    preview, p        Toggle redrawing the screen after every step, so drawing
+                      shows up as it happens instead of at the next vSync (slow)
+    continue, c       Leave the debugger and keep running
+    quit, q           Stop the emulator
+    help, h           Show this help
+";
+
+/// A debugger command, as typed at the prompt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Regs,
+    Step(u64),
+    Back(u64),
+    VideoTable,
+    Io,
+    Page,
+    Poke(u16, u8),
+    Checkpoint,
+    Rollback,
+    Preview,
+    Continue,
+    Quit,
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut words = s.split_whitespace();
+        let command = match words.next() {
+            Some("regs" | "r") => Command::Regs,
+            Some("step" | "s") => match words.next() {
+                Some(n) => Command::Step(n.parse().map_err(|_| format!("Invalid step count: {}", n))?),
+                None => Command::Step(1),
+            },
+            Some("back" | "b") => match words.next() {
+                Some(n) => Command::Back(n.parse().map_err(|_| format!("Invalid step count: {}", n))?),
+                None => Command::Back(1),
+            },
+            Some("video" | "v") => Command::VideoTable,
+            Some("io" | "i") => Command::Io,
+            Some("page" | "pg") => Command::Page,
+            Some("poke") => {
+                let (Some(addr), Some(value)) = (words.next(), words.next()) else {
+                    return Err("Usage: poke ADDR VALUE".to_string());
+                };
+                let addr = number(addr).and_then(|a| u16::try_from(a).ok());
+                let value = number(value).and_then(|v| u8::try_from(v).ok());
+                match (addr, value) {
+                    (Some(addr), Some(value)) => Command::Poke(addr, value),
+                    (None, _) => return Err("The address must be 0 to 0xffff".to_string()),
+                    (_, None) => return Err("The value must be a byte, 0 to 0xff".to_string()),
+                }
+            }
+            Some("checkpoint" | "cp") => Command::Checkpoint,
+            Some("rollback" | "rb") => Command::Rollback,
+            Some("preview" | "p") => Command::Preview,
+            Some("continue" | "c") => Command::Continue,
+            Some("quit" | "q") => Command::Quit,
+            Some("help" | "h") => Command::Help,
+            Some(word) => return Err(format!("Unknown command: {} (try help)", word)),
+            None => return Err("Empty command".to_string()),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("Unexpected argument: {}", extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+/// The command as typed, so a recorded session reads back the same.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Regs => write!(f, "regs"),
+            Command::Step(n) => write!(f, "step {}", n),
+            Command::Back(n) => write!(f, "back {}", n),
+            Command::VideoTable => write!(f, "video"),
+            Command::Io => write!(f, "io"),
+            Command::Page => write!(f, "page"),
+            Command::Poke(addr, value) => write!(f, "poke 0x{:04x} 0x{:02x}", addr, value),
+            Command::Checkpoint => write!(f, "checkpoint"),
+            Command::Rollback => write!(f, "rollback"),
+            Command::Preview => write!(f, "preview"),
+            Command::Continue => write!(f, "continue"),
+            Command::Quit => write!(f, "quit"),
+            Command::Help => write!(f, "help"),
+        }
+    }
+}
+
+/// An integer in decimal or in hex with a `0x` or `$` prefix.
+fn number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix('$')) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// A debugger script: one command per line as typed at the prompt, with
+/// blank lines and `#` comments skipped. Fails on the first line that
+/// doesn't parse, so a typo shows before anything runs.
+pub fn parse_script(text: &str) -> Result<Vec<Command>, String> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| line.parse().map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// Formats a data register with its unsigned and two's complement value,
+/// as in `AC=0xFE (254, -2)`.
+fn byte(name: &str, value: u8) -> String {
+    format!("{}=0x{:02X} ({}, {})", name, value, value, value as i8)
+}
+
+/// One-line register dump. PC, IR and D are addresses and opcodes so they are
+/// only shown in hex; the registers that hold data show both readings.
+pub fn regs(S: &CpuState) -> String {
+    format!(
+        "PC=0x{:04X} IR=0x{:02X} D=0x{:02X} {} {} {} {}",
+        S.PC, S.IR, S.D,
+        byte("AC", S.AC), byte("X", S.X), byte("Y", S.Y), byte("OUT", S.OUT),
+    )
+}
+
+/// The videoTable as `line N -> RAM page 0xXX, x 0xXX`, one logical
+/// scanline per line, with the X offset the kernel starts reading at,
+/// after a summary of the layout.
+pub fn video_table(gt: &Gigatron) -> String {
+    let g = gt.video_geometry();
+    let summary = format!(
+        "{}x{} from row {}, base 0x{:04X}, stride {}, scroll x {} y {}{}",
+        g.width, g.height, g.top, g.base, g.stride, g.scroll_x, g.scroll_y,
+        if g.linear { "" } else { " (not linear)" },
+    );
+    std::iter::once(summary)
+        .chain(gt.scanlines().iter().enumerate().map(|(line, &(page, x))| {
+            format!("line {} -> RAM page 0x{:02X}, x 0x{:02X}", line, page, x)
+        }))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The labeled RAM locations, e.g. `$0011 buttonState  $ff`, then the
+/// typed characters still waiting for the input port, if any.
+pub fn io_values(gt: &Gigatron) -> String {
+    let mut lines: Vec<String> = gt
+        .io_values()
+        .iter()
+        .map(|v| format!("${:04x} {:<12} ${:0w$x}", v.addr, v.name, v.value, w = 2 * v.size))
+        .collect();
+    let queued: Vec<String> = gt.port.queued().map(|(t, code)| format!("${:02x} (cycle {})", code, t)).collect();
+    if !queued.is_empty() {
+        lines.push(format!("Typed, waiting for the port: {}", queued.join(", ")));
+    }
+    lines.join("\n")
+}
+
+/// The ROM page PC is in, one instruction per line as in --disasm-out
+/// after a `page $XX` header, with PC's marked.
+pub fn page(gt: &Gigatron) -> String {
+    let start = gt.S.PC & 0xff00;
+    let mut lines = vec![format!("page ${:02x}", start >> 8)];
+    for (addr, &[opcode, d]) in (start..).zip(gt.rom_slice(start, 256)) {
+        let mark = if addr == gt.S.PC { "  <- PC" } else { "" };
+        lines.push(format!("{:04x}  {:02x}{:02x}  {}{}", addr, opcode, d, disasm::disassemble(opcode, d), mark));
+    }
+    lines.join("\n")
+}
+
+/// Runs `command` and returns what to print. `Checkpoint`, `Rollback`,
+/// `Preview`, `Continue` and `Quit` are left to the caller, which owns the
+/// checkpoint, the screen and the run loop.
+pub fn execute(gt: &mut Gigatron, command: Command) -> String {
+    match command {
+        Command::Regs => regs(&gt.S),
+        Command::Step(n) => {
+            for _ in 0..n {
+                gt.step();
+            }
+            regs(&gt.S)
+        }
+        Command::Back(n) => {
+            let undone = (0..n).take_while(|_| gt.step_back()).count() as u64;
+            match undone {
+                0 => "Nothing to step back over (start with --history N to keep cycles)".to_string(),
+                _ if undone < n => format!("Undid the {} cycle(s) kept\n{}", undone, regs(&gt.S)),
+                _ => regs(&gt.S),
+            }
+        }
+        Command::VideoTable => video_table(gt),
+        Command::Io => io_values(gt),
+        Command::Page => page(gt),
+        Command::Poke(addr, value) => {
+            // Where a store by the CPU would land, mirrored with 32K
+            let addr = gt.ram_addr(addr);
+            gt.RAM[addr as usize] = value;
+            format!("${:04x} = ${:02x}", addr, value)
+        }
+        Command::Help => HELP.trim_end().to_string(),
+        Command::Checkpoint | Command::Rollback | Command::Preview | Command::Continue | Command::Quit => String::new(),
+    }
+}
+
+/// A condition to break into the debugger on (--break), such as
+/// `PC==0x0300 && AC==0`: comparisons of registers, RAM bytes (`[0x0011]`)
+/// and numbers with `==`, `!=`, `<`, `<=`, `>` or `>=`, joined by `&&`
+/// and `||`, `&&` binding tighter. Registers are named as [`regs`] shows
+/// them, PC being the address fetched, so the condition sees the machine
+/// as it is shown when it breaks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    text: String,
+    any: Vec<Vec<Comparison>>, // Holds when all of one of these do
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Comparison {
+    left: Operand,
+    op: Op,
+    right: Operand,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operand {
+    Register(Register),
+    Ram(u16),
+    Number(u16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Register {
+    PC,
+    IR,
+    D,
+    AC,
+    X,
+    Y,
+    OUT,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Where two start at the same place, as `<=` and `<`, the longer one is
+/// meant.
+const OPS: [(&str, Op); 6] = [("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)];
+
+impl Condition {
+    pub fn holds(&self, gt: &Gigatron) -> bool {
+        self.any.iter().any(|all| all.iter().all(|comparison| comparison.holds(gt)))
+    }
+
+    /// The highest RAM address the condition reads, if it reads RAM, for
+    /// checking against the RAM fitted.
+    pub fn highest_ram_addr(&self) -> Option<u16> {
+        self.any
+            .iter()
+            .flatten()
+            .flat_map(|comparison| [comparison.left, comparison.right])
+            .filter_map(|operand| match operand {
+                Operand::Ram(addr) => Some(addr),
+                _ => None,
+            })
+            .max()
+    }
+}
+
+impl Comparison {
+    fn holds(&self, gt: &Gigatron) -> bool {
+        let (left, right) = (self.left.value(gt), self.right.value(gt));
+        match self.op {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+        }
+    }
+}
+
+impl Operand {
+    fn value(&self, gt: &Gigatron) -> u16 {
+        let S = &gt.S;
+        match *self {
+            Operand::Register(Register::PC) => S.PC,
+            Operand::Register(Register::IR) => S.IR as u16,
+            Operand::Register(Register::D) => S.D as u16,
+            Operand::Register(Register::AC) => S.AC as u16,
+            Operand::Register(Register::X) => S.X as u16,
+            Operand::Register(Register::Y) => S.Y as u16,
+            Operand::Register(Register::OUT) => S.OUT as u16,
+            Operand::Ram(addr) => gt.RAM[gt.ram_addr(addr) as usize] as u16,
+            Operand::Number(n) => n,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let any = s
+            .split("||")
+            .map(|all| all.split("&&").map(|comparison| comparison.parse()).collect::<Result<Vec<_>, _>>())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Condition { text: s.trim().to_string(), any })
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (at, op) = OPS
+            .iter()
+            .filter_map(|&(symbol, op)| s.find(symbol).map(|at| (at, symbol, op)))
+            .min_by_key(|&(at, symbol, _)| (at, usize::MAX - symbol.len()))
+            .map(|(at, symbol, op)| ((at, at + symbol.len()), op))
+            .ok_or(format!("No comparison in {:?}", s.trim()))?;
+        Ok(Comparison { left: s[..at.0].parse()?, op, right: s[at.1..].parse()? })
+    }
+}
+
+impl FromStr for Operand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Some(addr) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            return number(addr.trim())
+                .and_then(|addr| u16::try_from(addr).ok())
+                .map(Operand::Ram)
+                .ok_or(format!("Not a RAM address: {}", s));
+        }
+        let register = match s.to_ascii_uppercase().as_str() {
+            "PC" => Register::PC,
+            "IR" => Register::IR,
+            "D" => Register::D,
+            "AC" => Register::AC,
+            "X" => Register::X,
+            "Y" => Register::Y,
+            "OUT" => Register::OUT,
+            _ => {
+                return number(s)
+                    .and_then(|n| u16::try_from(n).ok())
+                    .map(Operand::Number)
+                    .ok_or(format!("Not a register, [address] or number: {:?}", s));
+            }
+        };
+        Ok(Operand::Register(register))
+    }
+}
+
+/// The condition as it was given.
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CpuState, RamSize};
+
+    /// Conditions against PC 0x0300, AC 0 and RAM[0x0011] 0xfe, and
+    /// whether each holds.
+    const CONDITIONS: [(&str, bool); 7] = [
+        ("PC==0x0300 && AC==0", true),
+        ("PC==0x0300 && AC!=0", false),
+        ("pc == $300", true),
+        ("[0x0011]<=0xfe && [0x11]>0xfd", true),
+        ("AC>0 || [$0011]==254", true),
+        ("AC>0 || X>0xff", false),
+        ("PC>=0x0301", false),
+    ];
+
+    #[test]
+    fn break_conditions() {
+        let mut gt = Gigatron::new();
+        gt.S = CpuState { PC: 0x0300, AC: 0, X: 0xff, ..CpuState::default() };
+        gt.RAM[0x0011] = 0xfe;
+        for (text, expected) in CONDITIONS {
+            let condition: Condition = text.parse().unwrap();
+            assert_eq!(condition.holds(&gt), expected, "{:?}", text);
+        }
+        for text in ["PC=3", "PC==", "[0x10000]==0", "foo>1", "AC==0 &&"] {
+            assert!(text.parse::<Condition>().is_err(), "{:?} should be refused", text);
+        }
+    }
+
+    /// Upper-half addresses read and poke the mirror with 32K fitted, and
+    /// their own bytes with 64K.
+    #[test]
+    fn upper_ram() {
+        for (size, landed) in [(RamSize::K32, 0x0012), (RamSize::K64, 0x8012)] {
+            let mut gt = Gigatron::new();
+            gt.set_ram_size(size);
+            gt.RAM[landed] = 0xfe;
+            let condition: Condition = "[0x8012]==0xfe && [3]==0".parse().unwrap();
+            assert!(condition.holds(&gt), "{:?}", size);
+            assert_eq!(condition.highest_ram_addr(), Some(0x8012));
+            execute(&mut gt, Command::Poke(0x8012, 0x42));
+            assert_eq!(gt.RAM[landed], 0x42, "{:?}", size);
+        }
+    }
+}
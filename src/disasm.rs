@@ -0,0 +1,145 @@
+use crate::{decode, DecodedInsn};
+use std::io::Write;
+
+/// Consecutive `ld $xx` words from which a stretch is listed as data. Code
+/// never loads that many immediates in a row, as each overwrites AC; tables
+/// and the ROM's embedded files do.
+const DATA_RUN: usize = 16;
+
+/// Operand address for the addressing mode, as in the Gigatron assembler.
+fn address(mode: u8, d: u8) -> String {
+    match mode {
+        1 => "[x]".to_string(),
+        2 => format!("[y,${:02x}]", d),
+        3 => "[y,x]".to_string(),
+        7 => "[y,x++]".to_string(),
+        _ => format!("[${:02x}]", d), // 0, 4, 5, 6
+    }
+}
+
+/// Disassembles one instruction into the assembler's syntax, e.g.
+/// `ld [y,x]`, `st [$30],x`, `bne $0c` or `jmp y,ac`.
+pub fn disassemble(opcode: u8, d: u8) -> String {
+    let DecodedInsn { ins, mode, bus, is_write, is_jump } = decode(opcode);
+    if opcode == 0x02 {
+        return "nop".to_string(); // ld ac
+    }
+    if is_jump {
+        let operand = match bus {
+            0 => format!("${:02x}", d),
+            1 => format!("[${:02x}]", d),
+            2 => "ac".to_string(),
+            _ => "in".to_string(),
+        };
+        const JUMPS: [&str; 8] = ["jmp", "bgt", "blt", "bne", "beq", "bge", "ble", "bra"];
+        return match mode {
+            0 => format!("jmp y,{}", operand),
+            _ => format!("{} {}", JUMPS[mode as usize], operand),
+        };
+    }
+    let target = match mode {
+        4 => ",x",
+        5 => ",y",
+        6 | 7 => ",out",
+        _ => "",
+    };
+    if is_write {
+        let source = match bus {
+            0 => format!("${:02x},", d),
+            1 => return format!("ctrl {}", address(mode, d)), // Picked up by the expander
+            2 => String::new(),                                // st [..] stores AC
+            _ => "in,".to_string(),
+        };
+        // Only X and Y are loaded during a write
+        let target = if mode == 4 || mode == 5 { target } else { "" };
+        return format!("st {}{}{}", source, address(mode, d), target);
+    }
+    const OPS: [&str; 6] = ["ld", "anda", "ora", "xora", "adda", "suba"];
+    let operand = match bus {
+        0 => format!("${:02x}", d),
+        1 => address(mode, d),
+        2 => "ac".to_string(),
+        _ => "in".to_string(),
+    };
+    format!("{} {}{}", OPS[ins as usize], operand, target)
+}
+
+/// Writes a listing of the whole ROM, one `address  word  instruction` line
+/// per word. Long runs of immediate loads are listed as `.data` instead.
+pub fn write_listing(rom: &[[u8; 2]], out: &mut impl Write) -> std::io::Result<()> {
+    let mut addr = 0;
+    while addr < rom.len() {
+        let run = rom[addr..].iter().take_while(|word| word[0] == 0x00).count();
+        if run >= DATA_RUN {
+            writeln!(out, "; data {:04x}..{:04x}", addr, addr + run - 1)?;
+            for (i, word) in rom[addr..addr + run].iter().enumerate() {
+                writeln!(out, "{:04x}  {:02x}{:02x}  .data ${:02x}", addr + i, word[0], word[1], word[1])?;
+            }
+            addr += run;
+            continue;
+        }
+        let [opcode, d] = rom[addr];
+        writeln!(out, "{:04x}  {:02x}{:02x}  {}", addr, opcode, d, disassemble(opcode, d))?;
+        addr += 1;
+    }
+    Ok(())
+}
+
+/// The first `count` instructions the CPU runs out of reset, PC=0, in the
+/// listing's format: straight on, and through each unconditional jump
+/// after its delay slot. Jumps show where they go (`-> 0100`) once that is
+/// known from the code itself, from an immediate and, for a far jump, the
+/// last `ld $xx,y`; the path ends at one that goes elsewhere or back to
+/// where it has been.
+pub fn boot_path(rom: &[[u8; 2]], count: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut visited = Vec::new();
+    let (mut pc, mut y, mut jump): (u16, Option<u8>, Option<Option<u16>>) = (0, None, None);
+    while lines.len() < count {
+        let [opcode, d] = rom[pc as usize];
+        let mut line = format!("{:04x}  {:02x}{:02x}  {}", pc, opcode, d, disassemble(opcode, d));
+        visited.push(pc);
+        let DecodedInsn { ins, mode, bus, is_jump, .. } = decode(opcode);
+        let after_slot = jump.take();
+        if is_jump {
+            let target = match (mode, bus) {
+                (0, 0) => y.map(|y| (y as u16) << 8 | d as u16),
+                (_, 0) => Some(pc.wrapping_add(1) & 0xff00 | d as u16),
+                _ => None,
+            };
+            line += &target.map_or("  -> ?".to_string(), |t| format!("  -> {:04x}", t));
+            if mode == 0 || mode == 7 {
+                jump = Some(target); // jmp and bra, taken after the next instruction
+            }
+        } else if mode == 5 {
+            y = (ins == 0 && bus == 0).then_some(d); // ld $xx,y
+        }
+        lines.push(line);
+        pc = match after_slot {
+            Some(Some(target)) if visited.contains(&target) => {
+                lines.push(format!("; loops back to {:04x}", target));
+                break;
+            }
+            Some(Some(target)) => target,
+            Some(None) => break,
+            None => pc.wrapping_add(1),
+        };
+    }
+    lines
+}
+
+/// Writes the addresses in `counts` (executions per ROM address) that ran
+/// at all, most executed first, with their share of the total and their
+/// instruction.
+pub fn write_profile(rom: &[[u8; 2]], counts: &[u64], out: &mut impl Write) -> std::io::Result<()> {
+    let mut hot: Vec<(usize, u64)> = counts.iter().copied().enumerate().filter(|&(_, n)| n > 0).collect();
+    hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let total: u64 = hot.iter().map(|&(_, n)| n).sum();
+    writeln!(out, "; {} instructions executed at {} addresses", total, hot.len())?;
+    for (addr, n) in hot {
+        let [opcode, d] = rom[addr];
+        let share = n as f64 * 100.0 / total as f64;
+        writeln!(out, "{:12} {:6.2}%  {:04x}  {:02x}{:02x}  {}", n, share, addr, opcode, d, disassemble(opcode, d))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::Crop;
+use gigatron_example::error::GigatronError;
+use gigatron_example::{Gigatron, SCREEN_WIDTH};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
+
+/// Logs the CPU stores that land on the visible screen (--trace-draws),
+/// one line per pixel drawn:
+///
+/// ```text
+/// # cycle addr x y color
+/// 9259721 $0823 35 0 $3f
+/// ```
+///
+/// Where a pixel is comes from the videoTable at the time of the store, so
+/// a byte on a page two scanlines show is logged for both, and one no
+/// scanline shows isn't logged at all.
+pub struct DrawTrace {
+    writer: BufWriter<File>,
+    stores: Rc<RefCell<Vec<(u16, u8)>>>, // Filled by the write watch, drained every step
+    region: Crop,
+}
+
+impl DrawTrace {
+    /// Starts logging the stores into `region` of `gt`'s screen to
+    /// `filename`.
+    pub fn create(filename: &str, gt: &mut Gigatron, region: Crop) -> Result<Self, GigatronError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        writeln!(writer, "# cycle addr x y color")?;
+        let stores: Rc<RefCell<Vec<(u16, u8)>>> = Rc::default();
+        let watched = stores.clone();
+        gt.add_write_watch_range(0x0800..=0x7fff, Box::new(move |addr, value| watched.borrow_mut().push((addr, value))));
+        Ok(DrawTrace { writer, stores, region })
+    }
+
+    /// Writes out the stores of the cycle `gt` just ran.
+    pub fn record(&mut self, gt: &Gigatron) -> Result<(), GigatronError> {
+        if self.stores.borrow().is_empty() {
+            return Ok(());
+        }
+        let cycle = gt.cycles() - 1;
+        let lines = gt.scanlines();
+        for (addr, value) in self.stores.borrow_mut().drain(..) {
+            let (page, offset) = ((addr >> 8) as u8, addr as u8);
+            for (y, &(_, dx)) in lines.iter().enumerate().filter(|(_, line)| line.0 == page) {
+                let x = offset.wrapping_sub(dx) as usize;
+                if x < SCREEN_WIDTH && self.region.contains(x, y) {
+                    writeln!(self.writer, "{} ${:04x} {} {} ${:02x}", cycle, addr, x, y, value & 0x3f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes out what is still buffered.
+    pub fn finish(&mut self) -> Result<(), GigatronError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
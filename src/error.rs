@@ -0,0 +1,45 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GigatronError {
+    #[error("ROM image must be exactly {expected} bytes, got {got}")]
+    RomSize { expected: usize, got: usize },
+    #[error("ROM image is larger than the {max} bytes (64K words) the 16-bit PC can address, got {got}")]
+    RomTooLarge { max: usize, got: usize },
+    #[error("ROM image has an odd number of bytes, expected whole 16-bit words")]
+    RomOdd,
+    #[error("ROM image is gzip-compressed, but gzip support (the `gzip` feature) isn't built in")]
+    RomCompressed,
+    #[error("ROM image is blank (all bytes {byte:#04x}), not a ROM")]
+    RomBlank { byte: u8 },
+    #[error("ROM's reset code loops forever at {addr:#06x} without producing video")]
+    RomStuck { addr: u16 },
+    #[error("RAM image must be exactly {expected} bytes, got {got}")]
+    RamSize { expected: usize, got: usize },
+    #[error("Not a Gigatron state file, or a truncated one")]
+    State,
+    #[error("State file is layout version {found}, but this build reads version {expected}")]
+    SaveStateVersion { found: u8, expected: u8 },
+    #[error("GT1 file is truncated")]
+    Gt1Truncated,
+    #[error("GT1 segment at {addr:#06x} crosses a page boundary")]
+    Gt1Segment { addr: u16 },
+    #[error("Stopped at the limit of {cycles} cycles, PC={pc:#06x}")]
+    CycleLimit { cycles: u64, pc: u16 },
+    #[error("Malformed screen recording at frame {frame}")]
+    Video { frame: u64 },
+    #[error("Malformed input log at line {line}")]
+    InputLog { line: usize },
+    #[error("Malformed input script at line {line}, expected `at <cycle> press <buttons> [for <n> frames|cycles]` or `at <cycle> type <text>`")]
+    InputScript { line: usize },
+    #[error("Malformed patch at line {line}, expected `address: word` in hex")]
+    Patch { line: usize },
+    #[error("Patch address {addr:#x} at line {line} is outside the 64K-word ROM")]
+    PatchAddress { line: usize, addr: u32 },
+    #[error("Font file must be exactly {expected} bytes for this ROM, got {got}")]
+    FontSize { expected: usize, got: usize },
+    #[error("No known font table for romType {rom_type:#04x}")]
+    FontRom { rom_type: u8 },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,40 @@
+/// Control register of the RAM & IO expander.
+///
+/// The expander decodes the otherwise useless `st` with the RAM on the bus
+/// (the `ctrl` instruction) and latches the 16-bit address it was given:
+/// bit 0 is SCLK, bits 2..5 are the active-low SPI selects /SS0../SS3,
+/// bits 6..7 the RAM bank seen at 0x8000-0xffff and bit 15 is MOSI.
+#[derive(Clone, Copy, Debug)]
+pub struct Expansion {
+    pub ctrl: u16,
+}
+
+impl Expansion {
+    /// All SPI devices deselected, bank 0.
+    pub fn new() -> Self {
+        Expansion { ctrl: 0x003c }
+    }
+
+    pub fn sclk(&self) -> bool {
+        self.ctrl & 0x0001 != 0
+    }
+
+    pub fn mosi(&self) -> bool {
+        self.ctrl & 0x8000 != 0
+    }
+
+    /// Whether SPI device `n` (0..=3) is selected, i.e. its /SS line is low.
+    pub fn selected(&self, n: usize) -> bool {
+        self.ctrl & (1 << (2 + n)) == 0
+    }
+
+    pub fn bank(&self) -> u8 {
+        (self.ctrl >> 6) as u8 & 3
+    }
+}
+
+impl Default for Expansion {
+    fn default() -> Self {
+        Expansion::new()
+    }
+}
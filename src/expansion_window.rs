@@ -0,0 +1,69 @@
+use gigatron_example::expansion::Expansion;
+use gigatron_example::font;
+use minifb::{Window, WindowOptions};
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 112;
+const SCALE: usize = 3; // Window pixels per font pixel
+
+const LIT: u32 = 0x00FF_3030;
+const UNLIT: u32 = 0x0040_0C0C;
+const HIGH: u32 = 0x00FF_FFFF;
+const LOW: u32 = 0x0050_5050;
+
+/// Secondary window with the blinkenLights and the expander lines, so their
+/// state can be watched without covering the video.
+pub struct ExpansionWindow {
+    window: Window,
+    buffer: Vec<u32>,
+}
+
+impl ExpansionWindow {
+    pub fn new() -> Result<Self, minifb::Error> {
+        let window = Window::new("Gigatron expansion", WIDTH, HEIGHT, WindowOptions::default())?;
+        Ok(ExpansionWindow { window, buffer: vec![0; WIDTH * HEIGHT] })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    /// Redraws the panel:
+    ///
+    /// ```text
+    /// LEDS  [] [] [] []
+    /// BANK 1
+    /// SCLK  MOSI
+    /// SS0 SS1 SS2 SS3
+    /// ```
+    ///
+    /// Lines at a high level and selected devices are drawn bright.
+    pub fn draw(&mut self, xout: u8, expansion: Expansion) {
+        self.buffer.fill(0);
+        self.text("LEDS", 8, 8, HIGH);
+        for i in 0..4 {
+            let color = if xout & (1 << i) != 0 { LIT } else { UNLIT };
+            self.rect(68 + i * 30, 4, 22, 22, color);
+        }
+        self.text(&format!("BANK {}", expansion.bank()), 8, 36, HIGH);
+        self.text("SCLK", 8, 60, if expansion.sclk() { HIGH } else { LOW });
+        self.text("MOSI", 68, 60, if expansion.mosi() { HIGH } else { LOW });
+        for n in 0..4 {
+            let color = if expansion.selected(n) { HIGH } else { LOW };
+            self.text(&format!("SS{}", n), 8 + n * 48, 84, color);
+        }
+        self.window.update_with_buffer(&self.buffer, WIDTH, HEIGHT).unwrap();
+    }
+
+    fn rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: u32) {
+        for py in y..(y + h).min(HEIGHT) {
+            for px in x..(x + w).min(WIDTH) {
+                self.buffer[py * WIDTH + px] = color;
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str, x: usize, y: usize, color: u32) {
+        font::draw(text, 0, 0, |fx, fy| self.rect(x + fx * SCALE, y + fy * SCALE, SCALE, SCALE, color));
+    }
+}
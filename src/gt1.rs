@@ -0,0 +1,79 @@
+use crate::error::GigatronError;
+use std::fs::File;
+use std::io::Read;
+
+/// A GT1 program: RAM segments followed by an optional start address.
+///
+/// Each segment is `hi lo size data...` (a size of 0 means 256 bytes) and may
+/// not cross a page boundary. The list ends with a zero byte where the next
+/// segment's `hi` would be (except for the first segment, which may load into
+/// the zero page), followed by the start address; 0x0000 means "don't run".
+pub struct Gt1 {
+    pub segments: Vec<(u16, Vec<u8>)>,
+    pub start: Option<u16>,
+}
+
+impl Gt1 {
+    pub fn parse(data: &[u8]) -> Result<Self, GigatronError> {
+        let mut segments = Vec::new();
+        let mut pos = 0;
+        let byte = |pos: usize| data.get(pos).copied().ok_or(GigatronError::Gt1Truncated);
+        loop {
+            let hi = byte(pos)?;
+            if hi == 0 && !segments.is_empty() {
+                break;
+            }
+            let lo = byte(pos + 1)?;
+            let size = match byte(pos + 2)? {
+                0 => 256,
+                n => n as usize,
+            };
+            let addr = (hi as u16) << 8 | lo as u16;
+            if lo as usize + size > 256 {
+                return Err(GigatronError::Gt1Segment { addr });
+            }
+            let body = data.get(pos + 3..pos + 3 + size).ok_or(GigatronError::Gt1Truncated)?;
+            segments.push((addr, body.to_vec()));
+            pos += 3 + size;
+        }
+        let start = (byte(pos + 1)? as u16) << 8 | byte(pos + 2)? as u16;
+        Ok(Gt1 {
+            segments,
+            start: if start != 0 { Some(start) } else { None },
+        })
+    }
+
+    pub fn read(filename: &str) -> Result<Self, GigatronError> {
+        let mut file = File::open(filename)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        Gt1::parse(&buffer)
+    }
+}
+
+/// Zero-page locations of the vCPU registers a program is started through.
+///
+/// ROMv1 through ROMv6 and ROMvX0 share the layout vPC, vAC, vLR, vSP from
+/// 0x0016; other ROMs can move it with `--vpc-addr`.
+#[derive(Clone, Copy, Debug)]
+pub struct VcpuVectors {
+    pub vPC: u16,
+    pub vLR: u16,
+    pub vSP: u16,
+}
+
+impl VcpuVectors {
+    pub fn at(vPC: u16) -> Self {
+        VcpuVectors {
+            vPC,
+            vLR: vPC + 4,
+            vSP: vPC + 6,
+        }
+    }
+}
+
+impl Default for VcpuVectors {
+    fn default() -> Self {
+        VcpuVectors::at(crate::mem::VPC)
+    }
+}
@@ -0,0 +1,141 @@
+//! Screen recordings that store only what changed (`.gtv`).
+//!
+//! A recording is `GTV1`, then one record per frame of the 160x120 logical
+//! screen: the number of rows that differ from the previous frame, then for
+//! each of them its row number and its 160 pixels, one 6-bit color per
+//! byte. The frame before the first is all black. A screen that doesn't move
+//! costs one byte per frame, so long sessions stay small.
+//!
+//! The format has no frame rate: a recording holds one record per vSync of
+//! the ROM, or per tick of a fixed rate when resampled, and is played at
+//! whatever rate the player is given.
+
+use crate::error::GigatronError;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+
+const MAGIC: &[u8; 4] = b"GTV1";
+
+pub struct DeltaRecorder {
+    writer: BufWriter<File>,
+    previous: Vec<u8>,
+    fps: Option<u32>,   // Resample to this many frames per second of emulated time ...
+    start: Option<f64>, // ... counted from the first frame, in seconds ...
+    written: u64,       // ... with this many written since
+}
+
+impl DeltaRecorder {
+    pub fn create(filename: &str) -> Result<Self, GigatronError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        writer.write_all(MAGIC)?;
+        Ok(DeltaRecorder { writer, previous: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT], fps: None, start: None, written: 0 })
+    }
+
+    /// Writes `fps` frames per second of emulated time from now on, however
+    /// often the ROM completes one; see [`record_at`](Self::record_at).
+    pub fn set_fps(&mut self, fps: u32) {
+        self.fps = Some(fps);
+    }
+
+    /// Takes `frame`, completed `seconds` of emulated time after power-on.
+    /// Without a fixed rate every frame is written once. With one, output
+    /// frame `k` falls `k / fps` seconds after the first frame and shows the
+    /// first frame completed at or after that time: a frame is written once
+    /// for every tick it is the first for, so it is repeated when the ROM
+    /// runs slower than the rate and skipped when it is faster and another
+    /// frame completes before the next tick.
+    pub fn record_at(&mut self, frame: &[u8], seconds: f64) -> Result<(), GigatronError> {
+        let Some(fps) = self.fps else {
+            return self.record(frame);
+        };
+        let start = *self.start.get_or_insert(seconds);
+        // Ticks 0..=n are due, n the last one at or before `seconds`
+        let due = ((seconds - start) * fps as f64).floor() as u64 + 1;
+        while self.written < due {
+            self.record(frame)?;
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    /// Appends `frame`, as [`Gigatron::frame`](crate::Gigatron::frame)
+    /// returns it.
+    pub fn record(&mut self, frame: &[u8]) -> Result<(), GigatronError> {
+        let rows = frame.chunks_exact(SCREEN_WIDTH);
+        let changed: Vec<(usize, &[u8])> = rows
+            .zip(self.previous.chunks_exact(SCREEN_WIDTH))
+            .enumerate()
+            .filter(|(_, (row, before))| row != before)
+            .map(|(y, (row, _))| (y, row))
+            .collect();
+        self.writer.write_all(&[changed.len() as u8])?;
+        for (y, row) in changed {
+            self.writer.write_all(&[y as u8])?;
+            self.writer.write_all(row)?;
+        }
+        self.previous.copy_from_slice(frame);
+        Ok(())
+    }
+
+    /// Writes out what is still buffered.
+    pub fn finish(&mut self) -> Result<(), GigatronError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Plays a recording back one frame at a time.
+pub struct DeltaPlayer {
+    reader: BufReader<File>,
+    frame: Vec<u8>,
+    frames: u64, // Frames read so far
+}
+
+impl DeltaPlayer {
+    pub fn open(filename: &str) -> Result<Self, GigatronError> {
+        let mut reader = BufReader::new(File::open(filename)?);
+        let mut magic = [0u8; 4];
+        match reader.read_exact(&mut magic) {
+            Ok(()) if &magic == MAGIC => {}
+            Ok(()) => return Err(GigatronError::Video { frame: 0 }),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Err(GigatronError::Video { frame: 0 }),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(DeltaPlayer { reader, frame: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT], frames: 0 })
+    }
+
+    /// The next frame in the layout of [`Gigatron::frame`](crate::Gigatron::frame),
+    /// or None at the end of the recording.
+    pub fn next_frame(&mut self) -> Result<Option<&[u8]>, GigatronError> {
+        let mut count = [0u8];
+        if self.reader.read(&mut count)? == 0 {
+            return Ok(None);
+        }
+        if count[0] as usize > SCREEN_HEIGHT {
+            return Err(GigatronError::Video { frame: self.frames });
+        }
+        for _ in 0..count[0] {
+            let mut y = [0u8];
+            self.read(&mut y)?;
+            let y = y[0] as usize;
+            if y >= SCREEN_HEIGHT {
+                return Err(GigatronError::Video { frame: self.frames });
+            }
+            let row = y * SCREEN_WIDTH;
+            let mut pixels = [0u8; SCREEN_WIDTH];
+            self.read(&mut pixels)?;
+            self.frame[row..row + SCREEN_WIDTH].copy_from_slice(&pixels);
+        }
+        self.frames += 1;
+        Ok(Some(&self.frame))
+    }
+
+    /// Fills `buf`, where the end of the file means a truncated frame.
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), GigatronError> {
+        match self.reader.read_exact(buf) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(GigatronError::Video { frame: self.frames }),
+            result => Ok(result?),
+        }
+    }
+}
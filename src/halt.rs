@@ -0,0 +1,99 @@
+use gigatron_example::{decode, disasm, Gigatron, FRAME_CYCLES};
+
+/// Widest PC range, in words, that still counts as a loop halting.
+const SPAN: u16 = 4;
+
+/// Cycles in the range before RAM is looked at, so that ordinary code
+/// passing through costs nothing ...
+const SETTLE: u64 = 64;
+/// ... and then without a store changing RAM before it counts as halted:
+/// ten frames, long enough that no video loop keeps this still.
+const HALT_CYCLES: u64 = 10 * FRAME_CYCLES;
+
+/// A loop the ROM can't leave, found by [`HaltWatch`].
+pub struct Halt {
+    pub start: u16, // ROM range the PC stays in
+    pub end: u16,
+    pub deliberate: bool, // An unconditional jump to a fixed address in the range keeps it there
+    pub why: String,
+}
+
+impl Halt {
+    pub fn describe(&self) -> String {
+        let at = if self.start == self.end { format!("${:04x}", self.start) } else { format!("${:04x}-${:04x}", self.start, self.end) };
+        if self.deliberate {
+            format!("ROM halted at PC {} ({})", at, self.why)
+        } else {
+            format!("ROM stuck at PC {}, probably crashed ({})", at, self.why)
+        }
+    }
+}
+
+/// Watches for the ROM settling into a tight loop that changes nothing:
+/// PC within SPAN words and RAM the same for HALT_CYCLES. Unlike
+/// --max-cycles, which stops a run that goes on too long whatever it is
+/// doing, this tells a ROM that stopped on purpose, the way a program ends
+/// with `bra` to itself, from one that lost its way.
+pub struct HaltWatch {
+    start: u16,
+    end: u16,
+    since: u64,                        // Cycle PC entered the range
+    snapshot: Option<(u64, Box<[u8]>)>, // RAM once it settled, and when
+    reported: bool,                    // For this range
+}
+
+impl HaltWatch {
+    pub fn new() -> Self {
+        HaltWatch { start: 0, end: 0, since: 0, snapshot: None, reported: false }
+    }
+
+    /// Looks at the cycle `gt` is about to run; returns the halt once, when
+    /// it is first seen.
+    pub fn check(&mut self, gt: &Gigatron) -> Option<Halt> {
+        let (pc, t) = (gt.S.PC, gt.cycles());
+        let (start, end) = (self.start.min(pc), self.end.max(pc));
+        if end - start >= SPAN {
+            *self = HaltWatch { start: pc, end: pc, since: t, snapshot: None, reported: false };
+            return None;
+        }
+        (self.start, self.end) = (start, end);
+        if self.reported || t - self.since < SETTLE {
+            return None;
+        }
+        match &self.snapshot {
+            None => self.snapshot = Some((t, gt.RAM.as_slice().into())),
+            Some((taken, _)) if t - taken < HALT_CYCLES => {}
+            Some((_, ram)) if **ram != gt.RAM[..] => self.snapshot = Some((t, gt.RAM.as_slice().into())),
+            Some(_) => {
+                self.reported = true;
+                return Some(classify(gt, self.start, self.end));
+            }
+        }
+        None
+    }
+}
+
+/// Deliberate when some word in the range is an unconditional jump with
+/// its target in the instruction, landing back in the range; a jump on a
+/// condition or to a computed address that happens to come back looks
+/// like a crash, as does no jump at all.
+fn classify(gt: &Gigatron, start: u16, end: u16) -> Halt {
+    let jump = (start..=end).find_map(|addr| {
+        let [opcode, d] = gt.rom_word(addr);
+        let insn = decode(opcode);
+        if !insn.is_jump || insn.bus != 0 || !matches!(insn.mode, 0 | 7) {
+            return None;
+        }
+        // bra $dd stays in the page; jmp y,$dd goes where Y says
+        let page = if insn.mode == 7 { addr & 0xff00 } else { (gt.S.Y as u16) << 8 };
+        let target = page | d as u16;
+        (start..=end).contains(&target).then(|| (addr, disasm::disassemble(opcode, d)))
+    });
+    match jump {
+        Some((addr, text)) => Halt { start, end, deliberate: true, why: format!("{} at ${:04x} loops on itself", text, addr) },
+        None => {
+            let [opcode, d] = gt.rom_word(start);
+            Halt { start, end, deliberate: false, why: format!("no jump to a fixed address keeps it there; ${:04x} is {}", start, disasm::disassemble(opcode, d)) }
+        }
+    }
+}
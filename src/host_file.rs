@@ -0,0 +1,81 @@
+//! A file on the host that vCPU programs can read and write through two RAM
+//! bytes. This is not Gigatron hardware; it exists so test programs can
+//! leave results on disk, and is only mapped with `--host-file`.
+//!
+//! The port sits at 0x7ffe (data) and 0x7fff (command), past the visible
+//! pixels of the last screen page. Storing a command byte runs it at once,
+//! and the command byte then reads back the status: 0x00 when it succeeded,
+//! 0xff on end of file or a host error.
+//!
+//! | Command | Effect                                                   |
+//! |---------|----------------------------------------------------------|
+//! | 0x01    | Append the data byte to the file                          |
+//! | 0x02    | Read the next byte of the file into the data byte         |
+//! | 0x03    | Rewind, so the next read starts at the beginning again    |
+//!
+//! Any other value is left alone, so that clearing the port doesn't count
+//! as a command.
+
+use log::warn;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+pub const DATA: u16 = 0x7ffe;
+pub const COMMAND: u16 = 0x7fff;
+
+const WRITE: u8 = 0x01;
+const READ: u8 = 0x02;
+const REWIND: u8 = 0x03;
+
+const OK: u8 = 0x00;
+const FAILED: u8 = 0xff;
+
+pub struct HostFile {
+    file: File,
+    read_pos: u64, // Reads and appends keep separate positions
+}
+
+impl HostFile {
+    /// Opens `filename` for reading and appending, creating it if needed.
+    pub fn open(filename: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).append(true).create(true).open(filename)?;
+        Ok(HostFile { file, read_pos: 0 })
+    }
+
+    /// Runs the command just stored at COMMAND, updating the port in `ram`.
+    pub fn command(&mut self, ram: &mut [u8]) {
+        let result = match ram[COMMAND as usize] {
+            WRITE => self.file.write_all(&[ram[DATA as usize]]).map(|()| true),
+            READ => self.read().map(|b| {
+                if let Some(b) = b {
+                    ram[DATA as usize] = b;
+                }
+                b.is_some()
+            }),
+            REWIND => {
+                self.read_pos = 0;
+                Ok(true)
+            }
+            _ => return,
+        };
+        let status = match result {
+            Ok(true) => OK,
+            Ok(false) => FAILED, // End of file
+            Err(e) => {
+                warn!("Host file: {}", e);
+                FAILED
+            }
+        };
+        ram[COMMAND as usize] = status;
+    }
+
+    fn read(&mut self) -> io::Result<Option<u8>> {
+        self.file.seek(SeekFrom::Start(self.read_pos))?;
+        let mut b = [0u8];
+        if self.file.read(&mut b)? == 0 {
+            return Ok(None);
+        }
+        self.read_pos += 1;
+        Ok(Some(b[0]))
+    }
+}
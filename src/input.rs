@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+/// Frames a typed character stays on the port, enough for the ROM to sample
+/// it before the port goes back to idle or the controller.
+const KEY_FRAMES: u32 = 3;
+
+/// Controller bits, one per button. The controller pulls a held button's
+/// bit low, so masks throughout are active-low: 0xff with nothing held.
+pub const BUTTON_RIGHT: u8 = 0x01;
+pub const BUTTON_LEFT: u8 = 0x02;
+pub const BUTTON_DOWN: u8 = 0x04;
+pub const BUTTON_UP: u8 = 0x08;
+pub const BUTTON_START: u8 = 0x10;
+pub const BUTTON_SELECT: u8 = 0x20;
+pub const BUTTON_B: u8 = 0x40;
+pub const BUTTON_A: u8 = 0x80;
+
+/// Level on IN for a held button.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Polarity {
+    #[default]
+    ActiveLow, // The original controller: idle reads 0xff
+    ActiveHigh, // Inverted wiring: idle reads 0x00
+}
+
+impl Polarity {
+    /// The byte IN shows for an active-low controller `mask`.
+    pub fn present(self, mask: u8) -> u8 {
+        match self {
+            Polarity::ActiveLow => mask,
+            Polarity::ActiveHigh => !mask,
+        }
+    }
+}
+
+impl std::str::FromStr for Polarity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "low" => Ok(Polarity::ActiveLow),
+            "high" => Ok(Polarity::ActiveHigh),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How an analog stick becomes the controller's direction buttons. The
+/// position is -1.0 to 1.0 on each axis, right and down positive. Inside
+/// `dead_zone` of the center nothing is held, so a stick that doesn't
+/// quite center doesn't drift; beyond it the distance is scaled to start
+/// from 0 again, and each axis holds its direction once it passes
+/// `threshold` of the way from there to the edge. Diagonals hold two.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StickMap {
+    pub dead_zone: f32,
+    pub threshold: f32,
+}
+
+impl StickMap {
+    pub const DEFAULT: StickMap = StickMap { dead_zone: 0.2, threshold: 0.5 };
+
+    /// The bits of the directions held at (`x`, `y`), as in BUTTON_*.
+    pub fn buttons(&self, x: f32, y: f32) -> u8 {
+        let distance = x.hypot(y);
+        if distance <= self.dead_zone || !distance.is_finite() {
+            return 0;
+        }
+        let scale = ((distance - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0) / distance;
+        let (x, y) = (x * scale, y * scale);
+        let mut buttons = 0;
+        if x >= self.threshold {
+            buttons |= BUTTON_RIGHT;
+        } else if x <= -self.threshold {
+            buttons |= BUTTON_LEFT;
+        }
+        if y >= self.threshold {
+            buttons |= BUTTON_DOWN;
+        } else if y <= -self.threshold {
+            buttons |= BUTTON_UP;
+        }
+        buttons
+    }
+}
+
+impl Default for StickMap {
+    fn default() -> Self {
+        StickMap::DEFAULT
+    }
+}
+
+/// `DEAD_ZONE,THRESHOLD`, e.g. `0.2,0.5`.
+impl std::str::FromStr for StickMap {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (dead_zone, threshold) = s.split_once(',').ok_or(())?;
+        let dead_zone: f32 = dead_zone.trim().parse().map_err(|_| ())?;
+        let threshold: f32 = threshold.trim().parse().map_err(|_| ())?;
+        let valid = (0.0..1.0).contains(&dead_zone) && threshold > 0.0 && threshold <= 1.0;
+        if !valid {
+            return Err(());
+        }
+        Ok(StickMap { dead_zone, threshold })
+    }
+}
+
+/// The code to send for a typed character. The ROMs only know ASCII, so
+/// those go as they are; accented Latin letters lose their accents and
+/// typographic punctuation becomes its plain counterpart, as a user on an
+/// international layout or pasting text would mean. Anything else has no
+/// code and gets None.
+pub fn key_code(c: char) -> Option<u8> {
+    if c.is_ascii() {
+        return Some(c as u8);
+    }
+    let plain = match c {
+        'À'..='Å' => 'A',
+        'Ç' => 'C',
+        'È'..='Ë' => 'E',
+        'Ì'..='Ï' => 'I',
+        'Ð' => 'D',
+        'Ñ' => 'N',
+        'Ò'..='Ö' | 'Ø' => 'O',
+        'Ù'..='Ü' => 'U',
+        'Ý' => 'Y',
+        'à'..='å' => 'a',
+        'ç' => 'c',
+        'è'..='ë' => 'e',
+        'ì'..='ï' => 'i',
+        'ð' => 'd',
+        'ñ' => 'n',
+        'ò'..='ö' | 'ø' => 'o',
+        'ù'..='ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        '\u{a0}' => ' ',                        // No-break space
+        '‘' | '’' | '‚' | '′' | '´' => '\'',
+        '“' | '”' | '„' | '″' | '«' | '»' => '"',
+        '‐' | '‑' | '–' | '—' | '−' => '-',
+        '×' => '*',
+        '÷' => '/',
+        _ => return None,
+    };
+    Some(plain as u8)
+}
+
+/// The input port shared by the game controller and the keyboard.
+///
+/// On hardware only one device drives IN at a time, so both can't be poked
+/// into RAM independently: a typed character takes over the port for a few
+/// frames, after which the controller state (0xff when idle, unless the
+/// polarity is inverted) shows again.
+/// Characters typed faster than that wait their turn, each followed by a
+/// frame of the controller so that the ROM sees a repeated one as a new key.
+/// The ROM samples IN during vertical blank, so a new value is latched once
+/// per frame at the vSync edge rather than mid-sample.
+pub struct InputPort {
+    joystick: u8, // Active-low buttons held on the controller
+    pub polarity: Polarity, // How the controller shows on IN
+    key: Option<u8>, // On the port now
+    key_frames: u32,
+    typed: VecDeque<(u64, u8)>, // Waiting for the port, with the cycle each was typed at
+}
+
+impl InputPort {
+    pub fn new() -> Self {
+        InputPort {
+            joystick: 0xff,
+            polarity: Polarity::ActiveLow,
+            key: None,
+            key_frames: 0,
+            typed: VecDeque::new(),
+        }
+    }
+
+    /// Sets the buttons held, as an active-low mask whatever the polarity.
+    pub fn joystick(&mut self, mask: u8) {
+        self.joystick = mask;
+    }
+
+    /// Queues a typed character, `t` being the cycle it was typed at.
+    pub fn key(&mut self, code: u8, t: u64) {
+        self.typed.push_back((t, code));
+    }
+
+    /// The characters waiting for the port, oldest first, with the cycle
+    /// each was typed at: for a recorder, or to see how far typing has got
+    /// ahead of the ROM.
+    pub fn queued(&self) -> impl Iterator<Item = (u64, u8)> + '_ {
+        self.typed.iter().copied()
+    }
+
+    /// Nothing typed is on the port or waiting for it, so a character
+    /// typed now shows at the next frame.
+    pub fn is_idle(&self) -> bool {
+        self.key.is_none() && self.typed.is_empty()
+    }
+
+    /// Value IN presents for the coming frame.
+    pub fn latch(&mut self) -> u8 {
+        match self.key {
+            Some(code) if self.key_frames > 0 => {
+                self.key_frames -= 1;
+                code
+            }
+            Some(_) => {
+                self.key = None; // The gap before the next character
+                self.polarity.present(self.joystick)
+            }
+            None => match self.typed.pop_front() {
+                Some((_, code)) => {
+                    self.key = Some(code);
+                    self.key_frames = KEY_FRAMES - 1;
+                    code
+                }
+                None => self.polarity.present(self.joystick),
+            },
+        }
+    }
+}
+
+impl Default for InputPort {
+    fn default() -> Self {
+        InputPort::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What IN shows with the controller idle and with Right held.
+    #[test]
+    fn joystick_polarity() {
+        for (polarity, idle, right) in [(Polarity::ActiveLow, 0xff, !BUTTON_RIGHT), (Polarity::ActiveHigh, 0x00, BUTTON_RIGHT)] {
+            let mut port = InputPort::new();
+            port.polarity = polarity;
+            assert_eq!(port.latch(), idle, "idle, {:?}", polarity);
+            port.joystick(!BUTTON_RIGHT);
+            assert_eq!(port.latch(), right, "Right held, {:?}", polarity);
+        }
+    }
+
+    /// Typed characters and the code the ROM gets, None where there is none.
+    #[test]
+    fn key_codes() {
+        assert_eq!(key_code('a'), Some(b'a'));
+        assert_eq!(key_code('\n'), Some(b'\n'));
+        assert_eq!(key_code('é'), Some(b'e'));
+        assert_eq!(key_code('Ü'), Some(b'U'));
+        assert_eq!(key_code('’'), Some(b'\''));
+        assert_eq!(key_code('—'), Some(b'-'));
+        assert_eq!(key_code('€'), None);
+    }
+
+    /// Stick positions and the directions the default map holds for them.
+    #[test]
+    fn stick_map() {
+        let buttons = |x, y| StickMap::DEFAULT.buttons(x, y);
+        assert_eq!(buttons(0.0, 0.0), 0);
+        assert_eq!(buttons(0.15, -0.1), 0, "drift, inside the dead zone");
+        assert_eq!(buttons(0.55, 0.0), 0, "past the dead zone, not yet the threshold");
+        assert_eq!(buttons(0.65, 0.0), BUTTON_RIGHT);
+        assert_eq!(buttons(-1.0, 0.1), BUTTON_LEFT);
+        assert_eq!(buttons(0.0, -0.9), BUTTON_UP);
+        assert_eq!(buttons(0.7, 0.7), BUTTON_RIGHT | BUTTON_DOWN);
+    }
+}
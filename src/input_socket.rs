@@ -0,0 +1,122 @@
+use gigatron_example::error::GigatronError;
+use gigatron_example::input::StickMap;
+use gigatron_example::script;
+use log::{info, warn};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Controller state sent by another process (--input-socket): a bot, a
+/// bridge to a real controller, a network play proxy. It connects to a TCP
+/// port (`host:port`, one client at a time) or writes to a named pipe (any
+/// other value, a path), sending one line per change:
+///
+/// ```text
+/// right+a            buttons held, as in input scripts
+/// none               nothing held
+/// 0xfe               or the controller byte itself, active-low
+/// stick 0.7,-0.1 a   an analog stick's position, -1 to 1 with right and
+///                    down positive, and optionally buttons held with it
+/// ```
+///
+/// A stick position holds the directions that `--stick-map` makes of it.
+/// Each state holds until the next line. A reader thread waits for the
+/// lines, so the emulator only ever looks at what has arrived. A TCP client
+/// that goes away releases every button; a pipe keeps its state from one
+/// writer to the next, so `echo right > pipe` works.
+pub struct InputSocket {
+    masks: Receiver<u8>,
+}
+
+impl InputSocket {
+    pub fn open(addr: &str, stick: StickMap) -> Result<Self, GigatronError> {
+        let (tx, masks) = mpsc::channel();
+        if is_tcp(addr) {
+            let listener = TcpListener::bind(addr)?;
+            info!("Waiting for controller input on {}", listener.local_addr()?);
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    info!("Controller input from {}", stream.peer_addr().map_or("?".to_string(), |a| a.to_string()));
+                    if !forward(stream, &tx, stick) || tx.send(0xff).is_err() {
+                        return;
+                    }
+                }
+            });
+        } else {
+            let path = Path::new(addr).to_path_buf();
+            std::fs::metadata(&path)?; // Fail now if it isn't there at all
+            std::thread::spawn(move || {
+                // Opening a pipe waits for a writer; reopen it for the next
+                // one, but read a plain file only once
+                while let Ok(file) = File::open(&path) {
+                    if !forward(file, &tx, stick) || !is_pipe(&path) {
+                        return;
+                    }
+                }
+            });
+        }
+        Ok(InputSocket { masks })
+    }
+
+    /// The next controller byte received, if any. One per call, so states
+    /// that arrive together still show one after the other.
+    pub fn poll(&self) -> Option<u8> {
+        self.masks.try_recv().ok()
+    }
+}
+
+/// Sends a mask per line until the end of `input`. Returns false once the
+/// emulator has stopped listening.
+fn forward(input: impl Read, tx: &Sender<u8>, stick: StickMap) -> bool {
+    for line in BufReader::new(input).lines() {
+        let Ok(line) = line else { break };
+        match parse(line.trim(), stick) {
+            Some(mask) => {
+                if tx.send(mask).is_err() {
+                    return false;
+                }
+            }
+            None => warn!("Ignoring controller input {:?}", line),
+        }
+    }
+    true
+}
+
+/// A line's active-low controller byte.
+fn parse(line: &str, stick: StickMap) -> Option<u8> {
+    if let Some(rest) = line.strip_prefix("stick ") {
+        let (position, buttons) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+        let (x, y) = position.split_once(',')?;
+        let (x, y): (f32, f32) = (x.trim().parse().ok()?, y.trim().parse().ok()?);
+        let held = match buttons.trim() {
+            "" => 0,
+            buttons => script::parse_buttons(buttons)?,
+        };
+        return Some(!(stick.buttons(x, y) | held));
+    }
+    let hex = line.strip_prefix("0x").or_else(|| line.strip_prefix('$'));
+    match (hex, line) {
+        (Some(hex), _) => u8::from_str_radix(hex, 16).ok(),
+        (None, "none" | "") => Some(0xff),
+        (None, _) if line.bytes().all(|b| b.is_ascii_digit()) => line.parse().ok(),
+        (None, _) => script::parse_buttons(line).map(|held| !held),
+    }
+}
+
+/// `host:port`, as opposed to a path.
+fn is_tcp(addr: &str) -> bool {
+    !addr.contains('/') && addr.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+}
+
+#[cfg(unix)]
+fn is_pipe(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_pipe(_path: &Path) -> bool {
+    false
+}
@@ -0,0 +1,105 @@
+use gigatron_example::input::{BUTTON_A, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+use gigatron_example::{menu, Gigatron};
+use log::{error, info};
+
+/// Frames a button is held for, long enough for the menu to see it, and
+/// then released before the cursor is read again.
+const PRESS_FRAMES: u64 = 3;
+const SETTLE_FRAMES: u64 = 5;
+
+/// Give up when the menu hasn't shown by then (10 seconds) ...
+const MENU_TIMEOUT: u64 = 600;
+/// ... or when the cursor hasn't reached the program after this many presses.
+const MAX_PRESSES: u32 = 24;
+
+enum Step {
+    Waiting,                           // For the menu to show
+    Pressing { held: u8, until: u64 }, // A button, its bit as in input::BUTTON_*
+    Settling { until: u64 },           // With nothing held
+    Done,
+}
+
+/// Starts a program from the ROM's boot menu (--launch-game): waits for
+/// the menu, then presses the directions that bring the cursor to the
+/// program, reading where it is off the screen after every press, and
+/// finally A.
+pub struct Launcher {
+    name: String,
+    step: Step,
+    presses: u32,
+}
+
+impl Launcher {
+    /// Fails with the names on offer when no known menu has `name`.
+    pub fn new(name: &str) -> Result<Self, String> {
+        if !menu::all_programs().any(|program| program.eq_ignore_ascii_case(name)) {
+            let offered: Vec<&str> = menu::all_programs().collect();
+            return Err(format!("No menu has a program called {:?}; known ones are {}", name, offered.join(", ")));
+        }
+        Ok(Launcher { name: name.to_string(), step: Step::Waiting, presses: 0 })
+    }
+
+    /// Advances at a vSync edge and returns the controller byte to hold
+    /// until the next one, active-low.
+    pub fn vsync(&mut self, gt: &Gigatron) -> u8 {
+        let frame = gt.frames();
+        match self.step {
+            Step::Waiting if frame > MENU_TIMEOUT => self.fail("the menu didn't show"),
+            Step::Waiting | Step::Settling { .. } if !self.settled(frame) => {}
+            Step::Waiting | Step::Settling { .. } => self.next_press(gt),
+            Step::Pressing { until, held } if frame >= until => {
+                self.step = if held == BUTTON_A { Step::Done } else { Step::Settling { until: frame + SETTLE_FRAMES } };
+            }
+            Step::Pressing { .. } | Step::Done => {}
+        }
+        match self.step {
+            Step::Pressing { held, .. } => !held,
+            _ => 0xff,
+        }
+    }
+
+    fn settled(&self, frame: u64) -> bool {
+        match self.step {
+            Step::Settling { until } => frame >= until,
+            _ => true,
+        }
+    }
+
+    /// Reads the cursor and picks the next button, A once it is there.
+    fn next_press(&mut self, gt: &Gigatron) {
+        let Some(menu) = menu::for_rom(gt.rom_type()) else {
+            return self.fail(&format!("there is no menu known for romType ${:02x}", gt.rom_type()));
+        };
+        let Some(target) = menu.find(&self.name) else {
+            return self.fail("this ROM's menu doesn't have it");
+        };
+        let Some(at) = menu.selected(&gt.frame()) else {
+            if matches!(self.step, Step::Settling { .. }) {
+                self.fail("the menu went away");
+            }
+            return;
+        };
+        if self.presses >= MAX_PRESSES {
+            return self.fail("the cursor doesn't get there");
+        }
+        let (held, button) = if at == target {
+            (BUTTON_A, "A")
+        } else if at.0 < target.0 {
+            (BUTTON_RIGHT, "Right")
+        } else if at.0 > target.0 {
+            (BUTTON_LEFT, "Left")
+        } else if at.1 < target.1 {
+            (BUTTON_DOWN, "Down")
+        } else {
+            (BUTTON_UP, "Up")
+        };
+        info!("Menu: on {}, pressing {} at frame {}", menu.columns[at.0][at.1], button, gt.frames());
+        self.presses += 1;
+        self.step = Step::Pressing { held, until: gt.frames() + PRESS_FRAMES };
+    }
+
+    fn fail(&mut self, why: &str) {
+        error!("Can't launch {}: {}", self.name, why);
+        self.step = Step::Done;
+    }
+}
@@ -0,0 +1,1627 @@
+//! Gigatron TTL emulator core: the CPU, RAM, ROM and input port, without any
+//! window. Frontends drive it with [`Gigatron::step`] and turn RAM into
+//! pixels; see `examples/headless_boot.rs` for the smallest one.
+
+// Names follow the hardware and the reference gtemu.c simulator (PC, AC, ALU, ...).
+#![allow(non_snake_case, clippy::upper_case_acronyms)]
+
+pub mod builder;
+pub mod clock;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod expansion;
+pub mod font;
+pub mod gt1;
+pub mod gtv;
+pub mod host_file;
+pub mod input;
+pub mod mem;
+pub mod menu;
+pub mod patch;
+pub mod png;
+pub mod ppm;
+pub mod peripheral;
+pub mod replay;
+pub mod reset;
+pub mod script;
+pub mod selftest;
+pub mod state;
+pub mod sys;
+pub mod uart;
+
+use log::{debug, info, trace, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::ops::RangeInclusive;
+use std::time::{SystemTime, UNIX_EPOCH};
+use error::GigatronError;
+use expansion::Expansion;
+use gt1::{Gt1, VcpuVectors};
+use host_file::HostFile;
+use input::InputPort;
+use patch::Patch;
+use peripheral::Peripheral;
+use reset::Supervisor;
+use state::{CpuSnapshot, History, State};
+
+pub use builder::GigatronBuilder;
+
+/// The ROMv6 image, built in.
+pub const ROM6: &[u8; 131072] = include_bytes!("../ROMv6.rom");
+
+pub const CLOCK_HZ: f64 = 6_250_000.0; // One cpuCycle per 160ns
+
+const GT1_BOOT_FRAMES: u64 = 100; // The ROM is in its menu well before this
+
+// Indices into RAM of the locations used here
+const SCREEN_START: usize = mem::VIDEO_BASE as usize;
+const VIDEO_TABLE: usize = mem::VIDEO_TABLE as usize;
+const ROM_TYPE: usize = mem::ROM_TYPE as usize;
+const SYS_FN: usize = mem::SYS_FN as usize;
+const VIDEO_Y: usize = mem::VIDEO_Y as usize;
+const VIDEO_TOP: usize = mem::VIDEO_TOP as usize;
+
+const ROM_V5A: u8 = 0x40;
+const ROM_V6: u8 = 0x48;
+
+const JMP_SYS_FN: u8 = 0xe1; // jmp y,[sysFn], with which the vCPU enters it
+
+/// sysFn while the Loader sits in its input loop, by ROM version. It only
+/// reads the port from then on, so bytes sent before can get lost.
+const LOADER_SYS: [(u8, u16); 1] = [(ROM_V6, 0x5b8c)];
+
+/// Size of the logical screen, in pixels.
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 120;
+
+/// The whole raster the beam sweeps, blanking included: cycles per line and
+/// lines per frame.
+pub const RASTER_WIDTH: usize = 200;
+pub const RASTER_HEIGHT: usize = 521;
+
+/// Where the visible 160 cycles by 480 lines start in the raster, past the
+/// sync pulses and back porches.
+pub const VISIBLE_X: usize = 36;
+pub const VISIBLE_Y: usize = 35;
+
+/// Raster value for a cycle where OUT was sending a sync pulse, during which
+/// the monitor blanks and the color bits mean nothing.
+pub const BLANK: u8 = 0x40;
+
+/// Cycles in one frame, vSync to vSync.
+pub const FRAME_CYCLES: u64 = (RASTER_WIDTH * RASTER_HEIGHT) as u64;
+
+/// 8-bit RGB levels of one screen pixel.
+pub struct Color(pub u8, pub u8, pub u8);
+
+fn bright(v: u8) -> u8 {
+    match v {
+        0b0000_0011 => 0b1111_1111,
+        0b0000_0010 => 0b0011_1111,
+        0b0000_0001 => 0b0000_1111,
+        0b0000_0000 => 0b0000_0011,
+        _ => unreachable!(),
+    }
+}
+
+pub fn unpack_color(reg: u8) -> Color {
+    let reg = reg & 0b0011_1111;
+
+    let red: u8 = reg & 0b0000_0011;
+    let green: u8 = (reg >> 2) & 0b0000_0011;
+    let blue: u8 = (reg >> 4) & 0b0000_0011;
+
+    let red = bright(red);
+    let green = bright(green);
+    let blue = bright(blue);
+
+    Color(red, green, blue)
+}
+
+/// How the 2-bit color channels become 8-bit levels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Palette {
+    /// The original levels, 0x03/0x0f/0x3f/0xff: steps of 4x in brightness,
+    /// which keeps dark colors apart on a PC screen.
+    #[default]
+    Default,
+    /// Levels from the resistor DAC between OUT and the VGA connector; see
+    /// RESISTOR_LEVELS.
+    Resistor,
+}
+
+impl std::str::FromStr for Palette {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "default" => Ok(Palette::Default),
+            "resistor" => Ok(Palette::Resistor),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Palette {
+    /// [`unpack_color`], with this palette's levels.
+    pub fn color(self, reg: u8) -> Color {
+        match self {
+            Palette::Default => unpack_color(reg),
+            Palette::Resistor => {
+                let level = |shift: u8| RESISTOR_LEVELS[((reg >> shift) & 0b11) as usize];
+                Color(level(0), level(2), level(4))
+            }
+        }
+    }
+}
+
+/// Each color channel is two OUT bits, each driving the VGA line through its
+/// own resistor into the monitor's 75 ohm termination; the resistors weigh
+/// the high bit about twice the low one. The outputs swing between 0V and
+/// the same high level whatever the color, so the node sees one fixed
+/// network and its voltage is proportional to the conductance of the bits
+/// that are high:
+///
+/// ```text
+/// level = 255 * (b1 / R_HIGH + b0 / R_LOW) / (1 / R_HIGH + 1 / R_LOW)
+/// ```
+///
+/// scaled so both bits high is full white. Neither the termination nor the
+/// supply voltage changes the shape, only the ratio of the two resistors:
+/// with 470 and 1000 ohm that is 0, 82, 173, 255, close to the even steps
+/// an exact 2:1 ladder would give.
+const R_HIGH: f64 = 470.0; // Ohm, bit 1 (and 3, 5) of OUT
+const R_LOW: f64 = 1000.0; // Ohm, bit 0 (and 2, 4)
+
+const RESISTOR_LEVELS: [u8; 4] = [resistor_level(0), resistor_level(1), resistor_level(2), resistor_level(3)];
+
+const fn resistor_level(bits: u8) -> u8 {
+    let (high, low) = (1.0 / R_HIGH, 1.0 / R_LOW);
+    let on = (bits >> 1) as f64 * high + (bits & 1) as f64 * low;
+    (255.0 * on / (high + low) + 0.5) as u8
+}
+
+pub fn makeRGB(c: &Color) -> u32 {
+    let mut color: u32 = 0;
+    color |= (c.0 as u32) << 16;   // R
+    color |= (c.1 as u32) << 8;    // G
+    color |= c.2 as u32;           // B
+    color
+}
+
+/// What RAM holds at power-on.
+///
+/// A pattern only survives where the ROM doesn't write. ROMv6 has overwritten
+/// these by the time the menu shows: most of the zero page (0x00-0x55 and
+/// 0x80), the videoTable and pages 2-7 holding the system programs
+/// (0x0100-0x08ff, with a few gaps) and the visible 160 bytes of every screen
+/// row (0xXX00-0xXX9f for pages 0x08-0x7f). The 96 off-screen bytes of each row
+/// keep their power-on contents until a program uses them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RamFill {
+    Random,  // Garble from the seeded RNG, like a cold SRAM
+    /// Random, except that the visible screen bytes (0xXX00-0xXX9f of pages
+    /// 0x08-0x7f) and the videoTable start zeroed: the screen stays black
+    /// instead of showing noise until the ROM draws.
+    RandomBlankScreen,
+    Zero,
+    Ones,
+    Checker, // 0x00 and 0xff alternating by address
+}
+
+/// RAM fitted: the stock 32K, or 64K as with the common upgrade that
+/// wires up address line 15.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RamSize {
+    K32,
+    K64,
+}
+
+impl RamSize {
+    pub fn bytes(self) -> usize {
+        match self {
+            RamSize::K32 => 1 << 15,
+            RamSize::K64 => 1 << 16,
+        }
+    }
+}
+
+impl std::str::FromStr for RamSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s.to_ascii_lowercase().trim_end_matches('k') {
+            "32" => Ok(RamSize::K32),
+            "64" => Ok(RamSize::K64),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::str::FromStr for RamFill {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "random" => Ok(RamFill::Random),
+            "random-blank-screen" => Ok(RamFill::RandomBlankScreen),
+            "zero" => Ok(RamFill::Zero),
+            "ones" => Ok(RamFill::Ones),
+            "checker" => Ok(RamFill::Checker),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The fields of an instruction byte (IR), as the control unit splits them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodedInsn {
+    pub ins: u8,  // Bits 7-5: the operation, 6 for a store, 7 for a jump
+    pub mode: u8, // Bits 4-2: addressing mode, or the condition of a jump
+    pub bus: u8,  // Bits 1-0: what drives the data bus: D, RAM, AC or IN
+    pub is_write: bool,
+    pub is_jump: bool,
+}
+
+/// Splits instruction byte `ir` into its fields.
+pub fn decode(ir: u8) -> DecodedInsn {
+    let ins = ir >> 5;
+    DecodedInsn { ins, mode: (ir >> 2) & 7, bus: ir & 3, is_write: ins == 6, is_jump: ins == 7 }
+}
+
+#[derive(Debug)]
+enum Register {
+    AC,
+    X,
+    Y,
+    OUT,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuState {
+    pub PC: u16,
+    pub IR: u8,
+    pub D: u8,
+    pub AC: u8,
+    pub X: u8,
+    pub Y: u8,
+    pub OUT: u8,
+    pub undef: u8,
+}
+
+/// Edges seen during one cycle: the sync signals, and ROM state changes
+/// a frontend may want to act on.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Edges {
+    pub hSync: bool, // A new line starts
+    pub vSync: bool, // A new frame starts
+    pub loaderReady: bool, // The Loader started listening for a program, checked on vSync
+}
+
+/// How the ROM has the screen set up right now, as
+/// [`Gigatron::video_geometry`] derives it from the videoTable and videoTop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoGeometry {
+    pub width: usize,    // Pixels per row the kernel shows
+    pub height: usize,   // Rows shown, below the ones blanked at the top
+    pub top: usize,      // First row shown
+    pub stride: usize,   // Bytes from the first shown row to the next, 256 in the default layout
+    pub base: u16,       // Address of the first pixel of the first shown row
+    pub scroll_x: u8,    // X offset of that row
+    pub scroll_y: usize, // Rows the pages are rotated by from the default layout
+    pub linear: bool,    // Every row is on the page after the one above, with the same X offset
+}
+
+pub struct Gigatron {
+    ROM: [[u8; 2]; 1 << 16],
+    pub RAM: Vec<u8>, // 32K or 64K, see set_ram_size
+    IN: u8,
+    pub S: CpuState,
+    XOUT: u8, // Extended output: blinkenLights in bits 0..3, audio in bits 4..7
+    expansion: Expansion,
+    vgaX: i32, // Beam position: cycles since the last hSync
+    vgaY: i32, // ... and lines since the last vSync
+    raster: Vec<u8>, // OUT as the beam saw it, RASTER_WIDTH x RASTER_HEIGHT
+    t: u64, // Cycles since power-on
+    pub reset_hold: u64, // Cycles the MCP100 holds reset after power-on
+    supervisor: Supervisor,
+    pub port: InputPort,
+    frames: u64, // vSync edges since power-on
+    vblank_start: Option<u64>,  // Cycle of the last vSync, until the ROM draws again
+    vblank_cycles: Option<u64>, // Length of the last completed vertical blank
+    loader_listening: bool,
+    pub gt1: Option<Gt1>, // Program waiting for the ROM to boot
+    pub fast_load: bool,
+    pub rom_byteswap: bool, // ROM images store each word as [data, instruction]
+    pub vectors: VcpuVectors,
+    rng: Box<dyn RngCore>, // Source of power-on garble and bus noise
+    pub clean_boot: bool, // Zero registers (and RAM, unless ram_fill is set) instead of garbling
+    pub ram_fill: RamFill,
+    pub rtc_addr: Option<u16>, // Where the RTC is mapped into RAM, if at all
+    rtc_base: u64,             // Host UTC time at power-on, in seconds
+    pub host_file: Option<HostFile>, // Mapped at host_file::DATA, not hardware
+    peripherals: Vec<Box<dyn Peripheral>>,
+    write_watches: Vec<(RangeInclusive<u16>, WriteWatch)>,
+    read_watches: Vec<(RangeInclusive<u16>, ReadWatch)>,
+    pc_profile: Option<Vec<u64>>, // Executions per ROM address, once enabled
+    vcpu_code: Option<Vec<bool>>, // RAM bytes the vCPU has fetched, once enabled
+    history: Option<History>,     // Cycles step_back can undo, once enabled
+    pub trace_sys: bool, // Log every SYS function the vCPU calls
+    rom_type: u8,        // romType the ROM sets during boot, 0 if not found
+}
+
+/// Called with the RAM address (0x0000-0x7fff, or 0xffff with 64K) and the
+/// value of a CPU store.
+pub type WriteWatch = Box<dyn FnMut(u16, u8)>;
+
+/// Called with the RAM address of a CPU load, returning the value to put on
+/// the bus instead of the RAM contents.
+pub type ReadWatch = Box<dyn FnMut(u16) -> u8>;
+
+/// Every ROM's reset code sets the sync bits with a `ld $xx,out` within its
+/// first few words, which tells a byte-swapped image apart.
+const RESET_WORDS: usize = 4;
+const LD_OUT: u8 = 0x18;
+
+/// Words in the ROM, one per value of PC.
+const ROM_WORDS: usize = 1 << 16;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The romType a ROM stores during boot, from the `ld $xx; st [$21]` in its
+/// first page, so it is known before the ROM has run that far; 0 if there
+/// is none.
+fn embedded_rom_type(rom: &[[u8; 2]]) -> u8 {
+    rom[..256].windows(2).find(|w| w[0][0] == 0x00 && w[1] == [0xc2, ROM_TYPE as u8]).map_or(0, |w| w[0][1])
+}
+
+/// Finds images that can't run at all: blank ones, as an erased EPROM
+/// or a zero-filled file, and ones whose reset code branches back into
+/// itself before leaving the first RESET_WORDS words, which never gets as
+/// far as producing video. Conditional branches and far jumps need the
+/// registers to judge, so the check stops at the first of them.
+fn check_reset_code(rom: &[[u8; 2]]) -> Result<(), GigatronError> {
+    let byte = rom[0][0];
+    if (byte == 0x00 || byte == 0xff) && rom.iter().all(|&word| word == [byte, byte]) {
+        return Err(GigatronError::RomBlank { byte });
+    }
+    for (addr, &[opcode, d]) in rom[..RESET_WORDS].iter().enumerate() {
+        let insn = decode(opcode);
+        if !insn.is_jump {
+            continue;
+        }
+        // bra $dd within page 0, with an ordinary delay slot
+        let back = insn.mode == 7 && insn.bus == 0 && (d as usize) <= addr;
+        if back && !decode(rom[addr + 1][0]).is_jump {
+            return Err(GigatronError::RomStuck { addr: addr as u16 });
+        }
+        break;
+    }
+    Ok(())
+}
+
+/// Decompresses a gzip ROM image. Reading stops a word past the ROM size,
+/// enough for `restore_rom` to reject an oversized image without inflating
+/// all of it.
+#[cfg(feature = "gzip")]
+fn gunzip(data: &[u8]) -> Result<Vec<u8>, GigatronError> {
+    let mut buffer = Vec::new();
+    flate2::read::GzDecoder::new(data).take(65536 * 2 + 2).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_data: &[u8]) -> Result<Vec<u8>, GigatronError> {
+    Err(GigatronError::RomCompressed)
+}
+
+/// Gives every register the random contents of a machine just powered on,
+/// from any `rng`.
+pub fn garble<R: Rng + ?Sized>(S: &mut CpuState, rng: &mut R) {
+    S.PC = rng.random();
+    S.IR = rng.random();
+    S.D = rng.random();
+    S.AC = rng.random();
+    S.X = rng.random();
+    S.Y = rng.random();
+    S.OUT = rng.random();
+    S.undef = rng.random();
+}
+
+/// The load target of a mode that loads AC or OUT, none during a store. A
+/// store's ALU result is AC itself, so AC wouldn't change anyway, but OUT
+/// would, and the hardware gates both off. X and Y still load in modes 4
+/// and 5 (`st [$dd],x`), and mode 7 still increments X.
+fn E(W: bool, p: Register) -> Option<Register> {
+    if W { None } else { Some(p) } // Disable AC and OUT loading during RAM write
+}
+
+fn makeAddr(hi: u8, lo: u8) -> u16 {
+    (hi as u16) << 8 | lo as u16
+}
+
+impl Gigatron {
+    /// A machine with every setting at its default and an all-zero ROM, not
+    /// yet powered on; [`GigatronBuilder`] sets things up in one go.
+    pub fn new() -> Self {
+        Gigatron {
+            ROM: [[0u8; 2]; 1 << 16],
+            RAM: vec![0u8; RamSize::K32.bytes()],
+            S: CpuState::new(),
+            IN: 0xff,
+            XOUT: 0,
+            expansion: Expansion::new(),
+            vgaX: 0,
+            vgaY: 0,
+            raster: vec![BLANK; RASTER_WIDTH * RASTER_HEIGHT],
+            t: 0,
+            reset_hold: reset::DEFAULT_HOLD,
+            supervisor: Supervisor::power_on(reset::DEFAULT_HOLD),
+            port: InputPort::new(),
+            frames: 0,
+            vblank_start: None,
+            vblank_cycles: None,
+            loader_listening: false,
+            gt1: None,
+            fast_load: false,
+            rom_byteswap: false,
+            vectors: VcpuVectors::default(),
+            rng: Box::new(StdRng::from_os_rng()),
+            clean_boot: false,
+            ram_fill: RamFill::Random,
+            rtc_addr: None,
+            rtc_base: 0,
+            host_file: None,
+            peripherals: Vec::new(),
+            write_watches: Vec::new(),
+            read_watches: Vec::new(),
+            pc_profile: None,
+            vcpu_code: None,
+            history: None,
+            trace_sys: false,
+            rom_type: 0,
+        }
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Box::new(StdRng::seed_from_u64(seed));
+    }
+
+    fn reset(&mut self) {
+        let fill = match self.ram_fill {
+            RamFill::Random if self.clean_boot => RamFill::Zero,
+            fill => fill,
+        };
+        match fill {
+            RamFill::Random => self.rng.fill(&mut self.RAM[..]),
+            RamFill::RandomBlankScreen => {
+                self.rng.fill(&mut self.RAM[..]);
+                for row in self.RAM[SCREEN_START..0x8000].chunks_exact_mut(256) {
+                    row[..SCREEN_WIDTH].fill(0);
+                }
+                // A zero table reads as the default layout over those pages
+                self.RAM[VIDEO_TABLE..VIDEO_TABLE + 2 * SCREEN_HEIGHT].fill(0);
+            }
+            RamFill::Zero => self.RAM.fill(0),
+            RamFill::Ones => self.RAM.fill(0xff),
+            RamFill::Checker => {
+                for (addr, b) in self.RAM.iter_mut().enumerate() {
+                    *b = if addr % 2 == 0 { 0x00 } else { 0xff };
+                }
+            }
+        }
+        if self.clean_boot {
+            self.S = CpuState::new();
+            return;
+        }
+        garble(&mut self.S, &mut *self.rng);
+    }
+
+    /// Replaces the source of power-on garble and bus noise, which
+    /// [`seed`](Self::seed) otherwise sets up: a deterministic one for
+    /// tests, say, or one drawing on hardware entropy. Takes effect at the
+    /// next [`init`](Self::init) for the garble.
+    pub fn set_rng(&mut self, rng: Box<dyn RngCore>) {
+        self.rng = rng;
+    }
+
+    /// The registers, I/O latches, cycle count and RAM, for saving to a
+    /// file.
+    pub fn state(&self) -> State {
+        State {
+            cpu: self.S.clone(),
+            input: self.IN,
+            xout: self.XOUT,
+            ctrl: self.expansion.ctrl,
+            cycles: self.t,
+            ram: self.RAM.to_vec(),
+        }
+    }
+
+    /// Puts back what [`state`](Self::state) saved, RAM size included. Cycle
+    /// and frame counts and the reset line are left alone, so restoring
+    /// right after [`init`](Self::init) replaces the power-on garble with
+    /// the saved one.
+    pub fn restore(&mut self, state: &State) {
+        self.S = state.cpu.clone();
+        self.IN = state.input;
+        self.XOUT = state.xout;
+        self.expansion.ctrl = state.ctrl;
+        self.RAM.clone_from(&state.ram);
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// The registers and cycle count, without RAM, as a lighter
+    /// [`state`](Self::state) to bookmark while debugging.
+    pub fn save_cpu_state(&self) -> CpuSnapshot {
+        CpuSnapshot { cpu: self.S.clone(), cycles: self.t }
+    }
+
+    /// Puts back what [`save_cpu_state`](Self::save_cpu_state) saved. RAM,
+    /// the I/O latches, the beam and the frame count stay as they are now.
+    pub fn restore_cpu_state(&mut self, snapshot: &CpuSnapshot) {
+        self.S = snapshot.cpu.clone();
+        self.t = snapshot.cycles;
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Starts keeping the last `depth` cycles run, each a snapshot and at
+    /// most one RAM byte, so [`step_back`](Self::step_back) can undo them.
+    /// Powering on or restoring a state or snapshot forgets them.
+    pub fn enable_history(&mut self, depth: usize) {
+        self.history = Some(History::new(depth));
+    }
+
+    /// Undoes the last cycle run, one instruction: puts back the registers,
+    /// the cycle count and the RAM byte it stored over. The beam, the frame
+    /// count, the I/O latches and the peripherals stay as they are. False
+    /// when the history is off or has nothing left to undo.
+    pub fn step_back(&mut self) -> bool {
+        let Some((before, overwritten)) = self.history.as_mut().and_then(History::pop) else {
+            return false;
+        };
+        if let Some((addr, old)) = overwritten {
+            self.ram_write(addr, old);
+        }
+        self.S = before.cpu;
+        self.t = before.cycles;
+        true
+    }
+
+    /// Clocks `peripheral` along with the CPU from the next cycle on.
+    pub fn attach(&mut self, peripheral: Box<dyn Peripheral>) {
+        self.peripherals.push(peripheral);
+    }
+
+    /// Calls `watch` after every CPU store to RAM address `addr`, which is
+    /// taken within the RAM fitted ([`set_ram_size`](Self::set_ram_size)
+    /// first) like the store's own address.
+    pub fn add_write_watch(&mut self, addr: u16, watch: WriteWatch) {
+        self.add_write_watch_range(addr..=addr, watch);
+    }
+
+    /// Calls `watch` after every CPU store into `range` of RAM. `ctrl`
+    /// instructions count too, as they store whatever is on the bus; writes
+    /// the emulator makes itself, such as loading a GT1 program, don't.
+    pub fn add_write_watch_range(&mut self, range: RangeInclusive<u16>, watch: WriteWatch) {
+        let range = self.ram_addr(*range.start())..=self.ram_addr(*range.end());
+        self.write_watches.push((range, watch));
+    }
+
+    /// Lets `watch` supply the value of every CPU load from RAM address
+    /// `addr`, for devices that produce their data when it is read.
+    pub fn add_read_watch(&mut self, addr: u16, watch: ReadWatch) {
+        self.add_read_watch_range(addr..=addr, watch);
+    }
+
+    /// Lets `watch` supply the value of every CPU load from `range` of RAM:
+    /// operands of ALU instructions and of jumps alike. RAM itself is left
+    /// alone. Where ranges overlap, the watch added first answers; the
+    /// screen and other emulator reads still see RAM.
+    pub fn add_read_watch_range(&mut self, range: RangeInclusive<u16>, watch: ReadWatch) {
+        let range = self.ram_addr(*range.start())..=self.ram_addr(*range.end());
+        self.read_watches.push((range, watch));
+    }
+
+    /// Fits `size` of RAM, zeroed until [`init`](Self::init) fills it.
+    pub fn set_ram_size(&mut self, size: RamSize) {
+        self.RAM = vec![0; size.bytes()];
+    }
+
+    pub fn ram_size(&self) -> RamSize {
+        if self.RAM.len() == RamSize::K64.bytes() { RamSize::K64 } else { RamSize::K32 }
+    }
+
+    /// Cycles since power-on, including those spent in reset.
+    pub fn cycles(&self) -> u64 {
+        self.t
+    }
+
+    /// Whether the MCP100 still holds the CPU in reset.
+    pub fn in_reset(&self) -> bool {
+        self.supervisor.is_held()
+    }
+
+    /// The extended output register, latched from AC on every hSync.
+    pub fn xout(&self) -> u8 {
+        self.XOUT
+    }
+
+    /// Expander state, changed by the ROM through `ctrl`.
+    pub fn expansion(&self) -> Expansion {
+        self.expansion
+    }
+
+    /// vSync edges since power-on.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Whether the ROM has started drawing, i.e. produced a vSync since
+    /// power-on. Booting takes about ten frames' worth of cycles before that.
+    pub fn has_synced(&self) -> bool {
+        self.frames > 0
+    }
+
+    /// Cycles from the last vSync edge to the hSync that started the first
+    /// visible scanline, which is the time the ROM leaves for vCPU code in
+    /// vertical blank. The ROM counts `videoY` at 0x0009 in odd steps until
+    /// it draws again. None until one full vertical blank has been seen.
+    pub fn vblank_cycles(&self) -> Option<u64> {
+        self.vblank_cycles
+    }
+
+    /// The romType the loaded ROM sets during boot, read from its reset
+    /// code, so it is known before the ROM has run; 0 if not found.
+    pub fn rom_type(&self) -> u8 {
+        self.rom_type
+    }
+
+    /// Whether the ROM's Loader is waiting for a program on the input port.
+    /// Only known for the ROM versions in LOADER_SYS; false for others.
+    pub fn loader_ready(&self) -> bool {
+        let rom_type = self.RAM[ROM_TYPE] & 0xfc;
+        let sys_fn = self.RAM[SYS_FN] as u16 | (self.RAM[SYS_FN + 1] as u16) << 8;
+        LOADER_SYS.contains(&(rom_type, sys_fn))
+    }
+
+    /// Seconds since power-on at CLOCK_HZ, including those spent in reset:
+    /// the time the ROM has seen pass, however fast the host ran it.
+    pub fn emulated_seconds(&self) -> f64 {
+        self.t as f64 / CLOCK_HZ
+    }
+
+    /// Writes seconds, minutes and hours at the RTC address. The clock starts
+    /// at host time on power-on and then runs on emulated time, so it follows
+    /// the emulation speed rather than the wall clock.
+    fn update_rtc(&mut self) {
+        if let Some(addr) = self.rtc_addr {
+            let now = self.rtc_base + self.emulated_seconds() as u64;
+            self.ram_write(addr, (now % 60) as u8);
+            self.ram_write(addr + 1, (now / 60 % 60) as u8);
+            self.ram_write(addr + 2, (now / 3600 % 24) as u8);
+        }
+    }
+
+    /// Powers on: garbles (or clears) RAM and registers and holds reset for
+    /// `reset_hold` cycles.
+    pub fn init(&mut self) {
+        self.reset();
+        self.rtc_base = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.IN = 0xFF;
+        self.expansion = Expansion::new();
+        self.t = 0;
+        self.frames = 0;
+        self.vblank_start = None;
+        self.vblank_cycles = None;
+        self.loader_listening = false;
+        self.supervisor = Supervisor::power_on(self.reset_hold);
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Presses reset, as opposed to power-cycling with [`init`](Self::init):
+    /// the MCP100 holds reset for `reset_hold` cycles again, during which
+    /// the CPU fetches from PC = 0, and nothing else changes. RAM, the
+    /// registers, the expander and the cycle and frame counts all carry
+    /// over, so a ROM can tell a warm start from a cold one by what RAM
+    /// still holds, as it would on the hardware.
+    pub fn soft_reset(&mut self) {
+        self.supervisor = Supervisor::power_on(self.reset_hold.max(1));
+    }
+
+    fn restore_rom(&mut self, buffer: &[u8]) -> Result<(), GigatronError> {
+        if !buffer.len().is_multiple_of(2) {
+            return Err(GigatronError::RomOdd);
+        }
+        // PC and the address bus are 16 bits wide, so no ROM, development
+        // builds included, has more words; there is no larger layout to map
+        if buffer.len() > 65536 * 2 {
+            return Err(GigatronError::RomTooLarge { max: 65536 * 2, got: buffer.len() });
+        }
+        if buffer.len() != 65536 * 2 {
+            return Err(GigatronError::RomSize { expected: 65536 * 2, got: buffer.len() });
+        }
+        let rom: Vec<[u8; 2]> = buffer
+            .chunks_exact(2)
+            .map(|chunk| if self.rom_byteswap { [chunk[1], chunk[0]] } else { [chunk[0], chunk[1]] })
+            .collect();
+        self.ROM.copy_from_slice(&rom);
+        self.rom_type = embedded_rom_type(&rom);
+        if let Err(e) = self.check_rom() {
+            warn!("{}", e);
+        }
+        if !self.ROM[..RESET_WORDS].iter().any(|word| word[0] == LD_OUT)
+            && self.ROM[..RESET_WORDS].iter().any(|word| word[1] == LD_OUT)
+        {
+            let hint = if self.rom_byteswap { "without" } else { "with" };
+            warn!("The ROM's reset code doesn't set OUT; it may be byte-swapped, try {} --rom-byteswap", hint);
+        }
+        Ok(())
+    }
+
+    /// Whether the loaded ROM can run at all: RomBlank or RomStuck if it
+    /// is blank or its reset code loops in place. Loading only warns about
+    /// these, so a caller that would rather not run such a ROM checks here.
+    pub fn check_rom(&self) -> Result<(), GigatronError> {
+        check_reset_code(&self.ROM)
+    }
+
+    /// Writes each patch's word into the ROM, skipping (and not logging)
+    /// those the ROM already holds, so a whole known-good block can be
+    /// applied. Returns how many words changed.
+    pub fn apply_patches(&mut self, patches: &[Patch]) -> usize {
+        let mut changed = 0;
+        for &(addr, word) in patches {
+            let old = self.ROM[addr as usize];
+            if old == word {
+                continue;
+            }
+            info!("ROM patch {:04x}: {:02x}{:02x} -> {:02x}{:02x}", addr, old[0], old[1], word[0], word[1]);
+            self.ROM[addr as usize] = word;
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Loads a ROM image from `filename`, which may be gzip-compressed.
+    pub fn read_rom(&mut self, filename: &str) -> Result<(), GigatronError> {
+        let mut file = File::open(filename)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        self.read_rom_bytes(&buffer)
+    }
+
+    /// Loads a ROM image already in memory, such as an `include_bytes!`,
+    /// with the same checks as [`read_rom`](Self::read_rom).
+    pub fn read_rom_bytes(&mut self, data: &[u8]) -> Result<(), GigatronError> {
+        if data.starts_with(&GZIP_MAGIC) {
+            return self.restore_rom(&gunzip(data)?);
+        }
+        self.restore_rom(data)
+    }
+
+    /// The ROM word at `addr`, as [instruction, data]. Every u16 is an
+    /// address, so there is nothing out of bounds.
+    pub fn rom_word(&self, addr: u16) -> [u8; 2] {
+        self.ROM[addr as usize]
+    }
+
+    /// Up to `len` ROM words from `start`, as [instruction, data]. The
+    /// slice stops at the last word, 0xffff, rather than wrapping around to
+    /// 0x0000 the way PC does, so it can be shorter than `len`; read across
+    /// the end with two calls or with [`rom_word`](Self::rom_word).
+    pub fn rom_slice(&self, start: u16, len: usize) -> &[[u8; 2]] {
+        let start = start as usize;
+        &self.ROM[start..start.saturating_add(len).min(self.ROM.len())]
+    }
+
+    /// The first `count` instructions from reset as [`disasm::boot_path`]
+    /// follows them, to see where a ROM starts.
+    pub fn boot_path(&self, count: usize) -> Vec<String> {
+        disasm::boot_path(self.rom_slice(0, ROM_WORDS), count)
+    }
+
+    /// Writes a disassembly of the whole ROM to `filename`.
+    pub fn write_disassembly(&self, filename: &str) -> Result<(), GigatronError> {
+        let mut out = BufWriter::new(File::create(filename)?);
+        disasm::write_listing(self.rom_slice(0, ROM_WORDS), &mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Starts counting how often the instruction at every ROM address runs.
+    /// Every fetched instruction is executed, the one after a jump included,
+    /// so this counts each cycle once.
+    pub fn enable_pc_profile(&mut self) {
+        self.pc_profile.get_or_insert_with(|| vec![0; 1 << 16]);
+    }
+
+    /// Starts logging stores into RAM the vCPU has run code from. The CPU
+    /// itself can only run from ROM, which no store reaches, so code can
+    /// only be changed under the vCPU: by a program patching itself, or by
+    /// one being loaded over another that ran (as from the Loader). The
+    /// vCPU fetches its instruction at vPC after advancing it, so a load
+    /// from there or the byte after counts as a fetch. Each page is noted
+    /// once, then again only after the vCPU ran from it again.
+    pub fn enable_vcpu_code_check(&mut self) {
+        self.vcpu_code.get_or_insert_with(|| vec![false; 1 << 16]); // RAM can grow with restore
+    }
+
+    /// Where the vCPU fetches from next, the address in vPC.
+    fn vpc(&self) -> u16 {
+        makeAddr(self.ram_read(self.vectors.vPC + 1), self.ram_read(self.vectors.vPC))
+    }
+
+    /// Marks `addr` as vCPU code when the load from it is the vCPU's fetch.
+    fn note_vcpu_fetch(&mut self, addr: u16) {
+        let vpc = self.ram_addr(self.vpc());
+        let fetched = addr == vpc || addr == vpc & 0xff00 | (vpc as u8).wrapping_add(1) as u16;
+        if fetched && let Some(code) = &mut self.vcpu_code {
+            code[addr as usize] = true;
+        }
+    }
+
+    /// Logs a store of `value` to `addr`, about to be made, when the vCPU
+    /// has run code from there and it changes the byte, with where the CPU
+    /// and the vCPU were. The interpreter itself stores the byte it fetched
+    /// back unchanged, just to step X.
+    fn check_vcpu_code(&mut self, addr: u16, value: u8) {
+        let old = self.ram_read(addr);
+        let Some(code) = &mut self.vcpu_code else {
+            return;
+        };
+        if !code[addr as usize] || value == old {
+            return;
+        }
+        let page = (addr & 0xff00) as usize;
+        code[page..page + 256].fill(false);
+        info!(
+            "vCPU code at ${:04x} overwritten, ${:02x} -> ${:02x}, at cycle {} by the instruction at ROM ${:04x} (vPC=${:04x})",
+            addr, old, value, self.t, self.S.PC.wrapping_sub(1), self.vpc()
+        );
+    }
+
+    /// Executions per ROM address since [`enable_pc_profile`](Self::enable_pc_profile).
+    pub fn pc_profile(&self) -> Option<&[u64]> {
+        self.pc_profile.as_deref()
+    }
+
+    /// Writes the PC profile, hottest address first, each with its
+    /// instruction. Writes nothing when profiling isn't enabled.
+    pub fn write_pc_profile(&self, out: &mut impl Write) -> std::io::Result<()> {
+        match &self.pc_profile {
+            Some(counts) => disasm::write_profile(self.rom_slice(0, ROM_WORDS), counts, out),
+            None => Ok(()),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn read_ram(&mut self) -> std::io::Result<()> {
+        //    let mut f = File::create_new("foo.txt")?;
+        //    f.write_all("Hello, world!".as_bytes())?;
+        Ok(())
+    }
+
+    /// Fills the screen with color bars, the 64-color palette and a notice,
+    /// to show the window works when there is no ROM to run.
+    pub fn load_test_pattern(&mut self) {
+        const BARS: [u8; 8] = [0x3f, 0x0f, 0x3c, 0x0c, 0x33, 0x03, 0x30, 0x00];
+        const NOTICE: &str = "NO ROM LOADED";
+        self.RAM.fill(0); // Also leaves the videoTable in its default layout
+        for y in 0..120 {
+            for x in 0..160 {
+                self.RAM[SCREEN_START + y * 256 + x] = match y {
+                    0..80 => BARS[x / 20],
+                    80..100 => (x * 64 / 160) as u8,
+                    _ => 0,
+                };
+            }
+        }
+        let x = (160 - NOTICE.len() * font::ADVANCE) / 2;
+        let y = 100 + (20 - font::HEIGHT) / 2;
+        font::draw(NOTICE, x, y, |x, y| self.RAM[SCREEN_START + y * 256 + x] = 0x3f);
+    }
+
+    pub fn write_ram(&self, filename: &str) -> Result<(), GigatronError> {
+        let mut file = File::create(filename)?;
+        file.write_all(&self.RAM)?;
+        Ok(())
+    }
+
+    /// Addresses where RAM differs from `other`, with the current and the
+    /// other value.
+    pub fn diff_ram(&self, other: &[u8]) -> Vec<(u16, u8, u8)> {
+        self.RAM.iter().zip(other)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(addr, (&a, &b))| (addr as u16, a, b))
+            .collect()
+    }
+
+    /// Places a GT1 program in RAM. With `fast_load` the vCPU is then sent to
+    /// its entry the way the Loader does it: vLR = start and vPC = start - 2,
+    /// as the vCPU advances vPC before each fetch.
+    pub fn load_gt1(&mut self, gt1: &Gt1) {
+        for (addr, data) in &gt1.segments {
+            for (i, b) in data.iter().enumerate() {
+                self.ram_write(addr.wrapping_add(i as u16), *b);
+            }
+            debug!("GT1 segment {:#06x}..{:#06x}", addr, *addr as usize + data.len());
+        }
+        match gt1.start {
+            Some(start) if self.fast_load => {
+                let vPC = start & 0xff00 | (start as u8).wrapping_sub(2) as u16;
+                self.write_word(self.vectors.vPC, vPC);
+                self.write_word(self.vectors.vLR, start);
+                self.ram_write(self.vectors.vSP, 0);
+                info!("GT1 program started at {:#06x}", start);
+            }
+            Some(start) => info!("GT1 program loaded, entry {:#06x} (use --fast-load to start it)", start),
+            None => info!("GT1 program loaded, no entry point"),
+        }
+    }
+
+    fn write_word(&mut self, addr: u16, value: u16) {
+        self.ram_write(addr, value as u8);
+        self.ram_write(addr.wrapping_add(1), (value >> 8) as u8);
+    }
+
+    /// Where in RAM the CPU's `addr` lands. With 32K fitted address line 15
+    /// isn't connected, so the upper half mirrors the lower; 64K takes every
+    /// address as it is.
+    pub fn ram_addr(&self, addr: u16) -> u16 {
+        addr & (self.RAM.len() - 1) as u16
+    }
+
+    /// The byte the CPU sees at `addr`.
+    fn ram_read(&self, addr: u16) -> u8 {
+        self.RAM[self.ram_addr(addr) as usize]
+    }
+
+    /// Stores `value` where [`ram_read`](Self::ram_read) finds it.
+    fn ram_write(&mut self, addr: u16, value: u8) {
+        let at = self.ram_addr(addr) as usize;
+        self.RAM[at] = value;
+    }
+
+    fn cpuCycle(&mut self) -> CpuState {
+        let mut T: CpuState = self.S.clone(); // New state is old state unless something changes
+        T.IR = self.ROM[self.S.PC as usize][0]; // Instruction Fetch
+        T.D = self.ROM[self.S.PC as usize][1];
+        let DecodedInsn { ins, mode, bus, is_write: W, is_jump: J } = decode(self.S.IR);
+
+        let mut lo = self.S.D;
+        let mut hi = 0;
+        let mut to: Option<Register> = None; // Mode Decoder
+        let mut incX = false;
+        // Jumps bypass the mode decoder, their mode bits being the condition:
+        // a RAM operand is then always [D] in the zero page
+        if !J {
+            match mode {
+                0 => {
+                    to = E(W, Register::AC);
+                }
+                1 => {
+                    to = E(W, Register::AC);
+                    lo = self.S.X;
+                }
+                2 => {
+                    to = E(W, Register::AC);
+                    hi = self.S.Y;
+                }
+                3 => {
+                    to = E(W, Register::AC);
+                    lo = self.S.X;
+                    hi = self.S.Y;
+                }
+                4 => {
+                    to = Some(Register::X);
+                }
+                5 => {
+                    to = Some(Register::Y);
+                }
+                6 => {
+                    to = E(W, Register::OUT);
+                }
+                7 => {
+                    to = E(W, Register::OUT);
+                    lo = self.S.X;
+                    hi = self.S.Y;
+                    incX = true;
+                }
+                _ => unreachable!(),
+            }
+        }
+        let addr: u16 = makeAddr(hi, lo);
+        // During a store the RAM takes the bus instead of driving it: WE is
+        // low and OE is gated off, so there is no read, nothing a read
+        // watch could see, and no conflict. Bus mode 1, which names the RAM
+        // as source, then leaves the bus floating and the RAM stores
+        // whatever it holds (`undef`); the expander qualifies this combination
+        // (`ctrl`) and latches the address as its control word. The other
+        // sources store as on a load, D, AC or IN.
+        let mut B = self.S.undef; // Data Bus
+        match bus {
+            0 => {
+                B = self.S.D;
+            }
+            1 => {
+                if !W {
+                    B = self.ram_read(addr);
+                    if self.vcpu_code.is_some() {
+                        self.note_vcpu_fetch(self.ram_addr(addr));
+                    }
+                    if !self.read_watches.is_empty() {
+                        let addr = self.ram_addr(addr);
+                        if let Some((_, watch)) = self.read_watches.iter_mut().find(|(range, _)| range.contains(&addr)) {
+                            B = watch(addr);
+                        }
+                    }
+                }
+            }
+            2 => {
+                B = self.S.AC;
+            }
+            3 => {
+                B = self.IN;
+            }
+            _ => unreachable!(),
+        }
+        if W {
+            let at = self.ram_addr(addr);
+            if self.vcpu_code.is_some() {
+                self.check_vcpu_code(at, B);
+            }
+            let old = self.ram_read(addr);
+            if let Some(history) = &mut self.history {
+                history.note_store(at, old);
+            }
+            self.ram_write(addr, B); // Random Access Memory
+            if at == host_file::COMMAND && let Some(file) = &mut self.host_file {
+                file.command(&mut self.RAM);
+            }
+            if bus == 1 {
+                self.expansion.ctrl = addr; // ctrl: the expander takes the address
+            }
+            if !self.write_watches.is_empty() {
+                for (range, watch) in &mut self.write_watches {
+                    if range.contains(&at) {
+                        watch(at, B);
+                    }
+                }
+            }
+        }
+        let ALU = match ins { // Arithmetic and Logic Unit
+            0 => B,                          // LD
+            1 => self.S.AC & B,              // ANDA
+            2 => self.S.AC | B,              // ORA
+            3 => self.S.AC ^ B,              // XORA
+            4 => self.S.AC.wrapping_add(B),  // ADDA
+            5 => self.S.AC.wrapping_sub(B),  // SUBA
+            6 => self.S.AC,                  // ST
+            7 => self.S.AC.wrapping_neg(),   // Bcc/JMP
+            _ => unreachable!(),
+        };
+        if let Some(reg) = to {
+            // Load value into register
+            trace!("Load value: reg = {:?} value = {}", reg, ALU);
+            match reg {
+                Register::AC => T.AC = ALU,
+                Register::OUT => T.OUT = ALU,
+                Register::X => T.X = ALU,
+                Register::Y => T.Y = ALU,
+            }
+            //*to = ALU;
+        }
+        if incX {
+            T.X = self.S.X.wrapping_add(1); // Increment X
+        }
+        T.PC = self.S.PC.wrapping_add(1); // Next instruction
+        if J {
+            if mode != 0 {
+                // Conditional branch within page
+                let sAC = if self.S.AC == 0 { 1 } else { 0 };
+                let cond = (self.S.AC >> 7) + 2 * sAC;
+                let st = mode & (1 << cond);
+                if st > 0 {
+                    // 74153
+                    T.PC = (self.S.PC & 0xff00) | B as u16;
+                }
+            } else {
+                T.PC = makeAddr(self.S.Y, B); // Unconditional far jump
+            }
+        }
+        T
+    }
+
+    /// Runs one clock cycle, which advances [`cycles`](Self::cycles) by
+    /// exactly one, ticks the peripherals and reports the sync edges it
+    /// produced.
+    pub fn step(&mut self) -> Edges {
+        if self.supervisor.clock() {
+            self.S.PC = 0; // MCP100 Power-On Reset
+        }
+        if let Some(counts) = &mut self.pc_profile {
+            counts[self.S.PC as usize] += 1; // Fetched now, so executed next cycle
+        }
+        if let Some(history) = &mut self.history {
+            history.push(CpuSnapshot { cpu: self.S.clone(), cycles: self.t });
+        }
+        if self.trace_sys && self.S.IR == JMP_SYS_FN && self.S.D as usize == SYS_FN {
+            self.log_sys_call();
+        }
+        let mut T: CpuState = self.cpuCycle(); // Update CPU
+        let edges = self.vga(&mut T);
+        self.S = T;
+        if !self.peripherals.is_empty() {
+            let mut peripherals = std::mem::take(&mut self.peripherals);
+            for peripheral in &mut peripherals {
+                peripheral.tick(self.t, self);
+            }
+            self.peripherals = peripherals;
+        }
+        self.t += 1;
+        edges
+    }
+
+    /// Logs the SYS function the jump being executed enters, by name where
+    /// the ROM version has a table. The version comes from the ROM image, as
+    /// the first calls come before the ROM has set romType in RAM.
+    fn log_sys_call(&self) {
+        let addr = makeAddr(self.S.Y, self.RAM[SYS_FN]);
+        match sys::name(self.rom_type, addr) {
+            Some(name) => info!("{} (${:04x}) at cycle {}", name, addr, self.t),
+            None => info!("SYS ${:04x} at cycle {}", addr, self.t),
+        }
+    }
+
+    /// Runs until the next vSync, so a host UI can call it once per frame
+    /// and present [`frame_rgb`](Self::frame_rgb) itself. Gives up after two
+    /// frames' worth of cycles and returns false when no vSync came, as
+    /// while the ROM is still booting or not producing video at all.
+    pub fn run_frame(&mut self) -> bool {
+        for _ in 0..2 * FRAME_CYCLES {
+            if self.step().vSync {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Runs until `frames` frames have been completed since power-on, giving
+    /// up once `max_cycles` cycles have run since power-on, so that a hung
+    /// ROM can't keep a test or batch job waiting. The limit is a count, not
+    /// a time: the same run stops at the same cycle on every host.
+    pub fn run_frames(&mut self, frames: u64, max_cycles: u64) -> Result<(), GigatronError> {
+        while self.frames < frames {
+            if self.t >= max_cycles {
+                return Err(GigatronError::CycleLimit { cycles: self.t, pc: self.S.PC });
+            }
+            self.step();
+        }
+        Ok(())
+    }
+
+    fn vga(&mut self, T: &mut CpuState) -> Edges {
+
+        // HSync (бит 2) переключается в 0, когда нужно начать новую строку
+        let hSync = ((self.S.OUT & 0b0100_0000) > 0) && ((T.OUT & 0b0100_0000) == 0);
+
+        // VSync (бит 1) переключается в 0, когда нужно начать новый кадр
+        let vSync = ((self.S.OUT & 0b1000_0000) > 0) && ((T.OUT & 0b1000_0000) == 0);
+
+        // XOUT is clocked by the rising edge of hSync
+        if ((self.S.OUT & 0b0100_0000) == 0) && ((T.OUT & 0b0100_0000) > 0) {
+            self.XOUT = T.AC;
+        }
+
+        // One pixel leaves the OUT register every cycle. A ROM that never
+        // syncs, such as a garbled or byte-swapped image, leaves them counting
+        self.vgaX = self.vgaX.saturating_add(1);
+        if hSync {
+            trace!("line {} took {} cycles", self.vgaY, self.vgaX);
+            self.vgaX = 0;
+            self.vgaY = self.vgaY.saturating_add(1);
+        }
+        if vSync {
+            trace!("frame took {} lines", self.vgaY);
+            self.vgaY = 0;
+        }
+
+        let (x, y) = (self.vgaX as usize, self.vgaY as usize);
+        if x < RASTER_WIDTH && y < RASTER_HEIGHT {
+            let syncing = T.OUT & 0b1100_0000 != 0b1100_0000;
+            self.raster[y * RASTER_WIDTH + x] = if syncing { BLANK } else { T.OUT & 0x3f };
+        }
+
+        let mut loaderReady = false;
+        if vSync {
+            let listening = self.loader_ready();
+            loaderReady = listening && !self.loader_listening;
+            self.loader_listening = listening;
+            self.vblank_start = Some(self.t);
+            self.IN = self.port.latch();
+            self.frames += 1;
+            self.update_rtc();
+            if self.frames >= GT1_BOOT_FRAMES && let Some(gt1) = self.gt1.take() {
+                self.load_gt1(&gt1);
+            }
+        }
+
+        if hSync {
+            T.undef = self.rng.random(); // Change this once in a while
+            if let Some(start) = self.vblank_start
+                && self.RAM[VIDEO_Y] & 1 == 0
+            {
+                self.vblank_cycles = Some(self.t - start);
+                self.vblank_start = None;
+            }
+        }
+        Edges { hSync, vSync, loaderReady }
+    }
+
+    /// The labeled RAM locations of [`mem::LOCATIONS`] with their current
+    /// values, for debuggers.
+    pub fn io_values(&self) -> Vec<mem::IoValue> {
+        mem::LOCATIONS
+            .iter()
+            .map(|&(name, addr, size)| {
+                let bytes = &self.RAM[addr as usize..addr as usize + size];
+                let value = bytes.iter().rev().fold(0, |value, &b| value << 8 | b as u16);
+                mem::IoValue { name, addr, size, value }
+            })
+            .collect()
+    }
+
+    /// The registers, the cycle count, IN and the
+    /// [`io_values`](Self::io_values) as one JSON object, for tools outside
+    /// the emulator, as in `{"t":1234,"PC":768,...,"IN":255,"io":{"videoY":0,...}}`.
+    /// The values are plain numbers, JSON having no hex.
+    pub fn state_json(&self) -> String {
+        let S = &self.S;
+        let registers = [
+            ("PC", S.PC), ("IR", S.IR as u16), ("D", S.D as u16), ("AC", S.AC as u16),
+            ("X", S.X as u16), ("Y", S.Y as u16), ("OUT", S.OUT as u16), ("undef", S.undef as u16),
+        ];
+        let mut json = format!("{{\"t\":{}", self.t);
+        for (name, value) in registers {
+            json += &format!(",\"{}\":{}", name, value);
+        }
+        let io: Vec<String> = self.io_values().iter().map(|v| format!("\"{}\":{}", v.name, v.value)).collect();
+        json += &format!(",\"IN\":{},\"io\":{{{}}}}}", self.IN, io.join(","));
+        json
+    }
+
+    /// Source of every logical scanline as `(page, X offset)`, read from the
+    /// videoTable the video kernel uses. While the ROM hasn't set the table up
+    /// yet (all zero) the default linear layout from [`mem::VIDEO_BASE`] is assumed.
+    pub fn scanlines(&self) -> [(u8, u8); 120] {
+        let table = &self.RAM[VIDEO_TABLE..VIDEO_TABLE + 2 * 120];
+        let uninitialized = table.iter().all(|&b| b == 0);
+        let mut lines = [(0u8, 0u8); 120];
+        for (y, line) in lines.iter_mut().enumerate() {
+            *line = if uninitialized {
+                ((SCREEN_START >> 8) as u8 + y as u8, 0)
+            } else {
+                (table[2 * y], table[2 * y + 1])
+            };
+        }
+        lines
+    }
+
+    /// The live screen layout, from the same videoTable and videoTop the
+    /// renderer reads, so it changes as soon as the ROM rewrites them. A
+    /// layout other than rows on consecutive pages (wrapping from page 0x7f
+    /// back to page 8, as the ROM scrolls) isn't `linear`; its stride and
+    /// scroll then only describe the first shown rows.
+    pub fn video_geometry(&self) -> VideoGeometry {
+        let lines = self.scanlines();
+        let top = self.video_top().min(SCREEN_HEIGHT - 1);
+        let first_page = (SCREEN_START >> 8) as u8;
+        let next_page = |page: u8| if page == first_page + SCREEN_HEIGHT as u8 - 1 { first_page } else { page + 1 };
+        let linear = lines.windows(2).skip(top).all(|pair| pair[1] == (next_page(pair[0].0), pair[0].1));
+        let (page, scroll_x) = lines[top];
+        VideoGeometry {
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT - self.video_top(),
+            top,
+            stride: lines.get(top + 1).map_or(256, |&(next, dx)| {
+                makeAddr(next, dx).wrapping_sub(makeAddr(page, scroll_x)) as usize
+            }),
+            base: makeAddr(page, scroll_x),
+            scroll_x,
+            scroll_y: (page.wrapping_sub(first_page) as usize + SCREEN_HEIGHT - top) % SCREEN_HEIGHT,
+            linear,
+        }
+    }
+
+    /// The logical screen as one 6-bit color (`--BBGGRR`) per pixel, row by
+    /// row, following the videoTable. Vertical scrolling needs nothing more,
+    /// as ROMs scroll by rewriting the table's pages.
+    pub fn frame(&self) -> Vec<u8> {
+        let lines = self.scanlines();
+        let top = self.video_top();
+        let mut frame = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT);
+        for (y, line) in lines.into_iter().enumerate() {
+            for x in 0..SCREEN_WIDTH {
+                frame.push(if y < top { 0 } else { self.video_byte(line, x) });
+            }
+        }
+        frame
+    }
+
+    /// Rows the video kernel leaves black at the top of the screen. ROMv5a
+    /// and later skip `videoTop_v5` scanline pairs, half a row each, to give
+    /// the vCPU more time; the rows below still show their own videoTable
+    /// entry, so nothing moves up. Older ROMs use the address for other
+    /// things and always show every row.
+    fn video_top(&self) -> usize {
+        if self.RAM[ROM_TYPE] & 0xfc >= ROM_V5A {
+            (self.RAM[VIDEO_TOP] as usize).div_ceil(2).min(SCREEN_HEIGHT)
+        } else {
+            0
+        }
+    }
+
+    /// The RAM address every pixel of [`frame`](Self::frame) is read from,
+    /// in the same order; None on the rows the video kernel leaves black.
+    /// Shows which part of RAM feeds which part of the screen.
+    pub fn frame_addrs(&self) -> Vec<Option<u16>> {
+        let top = self.video_top();
+        let mut addrs = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT);
+        for (y, line) in self.scanlines().into_iter().enumerate() {
+            for x in 0..SCREEN_WIDTH {
+                addrs.push((y >= top).then(|| self.ram_addr(video_addr(line, x))));
+            }
+        }
+        addrs
+    }
+
+    /// Color of pixel `x` on a scanline from the videoTable.
+    fn video_byte(&self, line: (u8, u8), x: usize) -> u8 {
+        self.ram_read(video_addr(line, x)) & 0x3f
+    }
+
+    /// [`frame`](Self::frame) in 0x00RRGGBB, the layout minifb and most
+    /// pixel buffers take.
+    pub fn frame_rgb(&self) -> Vec<u32> {
+        self.frame().iter().map(|&v| makeRGB(&unpack_color(v))).collect()
+    }
+
+    /// What OUT sent at every point of the raster during the last frame:
+    /// a 6-bit color, or [`BLANK`] while a sync pulse was on. Unlike
+    /// [`frame`](Self::frame) this follows the beam, so it shows what the
+    /// video kernel actually output, blank lines and mid-frame changes
+    /// included.
+    pub fn raster(&self) -> &[u8] {
+        &self.raster
+    }
+
+    /// FNV-1a hash of [`frame`](Self::frame), for telling screens apart
+    /// without storing them.
+    pub fn frame_hash(&self) -> u64 {
+        fnv1a(self.frame().iter())
+    }
+
+    /// Hash of the `w` x `h` pixels at (`x`, `y`) of [`frame`](Self::frame),
+    /// row by row, so one part of the screen can be checked while another
+    /// animates. The full screen hashes the same as `frame_hash`.
+    ///
+    /// Panics if the region doesn't fit the 160x120 screen.
+    pub fn region_hash(&self, x: usize, y: usize, w: usize, h: usize) -> u64 {
+        assert_region(x, y, w, h);
+        let frame = self.frame();
+        fnv1a(frame.chunks_exact(SCREEN_WIDTH).skip(y).take(h).flat_map(|row| &row[x..x + w]))
+    }
+
+    /// How many pixels of each 6-bit color the `w` x `h` region at (`x`,
+    /// `y`) of [`frame`](Self::frame) holds, indexed by color. Cheaper than
+    /// comparing images when all a test needs is "mostly red".
+    ///
+    /// Panics if the region doesn't fit the 160x120 screen.
+    pub fn region_histogram(&self, x: usize, y: usize, w: usize, h: usize) -> [u32; 64] {
+        assert_region(x, y, w, h);
+        let mut histogram = [0u32; 64];
+        for row in self.frame().chunks_exact(SCREEN_WIDTH).skip(y).take(h) {
+            for &color in &row[x..x + w] {
+                histogram[color as usize] += 1;
+            }
+        }
+        histogram
+    }
+
+    /// The color most pixels of [`frame`](Self::frame) have, decoded with
+    /// [`unpack_color`]; a tie goes to the lowest 6-bit color. One number
+    /// for noticing a screen that went mostly black.
+    pub fn dominant_color(&self) -> Color {
+        let histogram = self.region_histogram(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+        let mut dominant = 0;
+        for color in 1..histogram.len() {
+            if histogram[color] > histogram[dominant] {
+                dominant = color;
+            }
+        }
+        unpack_color(dominant as u8)
+    }
+}
+
+/// Address of pixel `x` on a scanline from the videoTable. X wraps within
+/// the page as in the video kernel, which reads through [y,x++] like any
+/// other RAM access.
+fn video_addr((page, dx): (u8, u8), x: usize) -> u16 {
+    makeAddr(page, dx.wrapping_add(x as u8))
+}
+
+fn assert_region(x: usize, y: usize, w: usize, h: usize) {
+    assert!(
+        x + w <= SCREEN_WIDTH && y + h <= SCREEN_HEIGHT,
+        "region {}x{} at ({}, {}) is outside the 160x120 screen", w, h, x, y
+    );
+}
+
+fn fnv1a<'a>(bytes: impl Iterator<Item = &'a u8>) -> u64 {
+    bytes.fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+impl Default for Gigatron {
+    fn default() -> Self {
+        Gigatron::new()
+    }
+}
+
+impl CpuState {
+    pub fn new() -> Self {
+        CpuState {
+            PC: 0,
+            IR: 0,
+            D: 0,
+            AC: 0,
+            X: 0,
+            Y: 0,
+            OUT: 0,
+            undef: 0,
+        }
+    }
+}
+
+impl Default for CpuState {
+    fn default() -> Self {
+        CpuState::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn insn(ins: u8, mode: u8, bus: u8, is_write: bool, is_jump: bool) -> DecodedInsn {
+        DecodedInsn { ins, mode, bus, is_write, is_jump }
+    }
+
+    /// Representative IR bytes, one per kind of instruction.
+    #[test]
+    fn decode_fields() {
+        assert_eq!(decode(0x00), insn(0, 0, 0, false, false)); // ld $xx
+        assert_eq!(decode(0x02), insn(0, 0, 2, false, false)); // nop (ld ac)
+        assert_eq!(decode(0x8d), insn(4, 3, 1, false, false)); // adda [y,x]
+        assert_eq!(decode(0xc2), insn(6, 0, 2, true, false)); // st [$xx]
+        assert_eq!(decode(0xde), insn(6, 7, 2, true, false)); // st [y,x++],out
+        assert_eq!(decode(0xfe), insn(7, 7, 2, false, true)); // bra ac
+    }
+
+    /// Blank and stuck images still load, and check_rom says why they won't
+    /// run. `bra $01` at 1 branches back to itself.
+    #[test]
+    fn unrunnable_roms_load() {
+        let mut gt = Gigatron::new();
+        let mut rom = vec![0xffu8; 1 << 17];
+        gt.read_rom_bytes(&rom).unwrap();
+        assert!(matches!(gt.check_rom(), Err(GigatronError::RomBlank { byte: 0xff })));
+        rom[..6].copy_from_slice(&[0x02, 0x00, 0xfc, 0x01, 0x02, 0x00]);
+        gt.read_rom_bytes(&rom).unwrap();
+        assert_eq!(gt.rom_word(1), [0xfc, 0x01], "didn't load the stuck ROM");
+        assert!(matches!(gt.check_rom(), Err(GigatronError::RomStuck { addr: 1 })));
+    }
+
+    /// Two machines powered on with the same seed start out identical, RAM
+    /// and registers alike, and a different seed gives a different start.
+    #[test]
+    fn seeded_garble() {
+        let power_on = |seed| {
+            let mut gt = Gigatron::new();
+            gt.seed(seed);
+            gt.init();
+            gt.state().to_bytes()
+        };
+        assert!(power_on(42) == power_on(42), "the same seed garbled differently");
+        assert!(power_on(42) != power_on(43), "different seeds garbled the same");
+    }
+
+    /// Every byte the same, to see the garble come from the RNG it was given.
+    struct Constant(u8);
+
+    impl RngCore for Constant {
+        fn next_u32(&mut self) -> u32 {
+            u32::from_ne_bytes([self.0; 4])
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            u64::from_ne_bytes([self.0; 8])
+        }
+
+        fn fill_bytes(&mut self, dst: &mut [u8]) {
+            dst.fill(self.0);
+        }
+    }
+
+    #[test]
+    fn garble_from_a_supplied_rng() {
+        let mut gt = Gigatron::new();
+        gt.set_rng(Box::new(Constant(0xa5)));
+        gt.init();
+        let S = &gt.S;
+        assert_eq!(S.PC, 0xa5a5);
+        assert_eq!([S.IR, S.D, S.AC, S.X, S.Y, S.OUT, S.undef], [0xa5; 7]);
+        assert_eq!(gt.RAM.iter().position(|&b| b != 0xa5), None, "RAM not all garble");
+    }
+
+    /// The resistor palette gives the levels its documentation works out, on
+    /// each channel.
+    #[test]
+    fn resistor_palette() {
+        const LEVELS: [u8; 4] = [0, 82, 173, 255];
+        for (bits, level) in (0..4u8).zip(LEVELS) {
+            let c = Palette::Resistor.color(bits | bits << 2 | bits << 4);
+            assert_eq!((c.0, c.1, c.2), (level, level, level), "bits {:02b}", bits);
+        }
+    }
+
+    /// Overwriting vCPU code in the upper 32K of a 64K machine clears the
+    /// mark on its own page, not on the page 32K below.
+    #[test]
+    fn vcpu_code_in_upper_ram() {
+        let mut gt = Gigatron::new();
+        gt.set_ram_size(RamSize::K64);
+        gt.enable_vcpu_code_check();
+        let code = gt.vcpu_code.as_mut().unwrap();
+        code[0x0110] = true;
+        code[0x8110] = true;
+        gt.check_vcpu_code(0x8110, 0x5a);
+        let code = gt.vcpu_code.as_ref().unwrap();
+        assert!(!code[0x8110], "page $81 still marked");
+        assert!(code[0x0110], "page $01 cleared");
+    }
+
+    /// A videoTable scrolled the way the ROM does it, pages rotated by 10 rows
+    /// and every row read from X offset 3, is seen as such.
+    #[test]
+    fn video_geometry_of_a_scrolled_table() {
+        let mut gt = Gigatron::new();
+        for y in 0..120 {
+            gt.RAM[0x0100 + 2 * y] = 0x08 + ((y + 10) % 120) as u8;
+            gt.RAM[0x0101 + 2 * y] = 3;
+        }
+        let expected = VideoGeometry {
+            width: 160,
+            height: 120,
+            top: 0,
+            stride: 256,
+            base: 0x1203,
+            scroll_x: 3,
+            scroll_y: 10,
+            linear: true,
+        };
+        assert_eq!(gt.video_geometry(), expected);
+    }
+}
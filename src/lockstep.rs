@@ -0,0 +1,133 @@
+use gigatron_example::replay::{InputEvent, Replay};
+use gigatron_example::{CpuState, Gigatron, FRAME_CYCLES, SCREEN_WIDTH};
+
+/// Where two machines running side by side first behaved differently.
+pub struct Divergence {
+    pub cycle: u64,
+    pub frame: u64,
+    pub what: String,
+    pub a: CpuState,
+    pub b: CpuState,
+}
+
+/// Steps `a` and `b` together for `frames` frames of `a`, feeding both the
+/// same recorded input, and stops at the first cycle where their registers
+/// differ or at the first vSync where their screens do. Both should have
+/// been powered on in the same state with the same seed, so that the bus
+/// noise agrees too.
+pub fn run(a: &mut Gigatron, b: &mut Gigatron, mut replays: Option<(Replay, Replay)>, frames: u64) -> Option<Divergence> {
+    // A ROM that never syncs would never count frames
+    let limit = (frames + 1) * 2 * FRAME_CYCLES;
+    while a.frames() < frames && a.cycles() < limit {
+        if let Some((replay_a, replay_b)) = &mut replays {
+            feed(a, replay_a);
+            feed(b, replay_b);
+        }
+        let (edges_a, edges_b) = (a.step(), b.step());
+        let what = if registers(&a.S) != registers(&b.S) {
+            Some("registers".to_string())
+        } else if edges_a.vSync || edges_b.vSync {
+            screen(a, b)
+        } else {
+            None
+        };
+        if let Some(what) = what {
+            // The cycle that produced the difference
+            let (cycle, frame) = (a.cycles() - 1, a.frames());
+            return Some(Divergence { cycle, frame, what, a: a.S.clone(), b: b.S.clone() });
+        }
+    }
+    None
+}
+
+/// Runs `a` and `b`, two power-ons of the same ROM with the same seed, for
+/// `frames` frames with the same input (--verify-determinism), checking at
+/// power-on and at every vSync that they agree in everything: registers,
+/// bus noise included, IN, RAM and the screen. An emulator that only
+/// depends on its seed never lets them differ; returns the first
+/// checkpoint where they do.
+pub fn verify(a: &mut Gigatron, b: &mut Gigatron, mut replays: Option<(Replay, Replay)>, frames: u64) -> Option<Divergence> {
+    let diverged = |a: &Gigatron, b: &Gigatron, what: String| {
+        let (cycle, frame) = (a.cycles().saturating_sub(1), a.frames());
+        Some(Divergence { cycle, frame, what, a: a.S.clone(), b: b.S.clone() })
+    };
+    if let Some(what) = checkpoint(a, b) {
+        return diverged(a, b, format!("power-on {}", what));
+    }
+    let limit = (frames + 1) * 2 * FRAME_CYCLES;
+    while a.frames() < frames && a.cycles() < limit {
+        if let Some((replay_a, replay_b)) = &mut replays {
+            feed(a, replay_a);
+            feed(b, replay_b);
+        }
+        let (edges_a, edges_b) = (a.step(), b.step());
+        let what = if edges_a.vSync != edges_b.vSync {
+            Some("vSync".to_string())
+        } else if edges_a.vSync {
+            checkpoint(a, b)
+        } else {
+            None
+        };
+        if let Some(what) = what {
+            return diverged(a, b, what);
+        }
+    }
+    None
+}
+
+/// What differs between two machines that should be in the same state.
+fn checkpoint(a: &Gigatron, b: &Gigatron) -> Option<String> {
+    let (state_a, state_b) = (a.state(), b.state());
+    if registers(&a.S) != registers(&b.S) {
+        Some("registers".to_string())
+    } else if a.S.undef != b.S.undef {
+        Some(format!("bus noise (undef ${:02x} vs ${:02x})", a.S.undef, b.S.undef))
+    } else if state_a.input != state_b.input {
+        Some("IN".to_string())
+    } else if let Some(addr) = state_a.ram.iter().zip(&state_b.ram).position(|(ra, rb)| ra != rb) {
+        Some(format!("RAM, from ${:04x}: ${:02x} vs ${:02x}", addr, state_a.ram[addr], state_b.ram[addr]))
+    } else if a.frame_hash() != b.frame_hash() {
+        Some("screen".to_string())
+    } else {
+        None
+    }
+}
+
+fn feed(gt: &mut Gigatron, replay: &mut Replay) {
+    while let Some(event) = replay.next(gt.cycles()) {
+        match event {
+            InputEvent::Key(code) => gt.port.key(code, gt.cycles()),
+            InputEvent::Joystick(mask) => gt.port.joystick(mask),
+        }
+    }
+}
+
+/// The registers that are compared; `undef` is bus noise, which only
+/// differs once something else already has.
+fn registers(S: &CpuState) -> [u16; 7] {
+    [S.PC, S.IR as u16, S.D as u16, S.AC as u16, S.X as u16, S.Y as u16, S.OUT as u16]
+}
+
+/// Describes the first pixel the screens differ in, if any.
+fn screen(a: &Gigatron, b: &Gigatron) -> Option<String> {
+    let (frame_a, frame_b) = (a.frame(), b.frame());
+    let i = frame_a.iter().zip(&frame_b).position(|(pa, pb)| pa != pb)?;
+    let (x, y) = (i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+    Some(format!("screen, from pixel ({}, {}): ${:02x} vs ${:02x}", x, y, frame_a[i], frame_b[i]))
+}
+
+/// The divergence with both sets of registers side by side, differing
+/// ones marked.
+pub fn report(d: &Divergence) -> String {
+    let mut lines = vec![
+        format!("Diverged at cycle {} (frame {}): {}", d.cycle, d.frame, d.what),
+        format!("{:<4} {:>6} {:>7}", "", "A", "B"),
+    ];
+    const NAMES: [&str; 7] = ["PC", "IR", "D", "AC", "X", "Y", "OUT"];
+    for ((name, a), b) in NAMES.iter().zip(registers(&d.a)).zip(registers(&d.b)) {
+        let width = if *name == "PC" { 4 } else { 2 };
+        let mark = if a != b { "  *" } else { "" };
+        lines.push(format!("{:<4} {:>6} {:>7}{}", name, format!("${:0w$x}", a, w = width), format!("${:0w$x}", b, w = width), mark));
+    }
+    lines.join("\n")
+}
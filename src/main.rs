@@ -1,101 +1,475 @@
+// Names follow the hardware and the reference gtemu.c simulator (PC, AC, ALU, ...).
+#![allow(non_snake_case, clippy::upper_case_acronyms)]
+
+mod batch;
+mod boot_trace;
+mod draw_trace;
+mod expansion_window;
+mod halt;
+mod input_socket;
+mod launcher;
+mod lockstep;
+mod logger;
+mod options;
+mod ram_window;
+mod session;
+mod shm_output;
+mod state_server;
+mod tty;
+mod watch;
 
 use log::{debug, error, info, trace, warn};
-use minifb::{Window, WindowOptions, Key};
-use rand::Rng;
+use minifb::{Window, WindowOptions, Key, KeyRepeat, MouseMode};
 use std::fs::File;
-use std::io::Read;
-use std::time::{Duration, Instant};
+use std::io::{BufRead, Read, Write};
+use std::time::Duration;
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use minifb::InputCallback;
 use std::rc::Rc;
+use std::cell::Cell;
+use gigatron_example::clock::{Clock, RealClock};
+use gigatron_example::debugger::{self, Command, Condition};
+use gigatron_example::disasm;
+use gigatron_example::error::GigatronError;
+use gigatron_example::font;
+use gigatron_example::gt1::{Gt1, VcpuVectors};
+use gigatron_example::gtv::{DeltaPlayer, DeltaRecorder};
+use gigatron_example::host_file::HostFile;
+use gigatron_example::input;
+use gigatron_example::patch::{self, Patch};
+use gigatron_example::png;
+use gigatron_example::ppm;
+use gigatron_example::replay::{InputEvent, Recorder, Replay};
+use gigatron_example::script;
+use gigatron_example::selftest;
+use gigatron_example::state::{CpuSnapshot, State};
+use gigatron_example::uart::Uart;
+use gigatron_example::{makeRGB, Edges, Gigatron, GigatronBuilder, Palette, BLANK, CLOCK_HZ, FRAME_CYCLES, RASTER_HEIGHT, RASTER_WIDTH, ROM6, SCREEN_HEIGHT, SCREEN_WIDTH, VISIBLE_X, VISIBLE_Y};
+use boot_trace::BootTrace;
+use draw_trace::DrawTrace;
+use expansion_window::ExpansionWindow;
+use input_socket::InputSocket;
+use launcher::Launcher;
+use halt::HaltWatch;
+use options::{Autorepeat, Frontend, HaltAction, Options, PpmSize, RamRange, RenderSize, Renderer, WriteBreak};
+use ram_window::RamWindow;
+use shm_output::ShmOutput;
+use state_server::StateServer;
+use session::Session;
+use tty::Tty;
+use watch::RomWatch;
+
+/// Region of the 160x120 logical screen that is scaled to fill the window.
+#[derive(Clone, Copy, Debug)]
+struct Crop {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Crop {
+    const FULL: Crop = Crop { x: 0, y: 0, w: 160, h: 120 };
 
-const ROM6: &[u8; 131072] = include_bytes!("../ROMv6.rom");
+    fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.w).contains(&x) && (self.y..self.y + self.h).contains(&y)
+    }
+}
 
-struct Color(u8, u8, u8);
+impl std::str::FromStr for Crop {
+    type Err = ();
 
-fn bright(v: u8) -> u8 {
-    match v {
-        0b0000_0011 => 0b1111_1111,
-        0b0000_0010 => 0b0011_1111,
-        0b0000_0001 => 0b0000_1111,
-        0b0000_0000 => 0b0000_0011,
-        _ => unreachable!(),
+    /// Parses `X,Y,W,H` in logical pixels, which must fit the 160x120 screen.
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let v: Vec<usize> = s.split(',')
+            .map(|f| f.trim().parse().map_err(|_| ()))
+            .collect::<Result<_, _>>()?;
+        match v[..] {
+            [x, y, w, h] if w > 0 && h > 0 && x.checked_add(w).is_some_and(|r| r <= 160) && y.checked_add(h).is_some_and(|b| b <= 120) => Ok(Crop { x, y, w, h }),
+            _ => Err(()),
+        }
     }
 }
 
-fn unpack_color(reg: u8) -> Color {
-    let reg = reg & 0b0011_1111;
+/// Integer zoom of the window over the 160x120 logical screen.
+const DEFAULT_ZOOM: usize = 4; // 640x480
+const ZOOM_MIN: usize = 1;
+const ZOOM_MAX: usize = 8;
+
+/// Grid guides (F11): every 8 logical pixels unless --grid says otherwise,
+/// lightening what they cross a quarter of the way to white.
+const DEFAULT_GRID: usize = 8;
+const GRID_COLOR: u32 = 0x00FF_FFFF;
+
+/// Cycles between CPU samples (F1): a frame and a bit, so they drift
+/// through the frame rather than landing at the same point of the video
+/// loop every time.
+const CPU_SAMPLE_CYCLES: u64 = FRAME_CYCLES + 1237;
+
+const BOOT_PATH: usize = 16; // Instructions --show-boot logs
+
+/// The emulator with a screen and host input attached.
+struct Emulator {
+    gt: Gigatron,
+    video: VGA,
+    expansion: Option<ExpansionWindow>, // --show-expansion
+    ram_window: Option<RamWindow>,      // --show-ram (F9)
+    joy: u8, // Controller byte last sent to the port
+    socket: Option<InputSocket>, // --input-socket ...
+    socket_joy: u8,              // ... and the state it last sent, held along with the keys
+    launcher: Option<Launcher>,  // --launch-game ...
+    launcher_joy: u8,            // ... and the buttons it holds, likewise
+    state_server: Option<StateServer>, // --state-server
+    shm_output: Option<ShmOutput>,     // --shm-output
+    active: bool,
+    cycles_per_frame: Option<u64>, // Present every N cycles instead of on vSync
+    frame_cycles: u64,
+    cold_start: bool, // Present the garbled RAM while reset is held (--cold-start)
+    recorder: Option<Recorder>,
+    delta: Option<DeltaRecorder>, // --record-delta
+    draw_trace: Option<DrawTrace>, // --trace-draws
+    boot_trace: Option<BootTrace>, // --boot-trace
+    replay: Option<Replay>,
+    renderer: Renderer,
+    border: u32, // Shown for blanked beam positions with the beam renderer, and around the picture
+    palette: Palette,
+    crop: Crop,
+    render_ram: Option<RamRange>, // Black out pixels read from elsewhere (--render-ram)
+    pixel_aspect: Option<f64>, // Width over height of a shown pixel; None stretches to the window
+    test_pattern: bool, // No ROM, show the test pattern instead of running
+    show_input: bool,   // Overlay the input bytes presented to the ROM (F3)
+    show_sync: bool,    // Overlay the sync edge counts (F4)
+    show_pad: bool,     // Show the controller as lit buttons, in the window only (Shift+F3)
+    show_help: bool,    // List the hotkeys over the screen (Shift+F1)
+    grid: usize,        // Logical pixels between guide lines ...
+    show_grid: bool,    // ... drawn over the screen (F11)
+    show_coords: bool,  // Show the logical pixel under the mouse (F12)
+    show_cpu: bool,     // Show a sample of the instruction and registers (F1) ...
+    cpu_sample_in: u64, // ... taken again after this many cycles
+    sync: SyncCounts,
+    profile_sync: bool, // Log the sync edge counts once a second
+    paused: bool,       // In the debugger, waiting for commands on stdin (F5)
+    step_preview: bool, // Redraw the screen after every debugger step
+    checkpoint: Option<CpuSnapshot>, // Registers saved by the debugger's checkpoint
+    breaks: Vec<(Condition, bool)>, // --break, and whether each held before the last cycle
+    break_hit: Rc<Cell<Option<(u16, u8)>>>, // Store that met --break-on-write, set by its watch
+    executing: u16,     // ROM address of the instruction the next step executes
+    debug_script: VecDeque<Command>, // --debug-script commands not run yet, taken before stdin
+    debug_record: Option<File>,      // Where typed debugger commands go (--debug-record)
+    zoom: usize,        // Window pixels per logical pixel (Ctrl +/-)
+    flip_x: bool,       // Mirror the screen left to right (F6)
+    flip_y: bool,       // ... and top to bottom (F7)
+    crt: bool,          // Darken every other row (F8)
+    crt_bleed: bool,    // ... and smear each pixel into the next one
+    vblank_profile: Option<VblankProfile>,
+    render_profile: Option<RenderProfile>, // --profile-render
+    drift: Option<Drift>, // --profile-drift
+    refresh: Option<RefreshRate>, // --profile-refresh
+    pacer: Option<Pacer>, // --speed
+    fast_forward: bool,   // Page Down held: unpaced until it is let go
+    watch: Option<RomWatch>, // --watch
+    frames: Option<u64>,     // Stop once this many frames have been completed
+    capture: Option<Capture>, // --capture-at-frame
+    max_cycles: Option<u64>, // Stop once this many cycles have run (--max-cycles) ...
+    cycle_limit_hit: bool,   // ... which counts as a failure
+    halt_watch: HaltWatch,
+    on_halt: HaltAction,
+    crashed: bool, // Exited on a halt that looks like a crash, also a failure
+    clock: Box<dyn Clock>,   // Host time for pacing and statistics
+    last_key: Option<u8>,
+    last_joy: u8,
+}
+
+/// Vertical blank lengths seen since the last report (--profile-vblank).
+struct VblankProfile {
+    since: Duration,
+    frames: u64,
+    total: u64,
+    min: u64,
+    max: u64,
+}
 
-    let red: u8 = reg & 0b0000_0011;
-    let green: u8 = (reg >> 2) & 0b0000_0011;
-    let blue: u8 = (reg >> 4) & 0b0000_0011;
+impl VblankProfile {
+    fn new(now: Duration) -> Self {
+        VblankProfile { since: now, frames: 0, total: 0, min: u64::MAX, max: 0 }
+    }
 
-    let red = bright(red);
-    let green = bright(green);
-    let blue = bright(blue);
+    fn add(&mut self, cycles: u64) {
+        self.frames += 1;
+        self.total += cycles;
+        self.min = self.min.min(cycles);
+        self.max = self.max.max(cycles);
+    }
 
-    Color(red, green, blue)
+    /// Logs the statistic and starts over once a second has passed.
+    fn report(&mut self, now: Duration) {
+        if now - self.since < Duration::from_secs(1) || self.frames == 0 {
+            return;
+        }
+        let average = self.total / self.frames;
+        info!(
+            "vblank: {} cycles ({:.2} ms, {:.1}% of the frame), min {}, max {}, over {} frames",
+            average,
+            average as f64 / CLOCK_HZ * 1000.0,
+            average as f64 * 100.0 / FRAME_CYCLES as f64,
+            self.min,
+            self.max,
+            self.frames
+        );
+        *self = VblankProfile::new(now);
+    }
 }
 
-fn makeRGB(c: &Color) -> u32 {
-    let mut color: u32 = 0;
-    color |= (c.0 as u32) << 16;   // R
-    color |= (c.1 as u32) << 8;    // G
-    color |= (c.2 as u32) << 0;    // B
-    return color;
+/// Host time taken by one stage of showing a frame, over the frames since
+/// the last report.
+struct Timings {
+    frames: u32,
+    total: Duration,
+    min: Duration,
+    max: Duration,
 }
 
-#[derive(Debug)]
-enum Register {
-    AC,
-    X,
-    Y,
-    OUT,
+impl Timings {
+    fn new() -> Self {
+        Timings { frames: 0, total: Duration::ZERO, min: Duration::MAX, max: Duration::ZERO }
+    }
+
+    fn add(&mut self, took: Duration) {
+        self.frames += 1;
+        self.total += took;
+        self.min = self.min.min(took);
+        self.max = self.max.max(took);
+    }
+
+    fn text(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        format!(
+            "{:.2} ms (min {:.2}, max {:.2})",
+            ms(self.total / self.frames.max(1)),
+            ms(if self.frames == 0 { Duration::ZERO } else { self.min }),
+            ms(self.max)
+        )
+    }
 }
 
-#[derive(Debug, Clone)]
-struct CpuState {
-    PC: u16,
-    IR: u8,
-    D: u8,
-    AC: u8,
-    X: u8,
-    Y: u8,
-    OUT: u8,
-    undef: u8,
+/// How long rebuilding the back buffer and presenting it to the window
+/// take (--profile-render), to tell which one holds the frame rate back.
+struct RenderProfile {
+    since: Duration,
+    render: Timings,  // Reconstructing the screen, overlays included
+    present: Timings, // Handing it to the window, without the --speed wait
 }
 
-struct Gigatron {
-    ROM: [[u8; 2]; 1 << 16],
-    RAM: [u8; 1 << 15],
-    IN: u8,
-    S: CpuState,
-    video: VGA,
-    vgaX: i32,
-    vgaY: i32,
-    t: i64,
-    joy: Option<Direction>,
-    active: bool,
+impl RenderProfile {
+    fn new(now: Duration) -> Self {
+        RenderProfile { since: now, render: Timings::new(), present: Timings::new() }
+    }
+
+    /// Logs the timings and starts over once a second has passed.
+    fn report(&mut self, now: Duration) {
+        if now - self.since < Duration::from_secs(1) || self.present.frames == 0 {
+            return;
+        }
+        info!(
+            "render: {} over {} frames, present: {} over {} frames",
+            self.render.text(),
+            self.render.frames,
+            self.present.text(),
+            self.present.frames
+        );
+        *self = RenderProfile::new(now);
+    }
 }
 
-fn E(W: bool, p: Register) -> Option<Register> {
-    if W { None } else { Some(p) } // Disable AC and OUT loading during RAM write
+/// Running counts of the sync edges the ROM sends, shown with F4 and
+/// logged once a second with --profile-sync. A ROM with standard timing
+/// sends RASTER_HEIGHT (521) hSyncs per vSync; a different ratio means
+/// sync is lost or the ROM's video loop is off, and the screen would roll.
+struct SyncCounts {
+    hsyncs: u64,             // Since start ...
+    vsyncs: u64,             // ... likewise
+    lines: u64,              // hSyncs since the last vSync ...
+    last_lines: Option<u64>, // ... and between the last two
+    framed: u64,             // hSyncs between the first vSync and the last
+    since: Duration,         // Last report
+}
+
+impl SyncCounts {
+    fn new(now: Duration) -> Self {
+        SyncCounts { hsyncs: 0, vsyncs: 0, lines: 0, last_lines: None, framed: 0, since: now }
+    }
+
+    fn add(&mut self, edges: Edges) {
+        if edges.hSync {
+            self.hsyncs += 1;
+            self.lines += 1;
+        }
+        if edges.vSync {
+            if self.vsyncs > 0 {
+                self.last_lines = Some(self.lines);
+                self.framed += self.lines;
+            }
+            self.vsyncs += 1;
+            self.lines = 0;
+        }
+    }
+
+    /// hSyncs per vSync over the whole frames, leaving out the lines
+    /// before the first vSync.
+    fn ratio(&self) -> f64 {
+        self.framed as f64 / self.vsyncs.saturating_sub(1).max(1) as f64
+    }
+
+    fn text(&self) -> String {
+        let last = self.last_lines.map_or("--".to_string(), |n| n.to_string());
+        format!("H {}  V {}  H/V {:.1}  LAST {}", self.hsyncs, self.vsyncs, self.ratio(), last)
+    }
+
+    /// Logs the counts once a second has passed.
+    fn report(&mut self, now: Duration) {
+        if now - self.since < Duration::from_secs(1) {
+            return;
+        }
+        let last = self.last_lines.map_or("none yet".to_string(), |n| n.to_string());
+        info!(
+            "sync: {} hSyncs, {} vSyncs, {:.2} per frame (last frame {}, expected {})",
+            self.hsyncs, self.vsyncs, self.ratio(), last, RASTER_HEIGHT
+        );
+        self.since = now;
+    }
+}
+
+/// Emulated time against wall-clock time since start (--profile-drift).
+struct Drift {
+    start: Duration,
+    cycles: u64,           // Emulated cycles at start
+    last: (Duration, u64), // Clock time and cycles at the last report
+}
+
+impl Drift {
+    fn new(now: Duration, cycles: u64) -> Self {
+        Drift { start: now, cycles, last: (now, cycles) }
+    }
+
+    /// Seconds the emulator is ahead of real time, negative when behind.
+    fn drift(&self, now: Duration, cycles: u64) -> f64 {
+        let emulated = (cycles - self.cycles) as f64 / CLOCK_HZ;
+        emulated - (now - self.start).as_secs_f64()
+    }
+
+    /// Logs the drift and the speed over the last second, once a second has
+    /// passed. A reset restarts the cycle count, and the measurement with it.
+    fn report(&mut self, now: Duration, cycles: u64) {
+        if cycles < self.cycles {
+            *self = Drift::new(now, cycles);
+            return;
+        }
+        let (then, then_cycles) = self.last;
+        if now - then < Duration::from_secs(1) {
+            return;
+        }
+        let drift = self.drift(now, cycles);
+        let speed = (cycles - then_cycles) as f64 / CLOCK_HZ / (now - then).as_secs_f64();
+        let direction = if drift < 0.0 { "behind" } else { "ahead of" };
+        info!("drift: {:.1} ms {} real time, {:.1}% speed over the last second", drift.abs() * 1000.0, direction, speed * 100.0);
+        self.last = (now, cycles);
+    }
+}
+
+/// The ROM's refresh rate, from the emulated cycles between vSync edges
+/// (--profile-refresh).
+struct RefreshRate {
+    last: Option<u64>,     // Cycle of the previous vSync edge
+    run: Vec<u64>,         // Intervals agreeing with the first of them
+    reported: Option<f64>, // Rate last logged
+}
+
+/// Consecutive frames whose lengths must agree to within
+/// REFRESH_TOLERANCE before the rate is logged.
+const REFRESH_FRAMES: usize = 30;
+const REFRESH_TOLERANCE: f64 = 0.001;
+
+impl RefreshRate {
+    fn new() -> Self {
+        RefreshRate { last: None, run: Vec::new(), reported: None }
+    }
+
+    /// Takes the vSync edge at `cycles`, and logs the rate whenever it has
+    /// settled on a new value, as after switching video modes.
+    fn vsync(&mut self, cycles: u64) {
+        let last = self.last.replace(cycles);
+        let Some(interval) = last.and_then(|last| cycles.checked_sub(last)).filter(|&n| n > 0) else {
+            self.run.clear(); // First edge, or the machine was reset
+            return;
+        };
+        if let Some(&first) = self.run.first()
+            && (interval as f64 - first as f64).abs() > first as f64 * REFRESH_TOLERANCE
+        {
+            self.run.clear();
+        }
+        self.run.push(interval);
+        if self.run.len() < REFRESH_FRAMES {
+            return;
+        }
+        let average = self.run.iter().sum::<u64>() as f64 / self.run.len() as f64;
+        let hz = CLOCK_HZ / average;
+        self.run.clear();
+        if self.reported.is_some_and(|reported| (hz - reported).abs() < 0.005) {
+            return;
+        }
+        info!("refresh: {:.2} Hz ({:.0} cycles from vSync to vSync)", hz, average);
+        self.reported = Some(hz);
+    }
 }
 
-fn makeAddr(hi: u8, lo: u8) -> u16 {
-    (hi as u16) << 8 | lo as u16
+/// Holds emulated time to a multiple of real time (--speed).
+struct Pacer {
+    speed: f64,
+    start: Duration, // Clock time at which ...
+    cycles: u64,     // ... the machine was at this cycle
 }
 
-fn busy_wait(target_duration: Duration) {
-    let start = Instant::now();
-    while start.elapsed() < target_duration {
-        std::hint::spin_loop();
+/// How far behind the pacer lets the emulator fall before it gives up on
+/// catching up, as after sitting in the debugger.
+const PACE_SLACK: Duration = Duration::from_millis(250);
+
+impl Pacer {
+    fn new(speed: f64, now: Duration, cycles: u64) -> Self {
+        Pacer { speed, start: now, cycles }
+    }
+
+    /// Paces from `cycles` at `now` on, forgetting how far ahead or behind
+    /// emulation was.
+    fn restart(&mut self, now: Duration, cycles: u64) {
+        *self = Pacer::new(self.speed, now, cycles);
+    }
+
+    /// Waits until real time has caught up with `cycles`.
+    fn wait(&mut self, clock: &dyn Clock, cycles: u64) {
+        let now = clock.now();
+        if cycles < self.cycles {
+            *self = Pacer::new(self.speed, now, cycles); // Reset
+            return;
+        }
+        let due = self.start + Duration::from_secs_f64((cycles - self.cycles) as f64 / (CLOCK_HZ * self.speed));
+        if now < due {
+            clock.sleep(due - now);
+        } else if now - due > PACE_SLACK {
+            *self = Pacer::new(self.speed, now, cycles);
+        }
     }
 }
 
+/// Three parts `a` to one part `b`, per channel.
+fn blend(a: u32, b: u32) -> u32 {
+    let channel = |shift: u32| (((a >> shift & 0xff) * 3 + (b >> shift & 0xff)) / 4) << shift;
+    channel(16) | channel(8) | channel(0)
+}
+
 type KeyVec = Rc<RefCell<Vec<u32>>>;
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum Direction {
     Up,
     Left,
@@ -107,454 +481,2132 @@ enum Direction {
     Select,
 }
 
+impl Direction {
+    /// Active-low controller bit.
+    fn mask(&self) -> u8 {
+        match self {
+            Direction::Right => !input::BUTTON_RIGHT,
+            Direction::Left => !input::BUTTON_LEFT,
+            Direction::Down => !input::BUTTON_DOWN,
+            Direction::Up => !input::BUTTON_UP,
+            Direction::Start => !input::BUTTON_START,
+            Direction::Select => !input::BUTTON_SELECT,
+            Direction::ButtonB => !input::BUTTON_B,
+            Direction::ButtonA => !input::BUTTON_A,
+        }
+    }
+}
+
+/// What a key does, as [`BINDINGS`] ties it to the key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Action {
+    CpuMonitor,
+    Help,
+    Reset,
+    WarmReset,
+    InputBytes,
+    PadDisplay,
+    SyncCounts,
+    Debugger,
+    MirrorX,
+    MirrorY,
+    Scanlines,
+    SwapRenderer,
+    RamWindow,
+    Quit,
+    Grid,
+    Coords,
+    ZoomIn,
+    ZoomOut,
+    FastForward,
+    Button(Direction),
+}
+
+/// The modifiers a binding wants down. None wants neither Shift nor Ctrl,
+/// so F1 and Shift+F1 are told apart; Any doesn't look.
+#[derive(Clone, Copy, PartialEq)]
+enum Mods {
+    None,
+    Shift,
+    Ctrl,
+    Any,
+}
+
+/// When a binding acts.
+#[derive(Clone, Copy, PartialEq)]
+enum Trigger {
+    Press, // Once per press, at the presented frame (process_hotkeys)
+    Poll,  // At every input poll while down (process_system)
+    Held,  // Read as down or up: fast-forward and the controller
+}
+
+struct Binding {
+    key: Key,
+    mods: Mods,
+    trigger: Trigger,
+    action: Action,
+    description: &'static str,
+}
+
+const fn bind(key: Key, mods: Mods, trigger: Trigger, action: Action, description: &'static str) -> Binding {
+    Binding { key, mods, trigger, action, description }
+}
+
+/// Every key the window acts on, in the order the help (Shift+F1) lists
+/// them. The hotkeys dispatch from here, the controller is read from here,
+/// and a new key only needs its line and its Action.
+const BINDINGS: [Binding; 29] = [
+    bind(Key::F1, Mods::None, Trigger::Press, Action::CpuMonitor, "CPU monitor"),
+    bind(Key::F1, Mods::Shift, Trigger::Press, Action::Help, "This help"),
+    bind(Key::F2, Mods::None, Trigger::Poll, Action::Reset, "Reset, held"),
+    bind(Key::F2, Mods::Shift, Trigger::Press, Action::WarmReset, "Warm reset, RAM kept"),
+    bind(Key::F3, Mods::None, Trigger::Press, Action::InputBytes, "Input bytes"),
+    bind(Key::F3, Mods::Shift, Trigger::Press, Action::PadDisplay, "Controller display"),
+    bind(Key::F4, Mods::None, Trigger::Press, Action::SyncCounts, "Sync edge counts"),
+    bind(Key::F5, Mods::None, Trigger::Press, Action::Debugger, "Debugger"),
+    bind(Key::F6, Mods::None, Trigger::Press, Action::MirrorX, "Mirror left to right"),
+    bind(Key::F7, Mods::None, Trigger::Press, Action::MirrorY, "Mirror top to bottom"),
+    bind(Key::F8, Mods::None, Trigger::Press, Action::Scanlines, "CRT scanlines"),
+    bind(Key::F8, Mods::Shift, Trigger::Press, Action::SwapRenderer, "Table/beam renderer"),
+    bind(Key::F9, Mods::None, Trigger::Press, Action::RamWindow, "RAM map window"),
+    bind(Key::F10, Mods::Any, Trigger::Poll, Action::Quit, "Quit"),
+    bind(Key::F11, Mods::None, Trigger::Press, Action::Grid, "Guide grid"),
+    bind(Key::F12, Mods::None, Trigger::Press, Action::Coords, "Pixel under the mouse"),
+    bind(Key::NumPadPlus, Mods::Any, Trigger::Press, Action::ZoomIn, "Zoom in"),
+    bind(Key::Equal, Mods::Ctrl, Trigger::Press, Action::ZoomIn, "Zoom in"),
+    bind(Key::NumPadMinus, Mods::Any, Trigger::Press, Action::ZoomOut, "Zoom out"),
+    bind(Key::Minus, Mods::Ctrl, Trigger::Press, Action::ZoomOut, "Zoom out"),
+    bind(Key::PageDown, Mods::Any, Trigger::Held, Action::FastForward, "Fast-forward, held"),
+    bind(Key::Up, Mods::Any, Trigger::Held, Action::Button(Direction::Up), "Controller up"),
+    bind(Key::Down, Mods::Any, Trigger::Held, Action::Button(Direction::Down), "Controller down"),
+    bind(Key::Left, Mods::Any, Trigger::Held, Action::Button(Direction::Left), "Controller left"),
+    bind(Key::Right, Mods::Any, Trigger::Held, Action::Button(Direction::Right), "Controller right"),
+    bind(Key::Enter, Mods::Any, Trigger::Held, Action::Button(Direction::Start), "Start"),
+    bind(Key::Backspace, Mods::Any, Trigger::Held, Action::Button(Direction::Select), "Select"),
+    bind(Key::Space, Mods::Any, Trigger::Held, Action::Button(Direction::ButtonA), "A"),
+    bind(Key::Tab, Mods::Any, Trigger::Held, Action::Button(Direction::ButtonB), "B"),
+];
+
+impl Binding {
+    /// The key as the help names it, with its modifier.
+    fn label(&self) -> String {
+        let key = match self.key {
+            Key::NumPadPlus => "Num+".to_string(),
+            Key::NumPadMinus => "Num-".to_string(),
+            Key::Equal => "=".to_string(),
+            Key::Minus => "-".to_string(),
+            Key::Backspace => "Bksp".to_string(),
+            Key::PageDown => "Page Down".to_string(),
+            key => format!("{:?}", key),
+        };
+        match self.mods {
+            Mods::Shift => format!("Shift+{}", key),
+            Mods::Ctrl => format!("Ctrl+{}", key),
+            Mods::None | Mods::Any => key,
+        }
+    }
+}
+
+/// The help overlay's lines, one per BINDINGS entry, except that keys
+/// next to each other doing the same share a line.
+fn help_lines() -> Vec<String> {
+    let rows: Vec<(String, &str)> = BINDINGS
+        .chunk_by(|a, b| a.action == b.action)
+        .map(|same| (same.iter().map(Binding::label).collect::<Vec<_>>().join(" "), same[0].description))
+        .collect();
+    let width = rows.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+    rows.iter().map(|(keys, description)| format!("{:<width$}  {}", keys, description)).collect()
+}
+
+/// Where presented frames go.
+enum Output {
+    Window(Box<Window>),
+    Tty(Tty), // No input, Ctrl-C to quit
+    Headless, // Frames are only kept for --screenshot
+}
+
 struct VGA {
     width: usize,
     height: usize,
-    buffer: Vec<u32>,
-    window: Window,
+    buffer: Vec<u32>, // Presented frame
+    back: Vec<u32>,   // Frame being reconstructed, swapped in on vSync
+    output: Output,
     keys: KeyVec,
+    target_fps: Option<usize>, // Reapplied when the window is recreated
+    key_path: KeyPath,
+    poll_keys: bool, // Keys pressed since the last update weren't read yet
+    autorepeat: Autorepeat,
+    held: Option<HeldKey>, // Of the last character typed, while it can repeat
+    native: bool, // Buffers are 160x120 and the window scales them up (--render native-res)
+    hud: Vec<String>, // Lines the window shows over the frame, top right, but the buffer never has
+    hud_scale: usize,
+    pad: Option<u8>, // Controller byte (active-low) shown bottom center, also never in the buffer
+    shown: Vec<u32>, // The buffer with the hud and the pad drawn on it
 }
 
 struct Input {
     keys: KeyVec,
 }
 
+/// A frame to save as it is presented (--capture-at-frame).
+struct Capture {
+    frame: u64, // vSync count
+    file: String,
+    pause: bool, // Break into the debugger after saving it
+}
+
+/// A key held down after typing `c`, which types it again at `next`.
+struct HeldKey {
+    c: char,
+    key: Key,
+    next: Duration,
+}
+
+/// The key that types `c` (US layout), to tell whether it is still held.
+fn key_for(c: char) -> Option<Key> {
+    match c {
+        '\n' | '\r' => Some(Key::Enter),
+        '\u{8}' | '\u{7f}' => Some(Key::Backspace),
+        '\t' => Some(Key::Tab),
+        _ => KEY_CHARS.iter().find(|&&(_, plain, shifted)| c == plain || c == shifted).map(|&(key, _, _)| key),
+    }
+}
+
+/// How typed characters reach the emulator. minifb's character callback
+/// knows the keyboard layout, but doesn't fire on some platforms (Wayland
+/// among them); there the keys pressed are read and mapped as on a US
+/// keyboard instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KeyPath {
+    Unknown,  // Nothing typed yet
+    Callback, // The callback has delivered characters, so it is used alone
+    Polled,   // Keys were pressed without any characters, so they are mapped
+}
+
+/// Characters of the keys the polled path maps, unshifted and shifted.
+const KEY_CHARS: [(Key, char, char); 48] = [
+    (Key::A, 'a', 'A'), (Key::B, 'b', 'B'), (Key::C, 'c', 'C'), (Key::D, 'd', 'D'),
+    (Key::E, 'e', 'E'), (Key::F, 'f', 'F'), (Key::G, 'g', 'G'), (Key::H, 'h', 'H'),
+    (Key::I, 'i', 'I'), (Key::J, 'j', 'J'), (Key::K, 'k', 'K'), (Key::L, 'l', 'L'),
+    (Key::M, 'm', 'M'), (Key::N, 'n', 'N'), (Key::O, 'o', 'O'), (Key::P, 'p', 'P'),
+    (Key::Q, 'q', 'Q'), (Key::R, 'r', 'R'), (Key::S, 's', 'S'), (Key::T, 't', 'T'),
+    (Key::U, 'u', 'U'), (Key::V, 'v', 'V'), (Key::W, 'w', 'W'), (Key::X, 'x', 'X'),
+    (Key::Y, 'y', 'Y'), (Key::Z, 'z', 'Z'),
+    (Key::Key0, '0', ')'), (Key::Key1, '1', '!'), (Key::Key2, '2', '@'), (Key::Key3, '3', '#'),
+    (Key::Key4, '4', '$'), (Key::Key5, '5', '%'), (Key::Key6, '6', '^'), (Key::Key7, '7', '&'),
+    (Key::Key8, '8', '*'), (Key::Key9, '9', '('),
+    (Key::Apostrophe, '\'', '"'), (Key::Backquote, '`', '~'), (Key::Backslash, '\\', '|'),
+    (Key::Comma, ',', '<'), (Key::Equal, '=', '+'), (Key::LeftBracket, '[', '{'),
+    (Key::Minus, '-', '_'), (Key::Period, '.', '>'), (Key::RightBracket, ']', '}'),
+    (Key::Semicolon, ';', ':'), (Key::Slash, '/', '?'),
+    (Key::Space, ' ', ' '),
+];
+
 impl InputCallback for Input {
     fn add_char(&mut self, uni_char: u32) {
         self.keys.borrow_mut().push(uni_char);
     }
 }
 
+/// Draws `text` into `buffer`, `width` by `height`, at (`x`, `y`) on a
+/// black box, each font pixel `scale` pixels wide.
+fn draw_text(buffer: &mut [u32], width: usize, height: usize, text: &str, x: usize, y: usize, scale: usize) {
+    let w = (text.len() * font::ADVANCE + 1) * scale;
+    let h = (font::HEIGHT + 2) * scale;
+    for py in y..(y + h).min(height) {
+        for px in x..(x + w).min(width) {
+            buffer[py * width + px] = 0;
+        }
+    }
+    font::draw(text, 1, 1, |fx, fy| {
+        for py in y + fy * scale..y + (fy + 1) * scale {
+            for px in x + fx * scale..x + (fx + 1) * scale {
+                if px < width && py < height {
+                    buffer[py * width + px] = 0x00FF_FFFF;
+                }
+            }
+        }
+    });
+}
+
+/// Where each controller button is drawn by draw_pad, as (bit, x, y, w,
+/// h) in units of two font pixels: the directions as a cross, Select and
+/// Start in the middle, then B and A.
+const PAD: [(u8, usize, usize, usize, usize); 8] = [
+    (input::BUTTON_UP, 3, 0, 3, 3),
+    (input::BUTTON_LEFT, 0, 3, 3, 3),
+    (input::BUTTON_RIGHT, 6, 3, 3, 3),
+    (input::BUTTON_DOWN, 3, 6, 3, 3),
+    (input::BUTTON_SELECT, 12, 4, 3, 2),
+    (input::BUTTON_START, 16, 4, 3, 2),
+    (input::BUTTON_B, 22, 3, 3, 3),
+    (input::BUTTON_A, 26, 3, 3, 3),
+];
+const PAD_SIZE: (usize, usize) = (29, 9);
+
+/// Draws the controller, lit where `mask` (active-low) has a button held,
+/// bottom center on a black box, at twice the input overlay's scale.
+fn draw_pad(buffer: &mut [u32], width: usize, height: usize, mask: u8) {
+    let scale = (height / SCREEN_HEIGHT).clamp(1, 3) * 2;
+    let (w, h) = ((PAD_SIZE.0 + 2) * scale, (PAD_SIZE.1 + 2) * scale);
+    let (x, y) = (width.saturating_sub(w) / 2, height.saturating_sub(h + 4));
+    let mut fill = |bx: usize, by: usize, bw: usize, bh: usize, color: u32| {
+        for py in y + by * scale..(y + (by + bh) * scale).min(height) {
+            for px in x + bx * scale..(x + (bx + bw) * scale).min(width) {
+                buffer[py * width + px] = color;
+            }
+        }
+    };
+    fill(0, 0, PAD_SIZE.0 + 2, PAD_SIZE.1 + 2, 0);
+    for (bit, bx, by, bw, bh) in PAD {
+        let color = if mask & bit == 0 { 0x00FF_FFFF } else { 0x0040_4040 };
+        fill(bx + 1, by + 1, bw, bh, color);
+    }
+}
+
+fn open_window(width: usize, height: usize, keys: &KeyVec) -> Box<Window> {
+    let mut window = Window::new("Gigatron TTL Simulator (c) Vitold S", width, height, WindowOptions::default()).unwrap();
+    window.set_input_callback(Box::new(Input { keys: keys.clone() }));
+    Box::new(window)
+}
+
 impl VGA {
     fn new(width: usize, height: usize) -> Self {
-        let mut buffer: Vec<u32> = vec![0u32; width * height];
-        let mut window = Window::new("Gigatron TTL Simulator (c) Vitold S", width, height, WindowOptions::default()).unwrap();
         let keys = KeyVec::new(RefCell::new(Vec::new()));
-        window.set_input_callback(Box::new(Input { keys: keys.clone() }));
+        VGA::with_output(width, height, Output::Window(open_window(width, height, &keys)), keys)
+    }
+
+    /// A `width` x `height` window showing a buffer of the logical screen's
+    /// size, which minifb stretches to fit (its default scale mode) instead
+    /// of the emulator writing every window pixel.
+    fn native(width: usize, height: usize) -> Self {
+        let keys = KeyVec::new(RefCell::new(Vec::new()));
+        let window = open_window(width, height, &keys);
+        let mut vga = VGA::with_output(SCREEN_WIDTH, SCREEN_HEIGHT, Output::Window(window), keys);
+        vga.native = true;
+        vga
+    }
+
+    fn tty(width: usize, height: usize) -> Self {
+        VGA::with_output(width, height, Output::Tty(Tty::new()), KeyVec::default())
+    }
+
+    fn headless(width: usize, height: usize) -> Self {
+        VGA::with_output(width, height, Output::Headless, KeyVec::default())
+    }
+
+    fn with_output(width: usize, height: usize, output: Output, keys: KeyVec) -> Self {
+        let buffer: Vec<u32> = vec![0u32; width * height];
+        let back: Vec<u32> = vec![0u32; width * height];
         VGA {
             width,
             height,
             buffer,
-            window,
+            back,
+            output,
             keys,
+            target_fps: None,
+            key_path: KeyPath::Unknown,
+            poll_keys: false,
+            autorepeat: Autorepeat::DEFAULT,
+            held: None,
+            native: false,
+            hud: Vec::new(),
+            hud_scale: 1,
+            pad: None,
+            shown: Vec::new(),
+        }
+    }
+
+    /// Recreates the window at the new size, with blank buffers to match
+    /// unless they stay at the logical size. The terminal keeps its size.
+    fn resize(&mut self, width: usize, height: usize) {
+        let Output::Window(window) = &mut self.output else {
+            return;
+        };
+        *window = open_window(width, height, &self.keys);
+        if let Some(fps) = self.target_fps {
+            window.set_target_fps(fps);
         }
+        if self.native {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        self.buffer = vec![0u32; width * height];
+        self.back = vec![0u32; width * height];
+    }
 
+    /// Makes the completed back buffer the presented frame.
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.buffer, &mut self.back);
     }
 
-    fn put(&mut self, vgaX: usize, vgaY: usize, color: u32) {
-        if vgaX < self.width && vgaY < self.height {
-            let offset: usize = vgaY as usize * self.width + vgaX as usize;
-            //self.buffer[offset] = 0xFF00_0000 | 0x00FF_0000; // ARGB (красный)
-            self.buffer[offset] = 0xFF00_0000 | color;
+    fn set_target_fps(&mut self, fps: usize) {
+        self.target_fps = Some(fps);
+        if let Output::Window(window) = &mut self.output {
+            window.set_target_fps(fps);
         }
     }
 
-    fn update(&mut self) {
-        if self.window.is_open() {
-            self.window
-                .update_with_buffer(&self.buffer, self.width, self.height)
-                .unwrap();
+    /// Lifts the target frame rate while fast-forwarding, and puts it back.
+    fn set_unpaced(&mut self, unpaced: bool) {
+        if let (Output::Window(window), Some(fps)) = (&mut self.output, self.target_fps) {
+            window.set_target_fps(if unpaced { 0 } else { fps });
         }
     }
 
-    fn check_key(&mut self) -> Option<char> {
-        let mut keys = self.keys.borrow_mut();
-        let mut key = None;
-        for t in keys.iter() {
-            key = char::from_u32(*t);
+    /// Shows the buffer; `now` is the frontend's clock, for the tty's frame skip.
+    fn update(&mut self, now: Duration) {
+        match &mut self.output {
+            Output::Window(window) => {
+                if window.is_open() {
+                    let shown = if self.hud.is_empty() && self.pad.is_none() {
+                        &self.buffer
+                    } else {
+                        // On a copy, so screenshots and recordings taken
+                        // from the buffer stay clean
+                        self.shown.clone_from(&self.buffer);
+                        let (scale, width, height) = (self.hud_scale, self.width, self.height);
+                        let h = (font::HEIGHT + 2) * scale;
+                        for (i, line) in self.hud.iter().enumerate() {
+                            let w = (line.len() * font::ADVANCE + 1) * scale;
+                            draw_text(&mut self.shown, width, height, line, width.saturating_sub(w + 4), 4 + i * h, scale);
+                        }
+                        if let Some(mask) = self.pad {
+                            draw_pad(&mut self.shown, width, height, mask);
+                        }
+                        &self.shown
+                    };
+                    window.update_with_buffer(shown, self.width, self.height).unwrap();
+                    self.poll_keys = true;
+                }
+            }
+            Output::Tty(tty) => tty.draw(&self.buffer, self.width, self.height, now),
+            Output::Headless => {}
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        match &self.output {
+            Output::Window(window) => window.is_open(),
+            Output::Tty(_) | Output::Headless => true,
         }
-        keys.clear();
-        key
     }
 
-    fn check_joystick(&mut self) -> Option<Direction> {
-        let mut result: Option<Direction> = None;
-        if self.window.is_key_down(Key::Up) {
-            result = Some(Direction::Up);
+    fn is_key_down(&self, key: Key) -> bool {
+        match &self.output {
+            Output::Window(window) => window.is_key_down(key),
+            Output::Tty(_) | Output::Headless => false,
         }
-        if self.window.is_key_down(Key::Down) {
-            result = Some(Direction::Down);
+    }
+
+    /// Whether `key` went down since the previous `update`.
+    fn is_key_pressed(&self, key: Key) -> bool {
+        match &self.output {
+            Output::Window(window) => window.is_key_pressed(key, KeyRepeat::No),
+            Output::Tty(_) | Output::Headless => false,
         }
-        if self.window.is_key_down(Key::Left) {
-            result = Some(Direction::Left);
+    }
+
+    /// Characters typed since the last call, oldest first. The system's
+    /// own repeats of a held key are dropped, so that --key-repeat alone
+    /// decides how it repeats; see [`repeat_key`](Self::repeat_key).
+    fn check_key(&mut self, now: Duration) -> VecDeque<char> {
+        let mut typed = self.typed_keys();
+        if let Some(held) = &self.held
+            && self.is_key_down(held.key)
+        {
+            typed.retain(|&c| c != held.c);
         }
-        if self.window.is_key_down(Key::Right) {
-            result = Some(Direction::Right);
+        if let Some(&c) = typed.back() {
+            self.held = match (self.autorepeat, key_for(c)) {
+                (Autorepeat::On { delay, .. }, Some(key)) => Some(HeldKey { c, key, next: now + delay }),
+                _ => None,
+            };
         }
-        if self.window.is_key_down(Key::Enter) {
-            result = Some(Direction::Start);
+        typed
+    }
+
+    /// The character of the held key once it is due to repeat, and then
+    /// at every interval after that for as long as the key stays down.
+    fn repeat_key(&mut self, now: Duration) -> Option<char> {
+        let Autorepeat::On { interval, .. } = self.autorepeat else {
+            return None;
+        };
+        let held = self.held.as_ref()?;
+        if !self.is_key_down(held.key) {
+            self.held = None;
+            return None;
         }
-        if self.window.is_key_down(Key::Backspace) {
-            result = Some(Direction::Select);
+        let held = self.held.as_mut()?;
+        if now < held.next {
+            return None;
         }
-        if self.window.is_key_down(Key::Space) {
-            result = Some(Direction::ButtonA);
+        // From now, so a repeat the port had no room for isn't made up later
+        held.next = now + interval;
+        Some(held.c)
+    }
+
+    fn typed_keys(&mut self) -> VecDeque<char> {
+        let typed: VecDeque<char> = self.keys.borrow_mut().drain(..).filter_map(|code| {
+            let c = char::from_u32(code);
+            if c.is_none() {
+                debug!("Ignoring character code 0x{:x} from the window, not a Unicode scalar", code);
+            }
+            c
+        }).collect();
+        if !typed.is_empty() {
+            if self.key_path != KeyPath::Callback {
+                info!("Keyboard: characters from the window");
+                self.key_path = KeyPath::Callback;
+            }
+            return typed;
         }
-        if self.window.is_key_down(Key::Tab) {
-            result = Some(Direction::ButtonB);
+        if self.key_path == KeyPath::Callback || !std::mem::take(&mut self.poll_keys) {
+            return typed;
         }
-        result
+        let Output::Window(window) = &self.output else {
+            return typed;
+        };
+        let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        let polled: VecDeque<char> = window
+            .get_keys_pressed(KeyRepeat::No)
+            .into_iter()
+            .filter_map(|key| match key {
+                Key::Enter => Some('\n'),
+                _ => KEY_CHARS.iter().find(|&&(k, _, _)| k == key).map(|&(_, c, shifted)| if shift { shifted } else { c }),
+            })
+            .collect();
+        if !polled.is_empty() && self.key_path == KeyPath::Unknown {
+            info!("Keyboard: no characters from the window, mapping the keys pressed (US layout)");
+            self.key_path = KeyPath::Polled;
+        }
+        polled
+    }
+
+    /// Where the mouse is over the window, in back buffer pixels, or None
+    /// when it is outside or there is no window.
+    fn mouse_pos(&self) -> Option<(usize, usize)> {
+        let Output::Window(window) = &self.output else {
+            return None;
+        };
+        let (x, y) = window.get_mouse_pos(MouseMode::Discard)?;
+        let (w, h) = window.get_size();
+        let (px, py) = ((x * self.width as f32 / w as f32) as usize, (y * self.height as f32 / h as f32) as usize);
+        (px < self.width && py < self.height).then_some((px, py))
+    }
+
+    /// Draws `text` into the back buffer at (`x`, `y`) on a black box, each
+    /// font pixel `scale` window pixels wide.
+    fn overlay_text(&mut self, text: &str, x: usize, y: usize, scale: usize) {
+        draw_text(&mut self.back, self.width, self.height, text, x, y, scale);
+    }
+
+    /// Whether `binding` acts now: its modifiers are down, and its key went
+    /// down since the previous `update` for a Press, or is down otherwise.
+    fn is_active(&self, binding: &Binding) -> bool {
+        let shift = self.is_key_down(Key::LeftShift) || self.is_key_down(Key::RightShift);
+        let ctrl = self.is_key_down(Key::LeftCtrl) || self.is_key_down(Key::RightCtrl);
+        let mods = match binding.mods {
+            Mods::None => !shift && !ctrl,
+            Mods::Shift => shift,
+            Mods::Ctrl => ctrl,
+            Mods::Any => true,
+        };
+        mods && match binding.trigger {
+            Trigger::Press => self.is_key_pressed(binding.key),
+            Trigger::Poll | Trigger::Held => self.is_key_down(binding.key),
+        }
+    }
+
+    /// Whether any of the keys BINDINGS gives `action` is active.
+    fn is_action_active(&self, action: Action) -> bool {
+        BINDINGS.iter().any(|binding| binding.action == action && self.is_active(binding))
+    }
+
+    /// Active-low controller byte for the buttons held down.
+    fn check_joystick(&mut self) -> u8 {
+        let mut mask = 0xff;
+        for binding in &BINDINGS {
+            if let Action::Button(direction) = binding.action
+                && self.is_active(binding)
+            {
+                mask &= direction.mask();
+            }
+        }
+        mask
     }
 
 }
 
-impl Gigatron {
-    pub fn new() -> Self {
-        Gigatron {
-            ROM: [[0u8; 2]; 1 << 16],
-            RAM: [0u8; 1 << 15],
-            S: CpuState::new(),
-            IN: 0xff,
-            video: VGA::new(640, 480),
-            vgaX: 0,
-            vgaY: 0,
-            t: -2,
-            joy: None,
+impl Emulator {
+    pub fn new(gt: Gigatron) -> Self {
+        Emulator::with_video(gt, VGA::new(SCREEN_WIDTH * DEFAULT_ZOOM, SCREEN_HEIGHT * DEFAULT_ZOOM))
+    }
+
+    fn with_video(gt: Gigatron, video: VGA) -> Self {
+        Emulator {
+            gt,
+            video,
+            expansion: None,
+            ram_window: None,
+            joy: 0xff,
+            socket: None,
+            socket_joy: 0xff,
+            launcher: None,
+            launcher_joy: 0xff,
+            state_server: None,
+            shm_output: None,
             active: true,
+            cycles_per_frame: None,
+            frame_cycles: 0,
+            cold_start: false,
+            recorder: None,
+            delta: None,
+            draw_trace: None,
+            boot_trace: None,
+            replay: None,
+            renderer: Renderer::Table,
+            border: 0,
+            palette: Palette::Default,
+            crop: Crop::FULL,
+            render_ram: None,
+            pixel_aspect: None,
+            test_pattern: false,
+            show_input: false,
+            show_sync: false,
+            grid: DEFAULT_GRID,
+            show_grid: false,
+            show_coords: false,
+            show_pad: false,
+            show_help: false,
+            show_cpu: false,
+            cpu_sample_in: 0,
+            sync: SyncCounts::new(Duration::ZERO), // Clocks start at zero
+            profile_sync: false,
+            paused: false,
+            step_preview: false,
+            checkpoint: None,
+            breaks: Vec::new(),
+            break_hit: Rc::default(),
+            executing: 0,
+            debug_script: VecDeque::new(),
+            debug_record: None,
+            zoom: DEFAULT_ZOOM,
+            flip_x: false,
+            flip_y: false,
+            crt: false,
+            crt_bleed: false,
+            vblank_profile: None,
+            render_profile: None,
+            drift: None,
+            refresh: None,
+            pacer: None,
+            fast_forward: false,
+            watch: None,
+            frames: None,
+            capture: None,
+            max_cycles: None,
+            cycle_limit_hit: false,
+            halt_watch: HaltWatch::new(),
+            on_halt: HaltAction::Log,
+            crashed: false,
+            clock: Box::new(RealClock::new()),
+            last_key: None,
+            last_joy: 0xff,
         }
     }
 
-    fn reset(&mut self) {
-        let mut rng = rand::rng();
-        //garble( &RAM );
-        rng.fill(&mut self.RAM);
-        //garble( &S );
-        self.S.PC = rand::random();
-        self.S.IR = rand::random();
-        self.S.D = rand::random();
-        self.S.AC = rand::random();
-        self.S.X = rand::random();
-        self.S.Y = rand::random();
-        self.S.OUT = rand::random();
-        self.S.undef = rand::random();
+    fn load_test_pattern(&mut self) {
+        self.gt.load_test_pattern();
+        self.test_pattern = true;
     }
 
-    fn init(&mut self) {
-        self.reset();
-        self.IN = 0xFF;
+    /// Presents the test pattern until the window is closed.
+    fn show_test_pattern(&mut self) {
+        self.render2();
+        self.video.swap();
+        while self.active && self.video.is_open() {
+            self.video.update(self.clock.now());
+            self.clock.sleep(Duration::from_millis(1000 / 30));
+            if self.video.is_action_active(Action::Quit) {
+                self.act(Action::Quit);
+            }
+        }
     }
 
-    fn restore_rom(&mut self, buffer: &Vec<u8>) -> std::io::Result<()> {
-        if buffer.len() != 65536 * 2 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "File size must be exactly 131072 bytes",
-            ));
+    /// Shows a --record-delta recording at `fps` until it ends or the
+    /// window is closed.
+    fn play(&mut self, mut player: DeltaPlayer, fps: u32) {
+        self.video.set_target_fps(fps as usize);
+        while self.active && self.video.is_open() {
+            match player.next_frame() {
+                Ok(Some(frame)) => self.render_frame(frame),
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Can't play the recording: {}", e);
+                    break;
+                }
+            }
+            if self.crt {
+                self.apply_crt();
+            }
+            self.video.swap();
+            self.present();
+            if self.video.is_action_active(Action::Quit) {
+                self.act(Action::Quit);
+            }
         }
-        for (i, chunk) in buffer.chunks_exact(2).enumerate() {
-            self.ROM[i] = [chunk[0], chunk[1]];
+    }
+
+    fn render2(&mut self) {
+        let mut frame = self.gt.frame();
+        if let Some(range) = self.render_ram {
+            for (pixel, addr) in frame.iter_mut().zip(self.gt.frame_addrs()) {
+                if !addr.is_some_and(|addr| range.contains(addr)) {
+                    *pixel = 0;
+                }
+            }
         }
-        Ok(())
+        self.render_frame(&frame);
     }
 
-    fn read_rom(&mut self, filename: &str) -> std::io::Result<()> {
-        let mut file = File::open(filename)?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-        self.restore_rom(&buffer)?;
-        Ok(())
+    /// Scales `frame`, laid out as [`Gigatron::frame`] returns it, into the
+    /// back buffer.
+    fn render_frame(&mut self, frame: &[u8]) {
+        let crop = self.crop;
+        let (left, top, width, height) = self.picture();
+        let (flip_x, flip_y) = (self.flip_x, self.flip_y);
+        let (palette, border) = (self.palette, self.border);
+        for (py, row) in self.video.back.chunks_exact_mut(self.video.width).enumerate() {
+            let Some(py) = py.checked_sub(top).filter(|&py| py < height) else {
+                row.fill(border);
+                continue;
+            };
+            let sy = if flip_y { height - 1 - py } else { py };
+            let y = crop.y + sy * crop.h / height;
+            for (px, pixel) in row.iter_mut().enumerate() {
+                let Some(px) = px.checked_sub(left).filter(|&px| px < width) else {
+                    *pixel = border;
+                    continue;
+                };
+                let sx = if flip_x { width - 1 - px } else { px };
+                let x = crop.x + sx * crop.w / width;
+                *pixel = makeRGB(&palette.color(frame[y * SCREEN_WIDTH + x]));
+            }
+        }
     }
 
-    fn read_ram(&mut self) -> std::io::Result<()> {
-        //    let mut f = File::create_new("foo.txt")?;
-        //    f.write_all("Hello, world!".as_bytes())?;
-        Ok(())
+    /// Draws the visible part of the raster, 4 lines per logical row.
+    fn render_beam(&mut self) {
+        let crop = self.crop;
+        let raster = self.gt.raster();
+        let (left, top, width, height) = self.picture();
+        let (flip_x, flip_y) = (self.flip_x, self.flip_y);
+        for (py, row) in self.video.back.chunks_exact_mut(self.video.width).enumerate() {
+            let Some(py) = py.checked_sub(top).filter(|&py| py < height) else {
+                row.fill(self.border);
+                continue;
+            };
+            let py = if flip_y { height - 1 - py } else { py };
+            let y = VISIBLE_Y + 4 * crop.y + py * 4 * crop.h / height;
+            for (px, pixel) in row.iter_mut().enumerate() {
+                let Some(px) = px.checked_sub(left).filter(|&px| px < width) else {
+                    *pixel = self.border;
+                    continue;
+                };
+                let px = if flip_x { width - 1 - px } else { px };
+                let x = VISIBLE_X + crop.x + px * crop.w / width;
+                *pixel = match raster[y * RASTER_WIDTH + x] {
+                    BLANK => self.border,
+                    v => makeRGB(&self.palette.color(v)),
+                };
+            }
+        }
     }
 
-    fn write_ram(&mut self) -> std::io::Result<()> {
-        Ok(())
+    /// Where in the back buffer the picture goes, as (left, top, width,
+    /// height): all of it, or with --pixel-aspect the largest centered
+    /// rectangle that keeps the pixels that shape, leaving bars either
+    /// side or above and below.
+    fn picture(&self) -> (usize, usize, usize, usize) {
+        let (width, height) = (self.video.width, self.video.height);
+        let Some(aspect) = self.pixel_aspect else {
+            return (0, 0, width, height);
+        };
+        let shape = self.crop.w as f64 * aspect / self.crop.h as f64;
+        if width as f64 > height as f64 * shape {
+            let w = ((height as f64 * shape).round() as usize).clamp(1, width);
+            ((width - w) / 2, 0, w, height)
+        } else {
+            let h = ((width as f64 / shape).round() as usize).clamp(1, height);
+            (0, (height - h) / 2, width, h)
+        }
     }
 
-    fn cpuCycle(&mut self) -> CpuState {
-        let mut T: CpuState = self.S.clone(); // New state is old state unless something changes
-        T.IR = self.ROM[self.S.PC as usize][0]; // Instruction Fetch
-        T.D = self.ROM[self.S.PC as usize][1];
-        let ins = self.S.IR >> 5; // Instruction
-        let mode = (self.S.IR >> 2) & 7; // Addressing mode (or condition)
-        let bus = self.S.IR & 3; // Busmode
-        let W = ins == 6; // Write instruction?
-        let J = ins == 7; // Jump instruction?
+    /// The logical pixel shown at back buffer pixel (`px`, `py`), undoing
+    /// the crop, the flips and the scaling; None on the bars around it.
+    fn logical(&self, px: usize, py: usize) -> Option<(usize, usize)> {
+        let (left, top, width, height) = self.picture();
+        let px = px.checked_sub(left).filter(|&px| px < width)?;
+        let py = py.checked_sub(top).filter(|&py| py < height)?;
+        let sx = if self.flip_x { width - 1 - px } else { px };
+        let sy = if self.flip_y { height - 1 - py } else { py };
+        Some((self.crop.x + sx * self.crop.w / width, self.crop.y + sy * self.crop.h / height))
+    }
 
-        let mut lo = self.S.D;
-        let mut hi = 0;
-        let mut to: Option<Register> = None; // Mode Decoder
-        let mut incX = false;
-        if !J {
-            match mode {
-                0 => {
-                    to = E(W, Register::AC);
-                }
-                1 => {
-                    to = E(W, Register::AC);
-                    lo = self.S.X;
-                }
-                2 => {
-                    to = E(W, Register::AC);
-                    hi = self.S.Y;
-                }
-                3 => {
-                    to = E(W, Register::AC);
-                    lo = self.S.X;
-                    hi = self.S.Y;
-                }
-                4 => {
-                    to = Some(Register::X);
+    /// Lightens the first column and row of back buffer pixels of every
+    /// logical column and row that is a multiple of `grid`.
+    fn draw_grid(&mut self) {
+        let (width, height) = (self.video.width, self.video.height);
+        let on_line = |here: Option<usize>, before: Option<usize>| {
+            here.is_some_and(|n| n % self.grid == 0 && here != before)
+        };
+        let columns: Vec<bool> = (0..width)
+            .map(|px| {
+                let x = |px| self.logical(px, height / 2).map(|(x, _)| x);
+                on_line(x(px), px.checked_sub(1).and_then(x))
+            })
+            .collect();
+        let rows: Vec<bool> = (0..height)
+            .map(|py| {
+                let y = |py| self.logical(width / 2, py).map(|(_, y)| y);
+                on_line(y(py), py.checked_sub(1).and_then(y))
+            })
+            .collect();
+        let (left, top, w, h) = self.picture();
+        for (py, row) in self.video.back.chunks_exact_mut(width).enumerate().skip(top).take(h) {
+            for (px, pixel) in row.iter_mut().enumerate().skip(left).take(w) {
+                if rows[py] || columns[px] {
+                    *pixel = blend(*pixel, GRID_COLOR);
                 }
-                5 => {
-                    to = Some(Register::Y);
+            }
+        }
+    }
+
+    /// Gives the back buffer a CRT look. Only ever applied to what is shown,
+    /// never to the emulated screen.
+    fn apply_crt(&mut self) {
+        let width = self.video.width;
+        for (py, row) in self.video.back.chunks_exact_mut(width).enumerate() {
+            if self.crt_bleed {
+                let mut left = row[0];
+                for pixel in row.iter_mut() {
+                    let here = *pixel;
+                    *pixel = blend(here, left);
+                    left = here;
                 }
-                6 => {
-                    to = E(W, Register::OUT);
+            }
+            if py % 2 == 1 {
+                for pixel in row.iter_mut() {
+                    *pixel = (*pixel >> 1) & 0x007f_7f7f;
                 }
-                7 => {
-                    to = E(W, Register::OUT);
-                    lo = self.S.X;
-                    hi = self.S.Y;
-                    incX = true;
+            }
+        }
+    }
+
+    /// Saves the frame just presented for --capture-at-frame.
+    fn capture_frame(&mut self) {
+        let Some(capture) = &self.capture else {
+            return;
+        };
+        match png::write(&capture.file, self.video.width, self.video.height, &self.video.buffer) {
+            Ok(()) => info!("Frame {} written to {}", capture.frame, capture.file),
+            Err(e) => error!("Can't write frame {} to {}: {}", capture.frame, capture.file, e),
+        }
+        if capture.pause {
+            self.paused = true;
+        }
+    }
+
+    /// Draws the screen as it is now into the back buffer and swaps it in.
+    fn render(&mut self) {
+        let start = self.clock.now();
+        match self.renderer {
+            Renderer::Table => self.render2(),
+            Renderer::Beam => self.render_beam(),
+        }
+        if self.crt {
+            self.apply_crt();
+        }
+        self.draw_overlays();
+        self.video.swap();
+        if let Some(profile) = &mut self.render_profile {
+            profile.render.add(self.clock.now() - start);
+        }
+    }
+
+    fn process(&mut self, edges: Edges) {
+        let vSync = edges.vSync;
+        self.sync.add(edges);
+        self.vga(vSync);
+        if vSync && self.capture.as_ref().is_some_and(|capture| capture.frame == self.gt.frames()) {
+            self.capture_frame();
+        }
+        if vSync && self.profile_sync {
+            self.sync.report(self.clock.now());
+        }
+        if vSync && let Some(server) = &self.state_server {
+            server.publish(self.gt.state_json());
+        }
+        if vSync && let Some(profile) = &mut self.vblank_profile {
+            if let Some(cycles) = self.gt.vblank_cycles() {
+                profile.add(cycles);
+            }
+            profile.report(self.clock.now());
+        }
+        if vSync && let Some(drift) = &mut self.drift {
+            drift.report(self.clock.now(), self.gt.cycles());
+        }
+        if vSync && let Some(launcher) = &mut self.launcher {
+            self.launcher_joy = launcher.vsync(&self.gt);
+        }
+        if vSync && let Some(refresh) = &mut self.refresh {
+            refresh.vsync(self.gt.cycles());
+        }
+        if vSync && let Some(watch) = &mut self.watch {
+            watch.check(&mut self.gt, self.clock.now());
+        }
+        // Replayed input goes in at its cycle, live input once a frame: the
+        // window only takes in keys when it is presented, at vSync just
+        // above, and the port only hands them to the ROM at the next vSync,
+        // so reading them more often gains nothing
+        self.process_replay();
+        if !vSync {
+            return;
+        }
+
+        let now = self.clock.now();
+        let mut typed = self.video.check_key(now);
+        // Repeats only once the port is free, at most one character every
+        // few frames, so they never pile up to type on after the key is up
+        if typed.is_empty() && self.gt.port.is_idle() {
+            typed.extend(self.video.repeat_key(now));
+        }
+        for k in typed {
+            debug!("Character: {:?}", k);
+            match input::key_code(k) {
+                Some(code) => {
+                    if !k.is_ascii() {
+                        debug!("Typing {:?} as {:?}", k, code as char);
+                    }
+                    self.input(InputEvent::Key(code));
                 }
-                _ => unreachable!(),
+                None => debug!("No key code for {:?} (U+{:04X}), the ROM only knows ASCII", k, k as u32),
             }
         }
-        let addr: u16 = makeAddr(hi, lo);
-        let mut B = self.S.undef; // Data Bus
-        match bus {
-            0 => {
-                B = self.S.D;
+
+        self.process_joystick();
+        self.process_system();
+
+    }
+
+    fn vga(&mut self, vSync: bool) {
+        // Nothing syncs while reset is held, so --cold-start paces the
+        // frames itself, each showing RAM as the power-on garble left it
+        if self.cold_start && self.gt.in_reset() {
+            self.frame_cycles += 1;
+            if self.frame_cycles >= FRAME_CYCLES {
+                self.frame_cycles = 0;
+                self.render();
+                self.present();
+            }
+            return;
+        }
+        // Frames are only completed at the vSync edge, so a present never
+        // shows a frame the ROM is still drawing
+        if vSync {
+            self.render();
+            let seconds = self.gt.cycles() as f64 / CLOCK_HZ;
+            if let Some(delta) = &mut self.delta
+                && let Err(e) = delta.record_at(&self.gt.frame(), seconds)
+            {
+                error!("Screen recording stopped: {}", e);
+                self.delta = None;
             }
-            1 => {
-                if !W {
-                    let p = addr & 0x7fff;
-                    B = self.RAM[p as usize];
+            if let Some(shm) = &mut self.shm_output {
+                let pixels: Vec<u32> = self.gt.frame().iter().map(|&v| makeRGB(&self.palette.color(v))).collect();
+                if let Err(e) = shm.publish(&pixels, self.gt.frames()) {
+                    error!("Screen publishing stopped: {}", e);
+                    self.shm_output = None;
                 }
             }
-            2 => {
-                B = self.S.AC;
+        }
+
+        match self.cycles_per_frame {
+            Some(limit) => {
+                self.frame_cycles += 1;
+                if self.frame_cycles >= limit {
+                    self.frame_cycles = 0;
+                    self.present();
+                }
             }
-            3 => {
-                B = self.IN;
+            None => {
+                if vSync {
+                    self.present();
+                }
             }
-            _ => unreachable!(),
         }
-        if W {
-            let p = addr & 0x7fff;
-            self.RAM[p as usize] = B; // Random Access Memory
+    }
+
+    fn present(&mut self) {
+        if let Some(pacer) = &mut self.pacer
+            && !self.fast_forward
+        {
+            pacer.wait(self.clock.as_ref(), self.gt.cycles());
+        }
+        self.video.pad = self.show_pad.then_some(self.last_joy);
+        let start = self.clock.now();
+        self.video.update(start);
+        if let Some(profile) = &mut self.render_profile {
+            let now = self.clock.now();
+            profile.present.add(now - start);
+            profile.report(now);
         }
-        let mut ALU; // Arithmetic and Logic Unit
-        match ins {
-            0 => {
-                ALU = B; // LD
+        if let Some(window) = &mut self.expansion {
+            if window.is_open() {
+                window.draw(self.gt.xout(), self.gt.expansion());
+            } else {
+                self.expansion = None;
             }
-            1 => {
-                ALU = self.S.AC & B; // ANDA
+        }
+        if let Some(window) = &mut self.ram_window {
+            if window.is_open() {
+                window.draw(&self.gt.RAM);
+            } else {
+                self.ram_window = None;
             }
-            2 => {
-                ALU = self.S.AC | B; // ORA
+        }
+        self.process_hotkeys();
+    }
+
+    /// Toggles polled once per presented frame, where `is_key_pressed`
+    /// reports each press exactly once.
+    fn process_hotkeys(&mut self) {
+        let fast_forward = self.video.is_action_active(Action::FastForward);
+        if fast_forward != self.fast_forward {
+            info!("Fast-forward {}", if fast_forward { "on" } else { "off" });
+            self.fast_forward = fast_forward;
+            self.video.set_unpaced(fast_forward);
+            if !fast_forward && let Some(pacer) = &mut self.pacer {
+                // Real time from here on, instead of waiting for it to
+                // catch up with the frames run ahead
+                pacer.restart(self.clock.now(), self.gt.cycles());
             }
-            3 => {
-                ALU = self.S.AC ^ B; // XORA
+        }
+        for binding in BINDINGS.iter().filter(|b| b.trigger == Trigger::Press) {
+            if self.video.is_active(binding) {
+                self.act(binding.action);
             }
-            4 => {
-                ALU = self.S.AC.wrapping_add(B); // ADDA
+        }
+    }
+
+    /// Held every poll, so a held F2 keeps the CPU in reset.
+    fn process_system(&mut self) {
+        for binding in BINDINGS.iter().filter(|b| b.trigger == Trigger::Poll) {
+            if self.video.is_active(binding) {
+                self.act(binding.action);
             }
-            5 => {
-                ALU = self.S.AC.wrapping_sub(B); // SUBA
+        }
+    }
+
+    /// Does what a Press or Poll binding asks for. Held ones are read where
+    /// they are used instead.
+    fn act(&mut self, action: Action) {
+        match action {
+            Action::CpuMonitor => {
+                self.show_cpu = !self.show_cpu;
+                self.cpu_sample_in = 0;
+                self.video.hud.clear();
             }
-            6 => {
-                ALU = self.S.AC; // ST
+            Action::Help => self.show_help = !self.show_help,
+            Action::Reset => {
+                info!("F2 press. Reset!");
+                self.gt.init();
             }
-            7 => {
-                ALU = self.S.AC.wrapping_neg(); // Bcc/JMP
+            Action::WarmReset => {
+                // Once per press: held, it would keep the CPU in reset, and
+                // the window only sees the keys again at the next vSync
+                info!("Shift+F2 press. Warm reset, RAM kept");
+                self.gt.soft_reset();
             }
-            _ => unreachable!(),
-        };
-        if let Some(reg) = to {
-            // Load value into register
-            //println!("Load value: addr = {:?} value = {}", reg, ALU);
-            match reg {
-                Register::AC => T.AC = ALU,
-                Register::OUT => T.OUT = ALU,
-                Register::X => T.X = ALU,
-                Register::Y => T.Y = ALU,
-            }
-            //*to = ALU;
-        }
-        if incX {
-            T.X = self.S.X.wrapping_add(1); // Increment X
-        }
-        T.PC = self.S.PC.wrapping_add(1); // Next instruction
-        if J {
-            if mode != 0 {
-                // Conditional branch within page
-                let sAC = if self.S.AC == 0 { 1 } else { 0 };
-                let cond = (self.S.AC >> 7) + 2 * sAC;
-                let st = mode & (1 << cond);
-                if st > 0 {
-                    // 74153
-                    T.PC = (self.S.PC & 0xff00) | B as u16;
-                }
-            } else {
-                T.PC = makeAddr(self.S.Y, B); // Unconditional far jump
+            Action::InputBytes => self.show_input = !self.show_input,
+            Action::PadDisplay => self.show_pad = !self.show_pad,
+            Action::SyncCounts => self.show_sync = !self.show_sync,
+            Action::Debugger => self.paused = true,
+            Action::MirrorX => self.flip_x = !self.flip_x,
+            Action::MirrorY => self.flip_y = !self.flip_y,
+            Action::Scanlines => self.crt = !self.crt,
+            Action::SwapRenderer => {
+                self.renderer = match self.renderer {
+                    Renderer::Table => Renderer::Beam,
+                    Renderer::Beam => Renderer::Table,
+                };
+                info!("Shift+F8 press. Renderer: {:?}", self.renderer);
+                // Redrawn now, so the next present shows the new renderer's
+                // picture, also with --cycles-per-frame
+                self.render();
             }
+            Action::RamWindow => self.toggle_ram_window(),
+            Action::Quit => self.active = false,
+            Action::Grid => self.show_grid = !self.show_grid,
+            Action::Coords => self.show_coords = !self.show_coords,
+            Action::ZoomIn => self.set_zoom(self.zoom + 1),
+            Action::ZoomOut => self.set_zoom(self.zoom - 1),
+            Action::FastForward | Action::Button(_) => {}
         }
-        T
     }
 
-    fn render(&mut self) {
-        for y in 0..120 {
-            for x in 0..160 {
-                let addr = 2048+y * 256 + x;
-                let pixel = self.RAM[addr];
-                let color = unpack_color(pixel);
-                let rgb = makeRGB(&color);
-                self.video.put(2*x as usize, 2*y as usize, rgb);
-                self.video.put(2*x as usize + 1, 2*y as usize, rgb);
-                self.video.put(2*x as usize, 2*y as usize + 1, rgb);
-                self.video.put(2*x as usize + 1, 2*y as usize + 1, rgb);
-            }
+    /// Opens the RAM map, or closes it if it is open.
+    fn toggle_ram_window(&mut self) {
+        if self.ram_window.take().is_some() {
+            return;
+        }
+        match RamWindow::new(self.gt.RAM.len()) {
+            Ok(window) => self.ram_window = Some(window),
+            Err(e) => error!("Can't open the RAM window: {}", e),
         }
     }
 
-    fn render2(&mut self) {
-        let mut vgaX: usize = 0;
-        let mut vgaY: usize = 0;
+    /// Resizes the window to `zoom` times the logical screen, within
+    /// ZOOM_MIN..=ZOOM_MAX. The whole screen still fills the window, so it
+    /// stays centered.
+    fn set_zoom(&mut self, zoom: usize) {
+        let zoom = zoom.clamp(ZOOM_MIN, ZOOM_MAX);
+        if zoom != self.zoom {
+            info!("Zoom {}x", zoom);
+            self.zoom = zoom;
+            self.video.resize(SCREEN_WIDTH * zoom, SCREEN_HEIGHT * zoom);
+        }
+    }
 
-        let scaleX = self.video.width / 160;
-        let scaleY = self.video.height / 120;
+    /// Counts down to the next CPU sample and puts the instruction about to
+    /// execute and the registers on the window's hud when it is due.
+    fn sample_cpu(&mut self) {
+        if self.cpu_sample_in > 0 {
+            self.cpu_sample_in -= 1;
+            return;
+        }
+        self.cpu_sample_in = CPU_SAMPLE_CYCLES;
+        let S = &self.gt.S;
+        self.video.hud = vec![
+            format!("${:04X} {:02X}{:02X} {}", self.executing, S.IR, S.D, disasm::disassemble(S.IR, S.D)),
+            format!("AC {:02X} X {:02X} Y {:02X} OUT {:02X}", S.AC, S.X, S.Y, S.OUT),
+        ];
+        self.video.hud_scale = if self.video.native { 1 } else { self.zoom.min(2) };
+    }
 
-        for pixel in self.video.buffer.iter_mut() {
+    fn draw_overlays(&mut self) {
+        if self.show_grid {
+            self.draw_grid();
+        }
+        if self.show_coords
+            && let Some((px, py)) = self.video.mouse_pos()
+            && let Some((x, y)) = self.logical(px, py)
+        {
+            let color = self.gt.frame()[y * SCREEN_WIDTH + x];
+            let text = format!("X {} Y {}  ${:02X}", x, y, color);
+            let scale = if self.video.native { 1 } else { self.zoom.min(2) };
+            let w = (text.len() * font::ADVANCE + 1) * scale;
+            let h = (font::HEIGHT + 2) * scale;
+            self.video.overlay_text(&text, self.video.width.saturating_sub(w + 4), self.video.height.saturating_sub(h + 4), scale);
+        }
+        if self.show_input {
+            let key = match self.last_key {
+                Some(code) if code.is_ascii_graphic() => format!("{:02X} '{}'", code, code as char),
+                Some(code) => format!("{:02X}", code),
+                None => "--".to_string(),
+            };
+            let text = format!("KEY {}  JOY {:02X}", key, self.last_joy);
+            let scale = if self.video.native { 1 } else { self.zoom.min(3) };
+            self.video.overlay_text(&text, 4, self.video.height - 4 - (font::HEIGHT + 2) * scale, scale);
+        }
+        if self.show_sync {
+            let text = self.sync.text();
+            let scale = if self.video.native { 1 } else { self.zoom.min(2) };
+            self.video.overlay_text(&text, 4, 4, scale);
+        }
+        if self.show_help {
+            // Over the other overlays; at native size the last lines fall off
+            let scale = if self.video.native { 1 } else { self.zoom.min(2) };
+            for (i, text) in help_lines().iter().enumerate() {
+                self.video.overlay_text(text, 4, 4 + i * (font::HEIGHT + 2) * scale, scale);
+            }
+        }
+    }
 
-            let x = vgaX / scaleX;
-            let y = vgaY / scaleY;
+    fn process_joystick(&mut self) {
+        if let Some(mask) = self.socket.as_ref().and_then(InputSocket::poll) {
+            self.socket_joy = mask;
+        }
+        let mask = self.video.check_joystick() & self.socket_joy & self.launcher_joy;
+        if mask != self.joy {
+            trace!("joystick = {:08b}", mask);
+            self.input(InputEvent::Joystick(mask));
+            self.joy = mask;
+        }
+    }
+
+    /// Host input enters here, which makes it the point to record it and to
+    /// override it while a replay is active.
+    fn input(&mut self, event: InputEvent) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(recorder) = &mut self.recorder
+            && let Err(e) = recorder.record(self.gt.cycles(), event)
+        {
+            error!("Input recording stopped: {}", e);
+            self.recorder = None;
+        }
+        self.apply_input(event);
+    }
 
-            let addr = 2048+y * 256 + x;
-            let v = if addr < 32768 { self.RAM[addr] } else { 0 };
-            let color = unpack_color(v);
-            let rgb = makeRGB(&color);
+    fn apply_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Key(code) => {
+                self.gt.port.key(code, self.gt.cycles());
+                self.last_key = Some(code);
+            }
+            InputEvent::Joystick(mask) => {
+                self.gt.port.joystick(mask);
+                self.last_joy = mask;
+            }
+        }
+    }
 
-            *pixel = rgb;
+    fn process_replay(&mut self) {
+        if let Some(mut replay) = self.replay.take() {
+            let t = self.gt.cycles();
+            while let Some(event) = replay.next(t) {
+                trace!("replay {:?} at {}", event, t);
+                self.apply_input(event);
+            }
+            self.replay = Some(replay);
+        }
+    }
 
-            vgaX += 1;
-            if vgaX == self.video.width {
-                vgaX = 0;
-                vgaY += 1;
+    /// Reports the --break conditions that hold now and didn't before the
+    /// last cycle, so one that stays true breaks once, and returns whether
+    /// there were any.
+    fn break_met(&mut self) -> bool {
+        let mut met = false;
+        for (i, (condition, held)) in self.breaks.iter_mut().enumerate() {
+            let holds = condition.holds(&self.gt);
+            if holds && !*held {
+                println!("Break {}: {} at cycle {}", i + 1, condition, self.gt.cycles());
+                met = true;
             }
+            *held = holds;
         }
+        met
     }
 
-    fn process(&mut self, T: &mut CpuState) {
-        self.vga(T);
-        let key = self.video.check_key();
-        if let Some(k) = key {
-            println!("Character: {:?}", key);
-            let ascii = u8::try_from(k as u32);
-            match ascii {
-                Ok(code) => {
-                    self.RAM[0x000f] = code;
-                    self.RAM[0x0010] = 0;
+    /// Breaks into the debugger after the first CPU store that meets
+    /// `condition`.
+    fn break_on(&mut self, condition: WriteBreak) {
+        let hit = self.break_hit.clone();
+        self.gt.add_write_watch(condition.addr, Box::new(move |addr, value| {
+            if condition.matches(value) && hit.get().is_none() {
+                hit.set(Some((addr, value)));
+            }
+        }));
+    }
+
+    /// Reads and runs debugger commands from stdin until one resumes or
+    /// stops the emulator. End of input stops it too.
+    fn debug(&mut self) {
+        println!("{}", debugger::regs(&self.gt.S));
+        let stdin = std::io::stdin();
+        loop {
+            print!("> ");
+            std::io::stdout().flush().ok();
+            let command = if let Some(command) = self.debug_script.pop_front() {
+                println!("{}", command);
+                Ok(command)
+            } else {
+                let mut line = String::new();
+                if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                    self.active = false;
+                    break;
+                }
+                let command = line.parse();
+                if let (Ok(command), Some(file)) = (&command, &mut self.debug_record)
+                    && let Err(e) = writeln!(file, "{}", command)
+                {
+                    error!("Debugger recording stopped: {}", e);
+                    self.debug_record = None;
+                }
+                command
+            };
+            match command {
+                Ok(Command::Continue) => break,
+                Ok(Command::Quit) => {
+                    self.active = false;
+                    break;
+                }
+                Ok(Command::Checkpoint) => {
+                    let snapshot = self.gt.save_cpu_state();
+                    println!("Checkpoint at cycle {}", snapshot.cycles);
+                    self.checkpoint = Some(snapshot);
+                }
+                Ok(Command::Rollback) => match &self.checkpoint {
+                    Some(snapshot) => {
+                        self.gt.restore_cpu_state(snapshot);
+                        println!("{}", debugger::regs(&self.gt.S));
+                    }
+                    None => println!("No checkpoint yet"),
                 },
-                _ => {},
+                Ok(Command::Preview) => {
+                    self.step_preview = !self.step_preview;
+                    println!("Step preview {}", if self.step_preview { "on" } else { "off" });
+                }
+                Ok(command) => {
+                    println!("{}", debugger::execute(&mut self.gt, command));
+                    // Stepping back always redraws: the next vSync's frame
+                    // would not show the undone stores
+                    if matches!(command, Command::Back(_)) || self.step_preview && matches!(command, Command::Step(_)) {
+                        self.render();
+                        self.video.update(self.clock.now());
+                    }
+                }
+                Err(e) => println!("{}", e),
             }
         }
+        self.paused = false;
+    }
 
-        self.process_joystick();
-        self.process_system();
+    fn run(&mut self) {
+        if self.test_pattern {
+            return self.show_test_pattern();
+        }
+        while self.active {
+            if self.paused {
+                self.debug();
+                self.break_hit.set(None); // Stores made while stepping aren't reported
+                continue;
+            }
+            if !self.breaks.is_empty() && self.break_met() {
+                self.paused = true;
+                continue;
+            }
+            if let Some(halt) = self.halt_watch.check(&self.gt) {
+                if halt.deliberate {
+                    info!("{} at cycle {}", halt.describe(), self.gt.cycles());
+                } else {
+                    warn!("{} at cycle {}", halt.describe(), self.gt.cycles());
+                }
+                match self.on_halt {
+                    HaltAction::Log => {}
+                    HaltAction::Pause => {
+                        self.paused = true;
+                        continue;
+                    }
+                    HaltAction::Exit => {
+                        self.crashed = !halt.deliberate;
+                        self.active = false;
+                        continue;
+                    }
+                }
+            }
+            let fetched = self.gt.S.PC; // Into IR this cycle, executed the next
+            let edges = self.gt.step();
+            if let Some(trace) = &mut self.draw_trace
+                && let Err(e) = trace.record(&self.gt)
+            {
+                error!("Draw trace stopped: {}", e);
+                self.draw_trace = None;
+            }
+            if let Some(trace) = &mut self.boot_trace {
+                trace.record(&self.gt, &edges);
+            }
+            if let Some((addr, value)) = self.break_hit.take() {
+                println!("Break: ${:02x} stored at ${:04x} by the instruction at ${:04x}", value, addr, self.executing);
+                self.paused = true;
+            }
+            self.executing = fetched;
+            if self.show_cpu {
+                self.sample_cpu();
+            }
+            if edges.loaderReady {
+                info!("Loader ready");
+            }
+            self.process(edges);
+            // The vSync edge itself completes a frame: vga() has presented it
+            if edges.vSync && self.frames.is_some_and(|n| self.gt.frames() >= n) {
+                self.active = false;
+            } else if self.max_cycles.is_some_and(|n| self.gt.cycles() >= n) {
+                let limit = GigatronError::CycleLimit { cycles: self.gt.cycles(), pc: self.gt.S.PC };
+                error!("{}", limit);
+                self.cycle_limit_hit = true;
+                self.active = false;
+            }
+        }
+    }
+}
 
+fn main() {
+    let parse = |args: Vec<String>| match Options::parse(args.into_iter()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let cli: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = parse(cli.clone());
+    let session = options.session.as_ref().map(|dir| match Session::read(dir) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Can't read session {}: {}", dir, e);
+            std::process::exit(2);
+        }
+    });
+    if let Some(session) = &session {
+        options = parse(session.args.iter().cloned().chain(cli).collect());
+        if options.rom.is_none() {
+            options.rom = session.rom.clone();
+        }
+    }
+    if let Err(e) = options.check() {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    logger::init(options.level());
+    if let (Some(dir), Some(session)) = (&options.session, &session) {
+        info!("Session {}: {}", dir, session.describe());
     }
 
-    fn vga(&mut self, T: &mut CpuState) {
+    if options.selftest {
+        let outcomes = selftest::run();
+        let failed = outcomes.iter().filter(|outcome| outcome.failure.is_some()).count();
+        for outcome in &outcomes {
+            match &outcome.failure {
+                None => println!("PASS {}", outcome.name),
+                Some(failure) => println!("FAIL {}: {}", outcome.name, failure),
+            }
+        }
+        println!("{} of {} cases passed", outcomes.len() - failed, outcomes.len());
+        std::process::exit(if failed == 0 { 0 } else { 1 });
+    }
 
-        // HSync (бит 2) переключается в 0, когда нужно начать новую строку
-        let hSync = ((self.S.OUT & 0b0100_0000) > 0) && ((T.OUT & 0b0100_0000) == 0);
+    if let Some(dir) = &options.batch {
+        let reports = match batch::run(dir, options.frames.unwrap_or(200), options.seed.unwrap_or(0)) {
+            Ok(reports) => reports,
+            Err(e) => {
+                error!("Can't read ROM directory {}: {}", dir, e);
+                std::process::exit(1);
+            }
+        };
+        print!("{}", if options.batch_csv { batch::csv(&reports) } else { batch::table(&reports) });
+        let booted = reports.iter().all(|report| report.status == batch::Status::Booted);
+        std::process::exit(if booted { 0 } else { 1 });
+    }
 
-        // VSync (бит 1) переключается в 0, когда нужно начать новый кадр
-        let vSync = ((self.S.OUT & 0b1000_0000) > 0) && ((T.OUT & 0b1000_0000) == 0);
+    if let Some((a, b)) = &options.diff_states {
+        let read = |filename: &str| match State::read(filename) {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Can't read state {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        };
+        let same = print_state_diff(&read(a), &read(b));
+        std::process::exit(if same { 0 } else { 1 });
+    }
 
-        if vSync {
-//            if (self.t % 100) == 0 {
-                self.render2();
-                self.video.update();
-//            }
+    let mut gt = Gigatron::new();
+    let loaded = match &options.rom {
+        Some(filename) => {
+            gt.rom_byteswap = options.rom_byteswap;
+            gt.read_rom(filename)
         }
-
-        if hSync {
-            T.undef = rand::random(); // Change this once in a while
+        None => gt.read_rom_bytes(ROM6),
+    };
+    if let Err(e) = &loaded {
+        error!("Can't load ROM: {}", e);
+    }
+    // Everything patched into the ROM: the fixed video kernel, the font,
+    // then --patch. --watch reapplies it to each reload, and --diff-patch's
+    // second machine starts from it
+    let mut patches: Vec<Patch> = Vec::new();
+    if options.fix_video && loaded.is_ok() {
+        match patch::video_kernel(gt.rom_type()) {
+            Some(kernel) => {
+                let differed = gt.apply_patches(&kernel);
+                info!("Video kernel patched with ROMv6's: {} words differed", differed);
+                patches.extend(kernel);
+            }
+            None => error!("Can't fix the video: no known-good kernel for romType ${:02x}", gt.rom_type()),
         }
     }
-
-    fn process_system(&mut self) {
-        if self.video.window.is_key_down(Key::F2) {
-            println!("F10 press. Reset!");
-            self.init();
-            self.t = -2;
+    if let Some(filename) = options.font.as_ref().filter(|_| loaded.is_ok()) {
+        match patch::read_font(filename, gt.rom_type()) {
+            Ok(glyphs) => {
+                let differed = gt.apply_patches(&glyphs);
+                info!("Font loaded from {}: {} glyph columns differed", filename, differed);
+                patches.extend(glyphs);
+            }
+            Err(e) => {
+                error!("Can't load font from {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(filename) = &options.patch {
+        match patch::read(filename) {
+            Ok(file) => {
+                gt.apply_patches(&file);
+                patches.extend(file);
+            }
+            Err(e) => {
+                error!("Can't patch ROM from {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if options.show_boot && loaded.is_ok() {
+        for line in gt.boot_path(BOOT_PATH) {
+            info!("Boot: {}", line);
+        }
+    }
+    if let Some(filename) = &options.disasm_out {
+        if loaded.is_err() {
+            std::process::exit(1);
         }
-        if self.video.window.is_key_down(Key::F10) {
-            self.active = false;
+        match gt.write_disassembly(filename) {
+            Ok(()) => info!("Disassembly written to {}", filename),
+            Err(e) => {
+                error!("Can't write disassembly to {}: {}", filename, e);
+                std::process::exit(1);
+            }
         }
+        return;
     }
 
-    fn process_joystick(&mut self) {
-        let joy = self.video.check_joystick();
-        if let Some(j) = joy {
-            if let Some(pj) = &self.joy {
-                if *pj == j {
-                    return;
+    let lockstep = options.diff_rom.is_some() || options.diff_patch.is_some();
+    let mut E: Emulator = match options.frontend {
+        _ if lockstep || options.verify_determinism => Emulator::with_video(gt, VGA::headless(640, 480)),
+        Frontend::Window if options.render_size == RenderSize::Native => {
+            Emulator::with_video(gt, VGA::native(SCREEN_WIDTH * DEFAULT_ZOOM, SCREEN_HEIGHT * DEFAULT_ZOOM))
+        }
+        Frontend::Window => Emulator::new(gt),
+        Frontend::Tty => Emulator::with_video(gt, VGA::tty(640, 480)),
+        Frontend::Headless => Emulator::with_video(gt, VGA::headless(640, 480)),
+    };
+
+    let seed = options.seed.unwrap_or_else(rand::random);
+    info!("Seed: {}", seed);
+    E.gt.seed(seed);
+    E.gt.clean_boot = options.clean_boot;
+    E.gt.port.polarity = options.polarity;
+    if let Some(fill) = options.ram_fill {
+        E.gt.ram_fill = fill;
+    }
+    E.gt.set_ram_size(options.ram_size);
+    if let Some(hold) = options.reset_hold {
+        E.gt.reset_hold = hold;
+    }
+    if options.cold_start > 0 {
+        E.gt.reset_hold += options.cold_start * FRAME_CYCLES;
+        E.cold_start = true;
+    }
+    if options.set_pc.is_some() {
+        E.gt.reset_hold = 0; // Reset would fetch from 0 again
+    }
+    E.gt.init();
+    if let Some(filename) = &options.load_garble {
+        match State::read(filename) {
+            Ok(state) => E.gt.restore(&state),
+            Err(e) => {
+                error!("Can't load power-on state from {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(filename) = &options.save_garble {
+        match E.gt.state().write(filename) {
+            Ok(()) => info!("Power-on state written to {}", filename),
+            Err(e) => {
+                error!("Can't write power-on state to {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(filename) = session.as_ref().and_then(|session| session.ram.as_ref()) {
+        match read_ram_image(filename, E.gt.RAM.len()) {
+            Ok(image) => E.gt.RAM.copy_from_slice(&image),
+            Err(e) => {
+                error!("Can't load the session's RAM image {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let S = &mut E.gt.S;
+    S.AC = options.set_ac.unwrap_or(S.AC);
+    S.X = options.set_x.unwrap_or(S.X);
+    S.Y = options.set_y.unwrap_or(S.Y);
+    if let Some(pc) = options.set_pc {
+        S.PC = pc;
+        S.IR = 0x02; // nop, so the garbled instruction in the pipeline can't jump away
+    }
+    if options.verify_determinism {
+        if loaded.is_err() {
+            std::process::exit(1);
+        }
+        let diverged = verify_determinism(&options, &E.gt, seed);
+        std::process::exit(if diverged { 1 } else { 0 });
+    }
+    if lockstep {
+        if loaded.is_err() {
+            std::process::exit(1);
+        }
+        let diverged = run_lockstep(&options, &mut E.gt, seed, &patches);
+        std::process::exit(if diverged { 1 } else { 0 });
+    }
+    if loaded.is_err() {
+        if options.frames.is_some() {
+            std::process::exit(1); // Nothing to run frames of
+        }
+        E.load_test_pattern();
+    } else if options.frames.is_none() && E.gt.check_rom().is_err() {
+        // Already warned about; a window would only stay blank
+        E.load_test_pattern();
+    }
+    E.frames = options.frames;
+    E.max_cycles = options.max_cycles;
+    E.on_halt = options.on_halt;
+    if let Some(filename) = &options.record_input {
+        match Recorder::create(filename) {
+            Ok(recorder) => E.recorder = Some(recorder),
+            Err(e) => {
+                error!("Can't record input to {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(filename) = &options.replay_input {
+        match Replay::read(filename) {
+            Ok(replay) => E.replay = Some(replay),
+            Err(e) => {
+                error!("Can't replay input from {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(filename) = &options.input_script {
+        match script::read(filename) {
+            Ok(events) => E.replay = Some(Replay::from_events(events)),
+            Err(e) => {
+                error!("Can't read input script {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(filename) = &options.record_delta {
+        match DeltaRecorder::create(filename) {
+            Ok(mut recorder) => {
+                if let Some(fps) = options.record_fps {
+                    recorder.set_fps(fps);
                 }
+                E.delta = Some(recorder);
             }
-
-            //println!("joystick = {:?}", j);
-            
-            match j {
-                Direction::Right => { self.RAM[0x0011] = 0b1111_1110; self.RAM[0x0010] = 0; } // Bit 0
-                Direction::Left => { self.RAM[0x0011] = 0b1111_1101; self.RAM[0x0010] = 0; } // Bit 1
-                Direction::Down => { self.RAM[0x0011] = 0b1111_1011; self.RAM[0x0010] = 0; } // Bit 2
-                Direction::Up => { self.RAM[0x0011] = 0b1111_0111; self.RAM[0x0010] = 0; } // Bit 3
-                Direction::Start => { self.RAM[0x0011] = 0b1110_1111; self.RAM[0x0010] = 0; }, // Bit 4
-                Direction::Select => { self.RAM[0x0011] = 0b1101_1111; self.RAM[0x0010] = 0; }, // Bit 5
-                Direction::ButtonB => { self.RAM[0x0011] = 0b1011_1111; self.RAM[0x0010] = 0; }, // Bit 6
-                Direction::ButtonA => { self.RAM[0x0011] = 0b0111_1111; self.RAM[0x0010] = 0; }, // Bit 7
+            Err(e) => {
+                error!("Can't record the screen to {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(name) = &options.launch_game {
+        match Launcher::new(name) {
+            Ok(launcher) => E.launcher = Some(launcher),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+    if let Some(name) = &options.shm_output {
+        match ShmOutput::create(name) {
+            Ok(shm) => E.shm_output = Some(shm),
+            Err(e) => {
+                error!("Can't publish the screen to {}: {}", name, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(addr) = &options.state_server {
+        match StateServer::open(addr) {
+            Ok(server) => E.state_server = Some(server),
+            Err(e) => {
+                error!("Can't serve the machine state on {}: {}", addr, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(addr) = &options.input_socket {
+        match InputSocket::open(addr, options.stick_map) {
+            Ok(socket) => E.socket = Some(socket),
+            Err(e) => {
+                error!("Can't take controller input from {}: {}", addr, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if options.boot_trace {
+        E.boot_trace = Some(BootTrace::new(&mut E.gt));
+    }
+    if let Some(filename) = &options.trace_draws {
+        match DrawTrace::create(filename, &mut E.gt, options.trace_draws_box) {
+            Ok(trace) => E.draw_trace = Some(trace),
+            Err(e) => {
+                error!("Can't trace draws to {}: {}", filename, e);
+                std::process::exit(1);
             }
-            
-            self.joy = Some(j);
         }
     }
+    if let Some(filename) = &options.gt1 {
+        match Gt1::read(filename) {
+            Ok(gt1) => E.gt.gt1 = Some(gt1),
+            Err(e) => {
+                error!("Can't load {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    E.gt.fast_load = options.fast_load;
+    E.gt.rtc_addr = options.rtc_addr;
+    if let Some(filename) = &options.uart_out {
+        let out: Box<dyn Write> = if filename == "-" {
+            Box::new(std::io::stdout())
+        } else {
+            match File::create(filename) {
+                Ok(file) => Box::new(file),
+                Err(e) => {
+                    error!("Can't write UART output to {}: {}", filename, e);
+                    std::process::exit(1);
+                }
+            }
+        };
+        E.gt.attach(Box::new(Uart::new(options.uart_baud, out)));
+    }
+    if let Some(filename) = &options.host_file {
+        match HostFile::open(filename) {
+            Ok(file) => E.gt.host_file = Some(file),
+            Err(e) => {
+                error!("Can't open host file {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    E.renderer = options.renderer;
+    E.palette = options.palette;
+    E.border = options.border;
+    E.crop = options.crop;
+    E.render_ram = options.render_ram;
+    E.pixel_aspect = options.pixel_aspect.map(|aspect| aspect.0);
+    if let Some(grid) = options.grid {
+        E.grid = grid;
+        E.show_grid = true;
+    }
+    E.show_coords = options.show_coords;
+    E.show_pad = options.show_pad;
+    E.paused = options.debug;
+    if let Some(filename) = &options.debug_script {
+        let text = std::fs::read_to_string(filename).unwrap_or_else(|e| {
+            error!("Can't read debugger script {}: {}", filename, e);
+            std::process::exit(1);
+        });
+        match debugger::parse_script(&text) {
+            Ok(commands) => E.debug_script = commands.into(),
+            Err(e) => {
+                error!("Debugger script {}, {}", filename, e);
+                std::process::exit(2);
+            }
+        }
+        E.paused = true;
+    }
+    if let Some(filename) = &options.debug_record {
+        match File::create(filename) {
+            Ok(file) => E.debug_record = Some(file),
+            Err(e) => {
+                error!("Can't record debugger commands to {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    E.breaks = options.breaks.iter().map(|condition| (condition.clone(), false)).collect();
+    if let Some(condition) = options.break_on_write {
+        E.break_on(condition);
+    }
+    E.capture = options.capture_at_frame.map(|frame| Capture {
+        frame,
+        file: options.capture_file.clone().unwrap_or_else(|| format!("frame-{}.png", frame)),
+        pause: options.capture_pause,
+    });
+    E.video.autorepeat = options.key_repeat;
+    E.flip_x = options.flip_x;
+    E.flip_y = options.flip_y;
+    E.crt = options.crt || options.crt_bleed;
+    E.crt_bleed = options.crt_bleed;
+    if options.watch {
+        match &options.rom {
+            Some(filename) => E.watch = Some(RomWatch::new(filename, patches, seed, E.clock.now())),
+            None => error!("--watch needs a --rom file"),
+        }
+    }
+    if let Some(speed) = options.speed {
+        E.pacer = Some(Pacer::new(speed, E.clock.now(), E.gt.cycles()));
+    }
+    if options.profile_drift {
+        E.drift = Some(Drift::new(E.clock.now(), E.gt.cycles()));
+    }
+    E.profile_sync = options.profile_sync;
+    if options.profile_refresh {
+        E.refresh = Some(RefreshRate::new());
+    }
+    E.gt.trace_sys = options.trace_sys;
+    if options.pc_profile {
+        E.gt.enable_pc_profile();
+    }
+    if options.check_vcpu_code {
+        E.gt.enable_vcpu_code_check();
+    }
+    if let Some(depth) = options.history {
+        E.gt.enable_history(depth);
+    }
+    if options.profile_vblank {
+        E.vblank_profile = Some(VblankProfile::new(E.clock.now()));
+    }
+    if options.profile_render {
+        E.render_profile = Some(RenderProfile::new(E.clock.now()));
+    }
+    if options.show_expansion {
+        match options.frontend {
+            Frontend::Window => match ExpansionWindow::new() {
+                Ok(window) => E.expansion = Some(window),
+                Err(e) => error!("Can't open the expansion window: {}", e),
+            },
+            Frontend::Tty | Frontend::Headless => error!("--show-expansion needs the window frontend"),
+        }
+    }
+    if options.show_ram {
+        match options.frontend {
+            Frontend::Window => E.toggle_ram_window(),
+            Frontend::Tty | Frontend::Headless => error!("--show-ram needs the window frontend"),
+        }
+    }
+    if let Some(addr) = options.vpc_addr {
+        E.gt.vectors = VcpuVectors::at(addr);
+    }
+    if let Some(limit) = options.cycles_per_frame {
+        E.cycles_per_frame = Some(limit);
+        E.video.set_target_fps(60);
+    }
+    let reference = options.diff_ram.as_ref().map(|filename| {
+        match read_ram_image(filename, E.gt.RAM.len()) {
+            Ok(image) => image,
+            Err(e) => {
+                error!("Can't read RAM image {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        }
+    });
 
-    fn run(&mut self) {
-        let delay = Duration::from_nanos(160);
-        while self.active {
-            if self.t < 0 {
-                self.S.PC = 0; // MCP100 Power-On Reset
+    match &options.play {
+        Some(filename) => match DeltaPlayer::open(filename) {
+            Ok(player) => E.play(player, options.record_fps.unwrap_or(60)),
+            Err(e) => {
+                error!("Can't play {}: {}", filename, e);
+                std::process::exit(1);
+            }
+        },
+        None => E.run(),
+    }
+    if let Some(delta) = &mut E.delta
+        && let Err(e) = delta.finish()
+    {
+        error!("Can't finish the screen recording: {}", e);
+    }
+    if let Some(trace) = &mut E.draw_trace
+        && let Err(e) = trace.finish()
+    {
+        error!("Can't finish the draw trace: {}", e);
+    }
+    if let Some(trace) = &E.boot_trace {
+        trace.finish(&E.gt);
+    }
+
+    if let Some(filename) = &options.screenshot {
+        match png::write(filename, E.video.width, E.video.height, &E.video.buffer) {
+            Ok(()) => info!("Screenshot written to {}", filename),
+            Err(e) => error!("Can't write screenshot to {}: {}", filename, e),
+        }
+    }
+    if let Some(filename) = &options.dump_ppm {
+        let written = match options.ppm_size {
+            PpmSize::Full => ppm::write(filename, E.video.width, E.video.height, &E.video.buffer),
+            PpmSize::Logical => {
+                let rgb: Vec<u32> = E.gt.frame().iter().map(|&v| makeRGB(&E.palette.color(v))).collect();
+                ppm::write(filename, SCREEN_WIDTH, SCREEN_HEIGHT, &rgb)
             }
-            let mut T: CpuState = self.cpuCycle(); // Update CPU
-            self.process(&mut T);
-            self.S = T;
-            self.t += 1;
-            //busy_wait(delay);
+        };
+        match written {
+            Ok(()) => info!("Frame written to {}", filename),
+            Err(e) => error!("Can't write frame to {}: {}", filename, e),
         }
     }
+
+    if options.pc_profile {
+        let mut out = std::io::stdout().lock();
+        if let Err(e) = E.gt.write_pc_profile(&mut out) {
+            error!("Can't write the PC profile: {}", e);
+        }
+    }
+    if let Some(filename) = &options.dump_ram {
+        match E.gt.write_ram(filename) {
+            Ok(()) => info!("RAM written to {}", filename),
+            Err(e) => error!("Can't write RAM to {}: {}", filename, e),
+        }
+    }
+    if let Some(reference) = reference {
+        print_ram_diff(&E.gt.diff_ram(&reference));
+    }
+    if E.cycle_limit_hit || E.crashed {
+        std::process::exit(1);
+    }
 }
 
-impl CpuState {
-    pub fn new() -> Self {
-        CpuState {
-            PC: 0,
-            IR: 0,
-            D: 0,
-            AC: 0,
-            X: 0,
-            Y: 0,
-            OUT: 0,
-            undef: 0,
+/// Runs a second machine next to `a` for --diff-rom/--diff-patch and prints
+/// where they diverged. Returns whether they did.
+fn run_lockstep(options: &Options, a: &mut Gigatron, seed: u64, patches: &[Patch]) -> bool {
+    let exit = |what: &str, filename: &str, e: GigatronError| -> ! {
+        error!("Can't {} {}: {}", what, filename, e);
+        std::process::exit(1);
+    };
+    let mut b = Gigatron::new();
+    b.rom_byteswap = options.rom_byteswap;
+    match (&options.diff_rom, &options.rom) {
+        (Some(filename), _) => b.read_rom(filename).unwrap_or_else(|e| exit("load ROM", filename, e)),
+        (None, Some(filename)) => {
+            b.read_rom(filename).unwrap_or_else(|e| exit("load ROM", filename, e));
+            b.apply_patches(patches);
+        }
+        (None, None) => {
+            b.read_rom_bytes(ROM6).unwrap_or_else(|e| exit("load ROM", "ROMv6", e));
+            b.apply_patches(patches);
+        }
+    }
+    if let Some(filename) = &options.diff_patch {
+        let patches = patch::read(filename).unwrap_or_else(|e| exit("patch ROM from", filename, e));
+        b.apply_patches(&patches);
+    }
+    // Same seed and power-on, so the bus noise agrees, then the same state
+    b.seed(seed);
+    b.clean_boot = a.clean_boot;
+    b.ram_fill = a.ram_fill;
+    b.set_ram_size(a.ram_size());
+    b.port.polarity = a.port.polarity;
+    b.reset_hold = a.reset_hold;
+    b.init();
+    b.restore(&a.state());
+
+    let replays = options.replay_input.as_ref().map(|filename| {
+        let read = || Replay::read(filename).unwrap_or_else(|e| exit("replay input from", filename, e));
+        (read(), read())
+    });
+    let frames = options.frames.unwrap_or(600);
+    match lockstep::run(a, &mut b, replays, frames) {
+        Some(divergence) => {
+            println!("{}", lockstep::report(&divergence));
+            true
+        }
+        None => {
+            println!("No divergence in {} frames ({} cycles)", a.frames(), a.cycles());
+            false
         }
     }
 }
 
-fn main() {
-    let mut E: Gigatron = Gigatron::new();
-//    E.read_rom("ROMv1.rom").expect("No ROM.");
-//    E.read_rom("ROMv2.rom").expect("No ROM.");
-//    E.read_rom("ROMv6.rom").expect("No ROM.");
-//    E.read_rom("ROMvX0.rom").expect("No ROM.");
-    E.restore_rom(&ROM6.to_vec());
-    E.init();
-    E.run();
+/// Powers on the (patched) ROM of `gt` twice with `seed` and its power-on
+/// settings for --verify-determinism, runs both and prints the first
+/// checkpoint where they disagreed. Returns whether they did.
+fn verify_determinism(options: &Options, gt: &Gigatron, seed: u64) -> bool {
+    let image = gt.rom_slice(0, 1 << 16).concat();
+    let power_on = || {
+        GigatronBuilder::new()
+            .rom_bytes(&image)
+            .seed(seed)
+            .clean_boot(gt.clean_boot)
+            .ram_fill(gt.ram_fill)
+            .ram_size(gt.ram_size())
+            .reset_hold(gt.reset_hold)
+            .polarity(gt.port.polarity)
+            .build()
+            .unwrap_or_else(|e| {
+                error!("Can't load the ROM again: {}", e);
+                std::process::exit(1);
+            })
+    };
+    let (mut a, mut b) = (power_on(), power_on());
+    let replays = options.replay_input.as_ref().map(|filename| {
+        let read = || {
+            Replay::read(filename).unwrap_or_else(|e| {
+                error!("Can't replay input from {}: {}", filename, e);
+                std::process::exit(1);
+            })
+        };
+        (read(), read())
+    });
+    let frames = options.frames.unwrap_or(600);
+    match lockstep::verify(&mut a, &mut b, replays, frames) {
+        Some(divergence) => {
+            println!("{}", lockstep::report(&divergence));
+            true
+        }
+        None => {
+            println!("Deterministic: both runs agreed at every vSync for {} frames ({} cycles)", a.frames(), a.cycles());
+            false
+        }
+    }
+}
+
+/// Reads a RAM image of exactly `size` bytes, the RAM fitted.
+fn read_ram_image(filename: &str, size: usize) -> Result<Vec<u8>, GigatronError> {
+    let mut buffer = Vec::new();
+    File::open(filename)?.read_to_end(&mut buffer)?;
+    if buffer.len() != size {
+        return Err(GigatronError::RamSize { expected: size, got: buffer.len() });
+    }
+    Ok(buffer)
+}
+
+/// Prints two saved states' registers and latches side by side, differing
+/// ones marked, then how A's RAM differs from B's (--diff-states). True
+/// when nothing does.
+fn print_state_diff(a: &State, b: &State) -> bool {
+    let fields = |state: &State| {
+        let S = &state.cpu;
+        [S.PC, S.IR as u16, S.D as u16, S.AC as u16, S.X as u16, S.Y as u16, S.OUT as u16, S.undef as u16,
+            state.input as u16, state.xout as u16, state.ctrl]
+    };
+    const NAMES: [&str; 11] = ["PC", "IR", "D", "AC", "X", "Y", "OUT", "undef", "IN", "XOUT", "ctrl"];
+    let (fields_a, fields_b) = (fields(a), fields(b));
+    println!("{:<5} {:>6} {:>7}", "", "A", "B");
+    for ((name, a), b) in NAMES.iter().zip(fields_a).zip(fields_b) {
+        let width = if ["PC", "ctrl"].contains(name) { 4 } else { 2 };
+        let mark = if a != b { "  *" } else { "" };
+        println!("{:<5} {:>6} {:>7}{}", name, format!("${:0w$x}", a, w = width), format!("${:0w$x}", b, w = width), mark);
+    }
+    let same_size = a.ram.len() == b.ram.len();
+    let (size_a, size_b) = (format!("{}K", a.ram.len() >> 10), format!("{}K", b.ram.len() >> 10));
+    println!("{:<5} {:>6} {:>7}{}", "RAM", size_a, size_b, if same_size { "" } else { "  *" });
+    let elapsed = b.cycles as i64 - a.cycles as i64;
+    println!("cycles {} -> {} ({:+} cycles, {:+.3} s)", a.cycles, b.cycles, elapsed, elapsed as f64 / CLOCK_HZ);
+    if !same_size {
+        println!("Comparing the first {}K of RAM only", a.ram.len().min(b.ram.len()) >> 10);
+    }
+    let mut gt = Gigatron::new();
+    gt.restore(a);
+    let diff = gt.diff_ram(&b.ram);
+    print_ram_diff(&diff);
+    fields_a == fields_b && same_size && diff.is_empty()
+}
+
+fn print_ram_diff(diff: &[(u16, u8, u8)]) {
+    if diff.is_empty() {
+        println!("RAM is identical to the reference");
+    }
+    for page in diff.chunk_by(|a, b| a.0 >> 8 == b.0 >> 8) {
+        println!("Page {:#04x}: {} byte(s) differ", page[0].0 >> 8, page.len());
+        for (addr, ours, theirs) in page {
+            println!("  {:04x}: {:02x} (reference {:02x})", addr, ours, theirs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gigatron_example::clock::ManualClock;
+    use gigatron_example::RamSize;
+
+    /// Cycles in `seconds` of emulated time; every time used here is a
+    /// sum of powers of two, so the waits come out exact.
+    fn cycles(seconds: f64) -> u64 {
+        (seconds * CLOCK_HZ) as u64
+    }
+
+    #[test]
+    fn pacer_holds_emulated_time_to_real_time() {
+        let clock = ManualClock::new();
+        let mut pacer = Pacer::new(1.0, clock.now(), 0);
+        pacer.wait(&clock, cycles(0.5));
+        assert_eq!(clock.now(), Duration::from_millis(500));
+        let mut fast = Pacer::new(2.0, clock.now(), cycles(0.5));
+        fast.wait(&clock, cycles(1.5));
+        assert_eq!(clock.now(), Duration::from_secs(1));
+    }
+
+    /// Falling behind by less than PACE_SLACK is made up by not waiting
+    /// until emulation is on time again.
+    #[test]
+    fn pacer_catches_up_after_a_short_stall() {
+        let clock = ManualClock::new();
+        let mut pacer = Pacer::new(1.0, clock.now(), 0);
+        clock.advance(Duration::from_millis(125));
+        pacer.wait(&clock, cycles(0.0625));
+        assert_eq!(clock.now(), Duration::from_millis(125));
+        pacer.wait(&clock, cycles(0.5));
+        assert_eq!(clock.now(), Duration::from_millis(500));
+    }
+
+    /// Falling further behind, or a reset taking the cycle count back,
+    /// paces from there instead.
+    #[test]
+    fn pacer_restarts_after_a_long_stall_or_a_reset() {
+        let clock = ManualClock::new();
+        let mut pacer = Pacer::new(1.0, clock.now(), 0);
+        clock.advance(Duration::from_secs(1));
+        pacer.wait(&clock, cycles(0.125));
+        assert_eq!(clock.now(), Duration::from_secs(1));
+        pacer.wait(&clock, cycles(0.375));
+        assert_eq!(clock.now(), Duration::from_millis(1250));
+        pacer.wait(&clock, 0);
+        assert_eq!(clock.now(), Duration::from_millis(1250));
+        pacer.wait(&clock, cycles(0.25));
+        assert_eq!(clock.now(), Duration::from_millis(1500));
+    }
+
+    /// States with different RAM sizes differ, even where the RAM they
+    /// share agrees.
+    #[test]
+    fn state_diff_of_ram_sizes() {
+        let mut gt = Gigatron::new();
+        let small = gt.state();
+        assert!(print_state_diff(&small, &small));
+        gt.set_ram_size(RamSize::K64);
+        assert!(!print_state_diff(&small, &gt.state()));
+    }
+
+    /// No key does two things at once: two bindings of a key need
+    /// modifiers that can't both be down.
+    #[test]
+    fn bindings_dont_overlap() {
+        for (i, a) in BINDINGS.iter().enumerate() {
+            for b in &BINDINGS[i + 1..] {
+                let apart = matches!((a.mods, b.mods), (Mods::None, Mods::Shift | Mods::Ctrl) | (Mods::Shift | Mods::Ctrl, Mods::None));
+                assert!(a.key != b.key || apart, "{} is {:?} and {:?}", a.label(), a.action, b.action);
+            }
+        }
+    }
+
+    /// The help has a line for each action, zooming in taking both its keys.
+    #[test]
+    fn help_lists_bindings() {
+        let lines = help_lines();
+        assert_eq!(lines.len(), BINDINGS.len() - 2);
+        assert!(lines.iter().any(|line| line.starts_with("Num+ Ctrl+=") && line.ends_with("  Zoom in")), "{:#?}", lines);
+    }
 }
@@ -0,0 +1,63 @@
+//! The RAM locations the ROM and the emulator share, by their ROMv6 names
+//! where the ROM sources have one. ROMv1 through ROMv6 keep these in place;
+//! the newer video variables only mean something from ROMv5a on.
+
+/// Scanline the video kernel is on (videoY), odd during vertical blank.
+pub const VIDEO_Y: u16 = 0x0009;
+
+/// Frames since power-on, wrapping (frameCount).
+pub const FRAME_COUNT: u16 = 0x000e;
+
+/// The byte on IN as the ROM last sampled it, controller or keyboard
+/// alike (serialRaw).
+pub const KEYBOARD_CHAR: u16 = 0x000f;
+
+/// serialRaw of the frame before, which tells a new key from a held one
+/// (serialLast).
+pub const KEYBOARD_FLAG: u16 = 0x0010;
+
+/// Controller buttons as the ROM debounces them, active-low (buttonState).
+pub const JOYSTICK: u16 = 0x0011;
+
+/// vCPU program counter; vAC, vLR and vSP follow it (vPC).
+pub const VPC: u16 = 0x0016;
+
+/// ROM version in the upper 6 bits, set during boot (romType).
+pub const ROM_TYPE: u16 = 0x0021;
+
+/// SYS function the vCPU calls next, little-endian (sysFn).
+pub const SYS_FN: u16 = 0x0022;
+
+/// 120 pairs of (page, X offset), one per logical scanline (videoTable).
+pub const VIDEO_TABLE: u16 = 0x0100;
+
+/// Scanline pairs blanked at the top of the screen, ROMv5a and later
+/// (videoTop_v5).
+pub const VIDEO_TOP: u16 = 0x01f9;
+
+/// Page 8, where the ROM puts the first screen row; row `y` starts at
+/// `VIDEO_BASE + 256 * y` in the default layout.
+pub const VIDEO_BASE: u16 = 0x0800;
+
+/// The locations above that hold a value, not a table, with their names
+/// and sizes in bytes, as [`Gigatron::io_values`](crate::Gigatron::io_values)
+/// reports them.
+pub const LOCATIONS: [(&str, u16, usize); 8] = [
+    ("videoY", VIDEO_Y, 1),
+    ("frameCount", FRAME_COUNT, 1),
+    ("serialRaw", KEYBOARD_CHAR, 1),
+    ("serialLast", KEYBOARD_FLAG, 1),
+    ("buttonState", JOYSTICK, 1),
+    ("vPC", VPC, 2),
+    ("romType", ROM_TYPE, 1),
+    ("sysFn", SYS_FN, 2),
+];
+
+/// A labeled location and what it holds now; words are little-endian.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IoValue {
+    pub name: &'static str,
+    pub addr: u16,
+    pub size: usize,
+    pub value: u16,
+}
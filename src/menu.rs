@@ -0,0 +1,72 @@
+//! The ROM's boot menu: which programs it offers and where, and where its
+//! cursor is, read off the screen. Enough to pick a program without a
+//! human at the controller.
+
+use crate::SCREEN_WIDTH;
+
+/// A menu of programs in columns, one row per text line, with a cursor
+/// arrow left of the selected one.
+pub struct Menu {
+    pub columns: &'static [[&'static str; 6]],
+    arrow_x: [usize; 2], // Left edge of the arrow, by column
+    arrow_y: usize,      // Top of the arrow on the first row ...
+    row_pitch: usize,    // ... and on each row after it
+}
+
+/// ROMv6's menu, as it first shows. Up and Down move within a column and
+/// stop at its ends; Left and Right keep the row.
+const ROM_V6: Menu = Menu {
+    columns: &[
+        ["Snake", "Racer", "Pictures", "Mandelbrot", "Credits", "Loader"],
+        ["GtMine", "Bricks", "TicTacToe", "BASIC", "MS BASIC", "Apple-1"],
+    ],
+    arrow_x: [11, 89],
+    arrow_y: 33,
+    row_pitch: 8,
+};
+
+/// Screen background behind the menu.
+const BACKGROUND: u8 = 0x20;
+
+/// Pixels of the arrow glyph, from the top left of its 5x5 cell. Its color
+/// cycles, so only the shape says it is there.
+const ARROW: [(usize, usize); 9] = [(2, 0), (3, 1), (0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (3, 3), (2, 4)];
+
+/// Menus by romType (its upper 6 bits, as in RAM at 0x0021).
+const MENUS: [(u8, &Menu); 1] = [(crate::ROM_V6, &ROM_V6)];
+
+/// The menu of a ROM of `rom_type`, if it is known.
+pub fn for_rom(rom_type: u8) -> Option<&'static Menu> {
+    MENUS.iter().find(|(version, _)| *version == rom_type & 0xfc).map(|(_, menu)| *menu)
+}
+
+/// Every program name some known menu offers.
+pub fn all_programs() -> impl Iterator<Item = &'static str> {
+    MENUS.iter().flat_map(|(_, menu)| menu.columns.iter().flatten().copied())
+}
+
+impl Menu {
+    /// (column, row) of the program named `name`, ignoring case.
+    pub fn find(&self, name: &str) -> Option<(usize, usize)> {
+        self.columns.iter().enumerate().find_map(|(column, names)| {
+            names.iter().position(|item| item.eq_ignore_ascii_case(name)).map(|row| (column, row))
+        })
+    }
+
+    /// (column, row) the cursor is on in `frame`, laid out as
+    /// [`Gigatron::frame`](crate::Gigatron::frame) returns it; None while
+    /// the menu isn't on the screen.
+    pub fn selected(&self, frame: &[u8]) -> Option<(usize, usize)> {
+        (0..self.columns.len())
+            .flat_map(|column| (0..self.columns[column].len()).map(move |row| (column, row)))
+            .find(|&(column, row)| {
+                let (x, y) = (self.arrow_x[column], self.arrow_y + row * self.row_pitch);
+                let at = |dx: usize, dy: usize| frame[(y + dy) * SCREEN_WIDTH + x + dx];
+                let color = at(2, 2);
+                color != BACKGROUND
+                    && ARROW.iter().all(|&(dx, dy)| at(dx, dy) == color)
+                    && at(0, 0) == BACKGROUND
+                    && at(4, 4) == BACKGROUND
+            })
+    }
+}
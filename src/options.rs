@@ -0,0 +1,1044 @@
+use crate::Crop;
+use gigatron_example::debugger::Condition;
+use gigatron_example::input::{Polarity, StickMap};
+use gigatron_example::{uart, Palette, RamFill, RamSize};
+use log::LevelFilter;
+use std::str::FromStr;
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: gigatron-example [OPTIONS]
+
+Options:
+    --log-level <LEVEL>   Log verbosity: off, error, warn, info, debug, trace (default: info)
+    --quiet               Suppress all non-error output
+    --frontend <NAME>     Where to show the screen: window, tty, headless (nowhere, running
+                          as fast as the host allows; needs --frames) (default: window)
+    --headless            Same as --frontend headless
+    --frames <N>          Exit once N frames (vSync edges since power-on) have been completed
+    --max-cycles <N>      Stop with exit status 1 once N cycles have run since power-on, in
+                          case the ROM hangs (e.g. before reaching --frames)
+    --on-halt <ACTION>    What to do when the ROM spends ten frames in a loop of up to 4 words
+                          that stores nothing, after logging it as a deliberate halt or a
+                          crash: log (nothing more), pause in the debugger, or exit (status 1
+                          for a crash) (default: log)
+    --screenshot <FILE>   Save the last presented frame as a PNG on exit
+    --dump-ppm <FILE>     Save the last frame as a binary PPM (P6) on exit
+    --ppm-size <SIZE>     What --dump-ppm saves: full (the presented frame, as --screenshot)
+                          or logical (the 160x120 screen, a pixel each) (default: full)
+    --capture-at-frame <N>
+                          Save the frame completed at vSync N as a PNG, to reproduce a glitch
+                          seen there (with --seed, the same frame every run)
+    --capture-file <FILE> Where --capture-at-frame saves it (default: frame-N.png)
+    --capture-pause       Break into the debugger once it is saved
+    --rom <FILE>          ROM image to run, optionally gzipped (default: built-in ROMv6)
+    --session <DIR>       Reproduce a session: DIR/rom is the ROM (unless --rom is given),
+                          DIR/ram a RAM image loaded after power-on, and DIR/options more
+                          options, which the command line overrides; all are optional
+    --rom-byteswap        The ROM image stores each word data byte first
+    --speed <X>           Pace emulation at X times real speed, e.g. 0.25 for slow motion or 2
+                          (default: as fast as the host runs it); holding Page Down runs
+                          unpaced until it is let go
+    --cycles-per-frame <N>
+                          Present a frame every N cycles (paced at 60 fps, except while Page
+                          Down is held) instead of on vSync
+    --renderer <NAME>     How the screen is reconstructed: table (from RAM through the
+                          videoTable), beam (from OUT as the beam sweeps) (default: table);
+                          Shift+F8 switches between them
+    --render <MODE>       Where the screen is scaled up to the window: full-res (by the
+                          emulator, every window pixel), native-res (the emulator draws
+                          160x120 and the window scales it, less CPU and memory traffic;
+                          --crt then darkens every other logical row) (default: full-res)
+    --border <RRGGBB>     Color of blanked beam positions with --renderer beam, and of the bars
+                          of --pixel-aspect (default: 000000)
+    --palette <NAME>      Color levels: default (0x03, 0x0f, 0x3f, 0xff per channel), resistor
+                          (from the VGA resistor DAC: 0, 82, 173, 255; see RESISTOR_LEVELS
+                          in src/lib.rs) (default: default)
+    --crop <X,Y,W,H>      Show only this region of the 160x120 screen (default: 0,0,160,120)
+    --render-ram <START-END>
+                          Show only the pixels read from RAM START to END (hex with 0x or $, or
+                          decimal), black elsewhere, to see which memory feeds which part of
+                          the screen (table renderer)
+    --pixel-aspect <RATIO>
+                          Keep each pixel this wide over tall, with --border color bars
+                          around the picture: square, vga (1.007, as a monitor shows the
+                          signal), or a number such as 1.2 or 6:5 (default: fill the window)
+    --grid <N>            Draw faint guide lines every N logical pixels (toggle with F11,
+                          which shows them every 8 by default)
+    --show-coords         Show the logical pixel under the mouse and its color (toggle with F12)
+    --show-pad            Show the controller at the bottom of the window, buttons lit while held;
+                          kept out of screenshots and recordings (toggle with Shift+F3)
+    --crt                 Darken every other row for a CRT look (toggle with F8)
+    --crt-bleed           Like --crt, also bleeding each pixel into its right neighbor
+    --flip-x              Mirror the screen left to right (toggle with F6)
+    --flip-y              Mirror the screen top to bottom (toggle with F7)
+    --watch               Reload the --rom file and reset whenever it changes, with the same seed
+    --patch <FILE>        Apply `address: word` ROM patches (hex) after loading the ROM
+    --fix-video           Replace the ROM's video kernel ($0100-$02ff) with the built-in
+                          ROMv6's, to salvage a ROMv6 dump whose sync is broken (before --patch)
+    --font <FILE>         Replace the ROM's font with FILE's glyphs: 5 bytes each, one per column
+                          with the top pixel in bit 7, from ' ' on (ROMv6: 100 glyphs, 500 bytes)
+    --diff-rom <FILE>     Run FILE alongside the ROM in lockstep, without a window, and report
+                          the first cycle their registers or screens differ (for --frames
+                          frames, default 600); both get the same seed and --replay-input
+    --diff-patch <FILE>   Like --diff-rom, with the second machine running the same (patched)
+                          ROM with FILE's patches on top; combines with --diff-rom
+    --verify-determinism  Power the ROM on twice with the same seed and --replay-input, run
+                          both for --frames frames (default 600) without a window, and report
+                          the first vSync at which their registers, RAM or screens differ
+    --disasm-out <FILE>   Write a disassembly of the (patched) ROM to FILE and exit
+    --batch <DIR>         Run every ROM image in DIR (*.rom, *.rom.gz, *.bin) without a window
+                          for --frames frames (default 200) with --seed (default 0), print
+                          whether each booted, its frame length and screen hash, and exit
+    --batch-csv           Print the --batch report as CSV instead of a table
+    --selftest            Check the emulated CPU against known results for every opcode and exit
+    --gt1 <FILE>          Place a GT1 program in RAM once the ROM has booted
+    --fast-load           Start the GT1 program right away instead of via the Loader
+    --vpc-addr <ADDR>     Zero-page address of vPC for --fast-load (default: 0x0016)
+    --seed <N>            Seed for power-on RAM garbling and bus noise (default: random)
+    --reset-hold <N>      Cycles the MCP100 holds the CPU in reset after power-on (default: 2)
+    --cold-start <FRAMES> Hold reset FRAMES frames longer on every power-on, showing the
+                          garbled RAM meanwhile, for the look of a machine just switched on
+                          (default: 0, straight to the ROM)
+    --clean-boot          Power on with zeroed RAM and registers instead of garbling them
+    --ram-fill <PATTERN>  Power-on RAM contents: random, random-blank-screen (random, but the
+                          visible 160 bytes of pages 0x08-0x7f and the videoTable zeroed),
+                          zero, ones, checker (default: random, zero with --clean-boot); the
+                          ROM overwrites the zero page, pages 1-7 and the visible screen during
+                          boot
+    --ram <KB>            RAM fitted: 32, with the upper half of the address space mirroring
+                          the lower, or 64 (default: 32)
+    --joystick-polarity <LEVEL>
+                          Level on IN for a held controller button: low (the original
+                          controller, idle reads 0xff), high (idle reads 0x00) (default: low)
+    --key-repeat <DELAY,RATE>
+                          Type a held key's character again after DELAY ms, then RATE times a
+                          second (the port takes at most 15), or off (default: 500,30)
+    --set-ac <N>          Start with AC set to N (after garbling or --load-garble)
+    --set-x <N>           Start with X set to N
+    --set-y <N>           Start with Y set to N
+    --set-pc <ADDR>       Start fetching at ADDR: reset is released at once, and the
+                          instruction in the pipeline is a nop
+    --save-garble <FILE>  Save the state the machine powered on in (after garbling) to FILE
+    --load-garble <FILE>  Power on in the state saved in FILE instead of garbling
+    --record-input <FILE> Record all input with cycle timestamps
+    --replay-input <FILE> Replay recorded input instead of live input
+    --input-script <FILE> Replay input from a script of `at <cycle> press <buttons> [for <n>
+                          frames]` and `at <cycle> type <text>` lines (see src/script.rs)
+    --record-delta <FILE> Record the screen to FILE (.gtv), storing only the rows that changed
+    --record-fps <FPS>    Record --record-delta at FPS frames per second of emulated time,
+                          repeating or skipping ROM frames to keep the rate whatever the
+                          ROM's refresh (default: one per vSync); --play then plays at FPS
+    --play <FILE>         Play a --record-delta recording at 60 fps (or --record-fps) instead
+                          of running a ROM
+    --input-socket <ADDR> Take controller input from another process: listen on ADDR if it
+                          is host:port, else read the named pipe at path ADDR; each line is
+                          a state such as `right+a`, `none` or `0xfe` (see src/input_socket.rs)
+    --stick-map <DEAD,THRESHOLD>
+                          How --input-socket's `stick X,Y` lines hold directions: nothing
+                          within DEAD of the center, then an axis past THRESHOLD of the rest
+                          of the way to the edge holds its direction (default: 0.2,0.5)
+    --state-server <ADDR> Answer HTTP requests on ADDR (host:port) with the registers, cycle
+                          count, IN and the ROM's I/O variables as JSON, updated every vSync
+    --shm-output <NAME>   Publish the screen every vSync to the file NAME in /dev/shm (or at
+                          path NAME, with a /) for another process to map; 160x120 0x00RRGGBB
+                          pixels after a 32-byte header (see src/shm_output.rs)
+    --launch-game <NAME>  Start NAME from the ROM's boot menu, e.g. BASIC, by pressing the
+                          buttons that get there (known for ROMv6; see src/menu.rs)
+    --show-expansion      Show the LEDs and the expander state in a second window
+    --show-ram            Show all of RAM as a live grayscale map in a second window, a page
+                          per row (toggle with F9)
+    --show-boot           Log the first 16 instructions the ROM runs from reset, following its
+                          jumps, after loading it
+    --boot-trace          Log the boot milestones the ROM reaches (reset released, first vSync,
+                          screen cleared, first input poll, menu showing, ...) and at exit the
+                          ones it never did
+    --debug               Start in the debugger (F5 breaks into it while running)
+    --debug-script <FILE> Start in the debugger and run its commands from FILE, one per line
+                          as typed at the prompt, before reading more from stdin
+    --debug-record <FILE> Save the debugger commands typed at the prompt to FILE, for
+                          --debug-script
+    --history <N>         Keep the last N cycles run, so the debugger's back command can undo
+                          them one instruction at a time (about 32 bytes each)
+    --break <COND>        Break into the debugger when COND comes to hold, checked before
+                          every cycle, e.g. 'PC==0x0300 && AC==0': registers as the debugger
+                          shows them, RAM bytes as [ADDR] and numbers, compared with == != <
+                          <= > >= and joined by && and ||; may be given more than once
+    --break-on-write <ADDR=VALUE>
+                          Break into the debugger when the CPU stores VALUE at RAM address
+                          ADDR, printing the address of the storing instruction; ADDR!=VALUE
+                          breaks on storing anything else
+    --profile-vblank      Print once per second how many cycles the ROM spends in vertical blank
+    --profile-render      Print once per second how long drawing a frame and presenting it to
+                          the window take, average, min and max in milliseconds
+    --profile-drift       Print once per second how far emulated time is ahead of or behind
+                          the wall clock since start, and the speed over the last second
+    --profile-sync        Print once per second how many hSync and vSync edges the ROM has
+                          sent and their ratio, 521 lines per frame when in sync (F4 shows them)
+    --profile-refresh     Print the ROM's refresh rate, measured in emulated cycles between
+                          vSync edges, once it is steady and whenever it changes
+    --trace-draws <FILE>  Write every CPU store to a visible pixel to FILE, with its cycle,
+                          address, screen position and color (high volume)
+    --trace-draws-box <X,Y,W,H>
+                          Only trace the stores to this region of the screen (default: all)
+    --trace-sys           Log every native SYS function the vCPU calls, named for ROMv6
+    --check-vcpu-code     Log stores into RAM the vCPU has run code from: a program patching
+                          itself, or loaded over one that ran (the CPU only runs from ROM,
+                          which stores never reach)
+    --pc-profile          On exit, print how often each ROM address ran, hottest first
+    --dump-ram <FILE>     Write the RAM contents to FILE on exit
+    --diff-ram <FILE>     On exit, print how RAM differs from the image in FILE
+    --diff-states <A> <B> Print how the state saved in A (as by --save-garble) differs from
+                          the one in B: registers, I/O latches, cycle count and RAM, and exit
+    --rtc-addr <ADDR>     Write an RTC (seconds, minutes, hours UTC) to RAM at ADDR every frame
+    --uart-out <FILE>     Write bytes sent 8N1 on XOUT bit 0 to FILE (- for stdout)
+    --uart-baud <N>       Bit rate for --uart-out (default: 9600)
+    --host-file <FILE>    Let vCPU programs read and append to FILE through RAM 0x7ffe-0x7fff
+                          (not hardware; see src/host_file.rs for the protocol)
+    -h, --help            Print this help
+";
+
+/// How frames are presented.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frontend {
+    Window,
+    Tty,
+    Headless,
+}
+
+impl FromStr for Frontend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "window" => Ok(Frontend::Window),
+            "tty" => Ok(Frontend::Tty),
+            "headless" => Ok(Frontend::Headless),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How the screen is reconstructed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Renderer {
+    Table,
+    Beam,
+}
+
+impl FromStr for Renderer {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "table" => Ok(Renderer::Table),
+            "beam" => Ok(Renderer::Beam),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Who scales the 160x120 screen up to the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderSize {
+    Full,   // The emulator, into a window-sized buffer
+    Native, // The window, from a 160x120 buffer
+}
+
+impl FromStr for RenderSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "full-res" => Ok(RenderSize::Full),
+            "native-res" => Ok(RenderSize::Native),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What --dump-ppm saves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PpmSize {
+    Full,    // The presented frame, at the window's size
+    Logical, // The screen, 160x120
+}
+
+impl FromStr for PpmSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "full" => Ok(PpmSize::Full),
+            "logical" => Ok(PpmSize::Logical),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A store that breaks into the debugger (--break-on-write).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WriteBreak {
+    pub addr: u16,
+    pub value: u8,
+    pub equal: bool, // Break on storing `value`, or on storing anything else
+}
+
+impl WriteBreak {
+    pub fn matches(&self, value: u8) -> bool {
+        (value == self.value) == self.equal
+    }
+}
+
+impl FromStr for WriteBreak {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (addr, value, equal) = match s.split_once("!=") {
+            Some((addr, value)) => (addr, value, false),
+            None => s.split_once('=').map(|(addr, value)| (addr, value, true)).ok_or(())?,
+        };
+        let addr = parse_int(addr.trim()).and_then(|addr| u16::try_from(addr).ok()).ok_or(())?;
+        let value = parse_int(value.trim()).and_then(|value| u8::try_from(value).ok()).ok_or(())?;
+        Ok(WriteBreak { addr, value, equal })
+    }
+}
+
+/// RAM addresses the screen is shown from (--render-ram), both ends
+/// included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RamRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl RamRange {
+    pub fn contains(&self, addr: u16) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+}
+
+impl FromStr for RamRange {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (start, end) = s.split_once('-').ok_or(())?;
+        let addr = |s: &str| parse_int(s.trim()).and_then(|addr| u16::try_from(addr).ok()).ok_or(());
+        let (start, end) = (addr(start)?, addr(end)?);
+        if start > end {
+            return Err(());
+        }
+        Ok(RamRange { start, end })
+    }
+}
+
+/// Width over height of one logical pixel as shown (--pixel-aspect).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelAspect(pub f64);
+
+impl PixelAspect {
+    /// A monitor shows the 640x480 mode's pixels square. A logical pixel is
+    /// one 160 ns cycle wide, 4.03 of those pixels at 25.175 MHz, and 4
+    /// lines tall.
+    pub const VGA: PixelAspect = PixelAspect(160e-9 * 25.175e6 / 4.0);
+}
+
+impl FromStr for PixelAspect {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "square" => Ok(PixelAspect(1.0)),
+            "vga" => Ok(PixelAspect::VGA),
+            _ => match s.split_once(':') {
+                Some((w, h)) => {
+                    let (w, h): (f64, f64) = (w.parse().map_err(|_| ())?, h.parse().map_err(|_| ())?);
+                    Some(w / h)
+                }
+                None => s.parse().ok(),
+            }
+            .filter(|ratio| ratio.is_finite() && *ratio > 0.0)
+            .map(PixelAspect)
+            .ok_or(()),
+        }
+    }
+}
+
+/// What is done about the ROM halting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HaltAction {
+    Log,
+    Pause, // Break into the debugger
+    Exit,
+}
+
+impl FromStr for HaltAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "log" => Ok(HaltAction::Log),
+            "pause" => Ok(HaltAction::Pause),
+            "exit" => Ok(HaltAction::Exit),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Keyboard auto-repeat for a held key (--key-repeat).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Autorepeat {
+    Off,
+    On { delay: Duration, interval: Duration },
+}
+
+impl Autorepeat {
+    /// What desktops typically start with.
+    pub const DEFAULT: Autorepeat = Autorepeat::On {
+        delay: Duration::from_millis(500),
+        interval: Duration::from_nanos(1_000_000_000 / 30),
+    };
+}
+
+impl FromStr for Autorepeat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        if s == "off" {
+            return Ok(Autorepeat::Off);
+        }
+        let (delay, rate) = s.split_once(',').ok_or(())?;
+        let delay: u64 = delay.parse().map_err(|_| ())?;
+        let rate: f64 = rate.parse().map_err(|_| ())?;
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(());
+        }
+        Ok(Autorepeat::On { delay: Duration::from_millis(delay), interval: Duration::from_secs_f64(1.0 / rate) })
+    }
+}
+
+/// Command line options.
+pub struct Options {
+    pub log_level: LevelFilter,
+    pub quiet: bool,
+    pub frontend: Frontend,
+    pub frames: Option<u64>,
+    pub max_cycles: Option<u64>,
+    pub on_halt: HaltAction,
+    pub screenshot: Option<String>,
+    pub fix_video: bool,
+    pub font: Option<String>,
+    pub capture_at_frame: Option<u64>,
+    pub capture_file: Option<String>,
+    pub capture_pause: bool,
+    pub dump_ppm: Option<String>,
+    pub ppm_size: PpmSize,
+    pub rom: Option<String>,
+    pub rom_byteswap: bool,
+    pub session: Option<String>,
+    pub speed: Option<f64>,
+    pub cycles_per_frame: Option<u64>,
+    pub renderer: Renderer,
+    pub render_size: RenderSize,
+    pub border: u32,
+    pub palette: Palette,
+    pub crop: Crop,
+    pub render_ram: Option<RamRange>,
+    pub pixel_aspect: Option<PixelAspect>,
+    pub grid: Option<usize>,
+    pub show_coords: bool,
+    pub show_pad: bool,
+    pub crt: bool,
+    pub crt_bleed: bool,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub watch: bool,
+    pub patch: Option<String>,
+    pub disasm_out: Option<String>,
+    pub diff_rom: Option<String>,
+    pub diff_patch: Option<String>,
+    pub verify_determinism: bool,
+    pub selftest: bool,
+    pub batch: Option<String>,
+    pub batch_csv: bool,
+    pub gt1: Option<String>,
+    pub fast_load: bool,
+    pub vpc_addr: Option<u16>,
+    pub seed: Option<u64>,
+    pub clean_boot: bool,
+    pub ram_fill: Option<RamFill>,
+    pub ram_size: RamSize,
+    pub polarity: Polarity,
+    pub reset_hold: Option<u64>,
+    pub cold_start: u64,
+    pub key_repeat: Autorepeat,
+    pub set_ac: Option<u8>,
+    pub set_x: Option<u8>,
+    pub set_y: Option<u8>,
+    pub set_pc: Option<u16>,
+    pub save_garble: Option<String>,
+    pub load_garble: Option<String>,
+    pub record_input: Option<String>,
+    pub replay_input: Option<String>,
+    pub input_script: Option<String>,
+    pub record_delta: Option<String>,
+    pub play: Option<String>,
+    pub record_fps: Option<u32>,
+    pub rtc_addr: Option<u16>,
+    pub uart_out: Option<String>,
+    pub uart_baud: u32,
+    pub host_file: Option<String>,
+    pub input_socket: Option<String>,
+    pub stick_map: StickMap,
+    pub state_server: Option<String>,
+    pub shm_output: Option<String>,
+    pub launch_game: Option<String>,
+    pub show_expansion: bool,
+    pub show_ram: bool,
+    pub show_boot: bool,
+    pub boot_trace: bool,
+    pub debug: bool,
+    pub debug_script: Option<String>,
+    pub debug_record: Option<String>,
+    pub history: Option<usize>,
+    pub breaks: Vec<Condition>,
+    pub break_on_write: Option<WriteBreak>,
+    pub profile_vblank: bool,
+    pub profile_render: bool,
+    pub profile_sync: bool,
+    pub pc_profile: bool,
+    pub check_vcpu_code: bool,
+    pub trace_sys: bool,
+    pub trace_draws: Option<String>,
+    pub trace_draws_box: Crop,
+    pub profile_drift: bool,
+    pub profile_refresh: bool,
+    pub dump_ram: Option<String>,
+    pub diff_ram: Option<String>,
+    pub diff_states: Option<(String, String)>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options {
+            log_level: LevelFilter::Info,
+            quiet: false,
+            frontend: Frontend::Window,
+            frames: None,
+            max_cycles: None,
+            on_halt: HaltAction::Log,
+            screenshot: None,
+            fix_video: false,
+            font: None,
+            capture_at_frame: None,
+            capture_file: None,
+            capture_pause: false,
+            dump_ppm: None,
+            ppm_size: PpmSize::Full,
+            rom: None,
+            rom_byteswap: false,
+            session: None,
+            speed: None,
+            cycles_per_frame: None,
+            renderer: Renderer::Table,
+            render_size: RenderSize::Full,
+            border: 0,
+            palette: Palette::Default,
+            crop: Crop::FULL,
+            render_ram: None,
+            pixel_aspect: None,
+            grid: None,
+            show_coords: false,
+            show_pad: false,
+            crt: false,
+            crt_bleed: false,
+            flip_x: false,
+            flip_y: false,
+            watch: false,
+            patch: None,
+            disasm_out: None,
+            diff_rom: None,
+            diff_patch: None,
+            verify_determinism: false,
+            selftest: false,
+            batch: None,
+            batch_csv: false,
+            gt1: None,
+            fast_load: false,
+            vpc_addr: None,
+            seed: None,
+            clean_boot: false,
+            ram_fill: None,
+            ram_size: RamSize::K32,
+            polarity: Polarity::ActiveLow,
+            reset_hold: None,
+            cold_start: 0,
+            key_repeat: Autorepeat::DEFAULT,
+            set_ac: None,
+            set_x: None,
+            set_y: None,
+            set_pc: None,
+            save_garble: None,
+            load_garble: None,
+            record_input: None,
+            replay_input: None,
+            input_script: None,
+            record_delta: None,
+            play: None,
+            record_fps: None,
+            rtc_addr: None,
+            uart_out: None,
+            uart_baud: uart::DEFAULT_BAUD,
+            host_file: None,
+            input_socket: None,
+            stick_map: StickMap::DEFAULT,
+            state_server: None,
+            shm_output: None,
+            launch_game: None,
+            show_expansion: false,
+            show_ram: false,
+            show_boot: false,
+            boot_trace: false,
+            debug: false,
+            debug_script: None,
+            debug_record: None,
+            history: None,
+            breaks: Vec::new(),
+            break_on_write: None,
+            profile_vblank: false,
+            profile_render: false,
+            profile_sync: false,
+            pc_profile: false,
+            check_vcpu_code: false,
+            trace_sys: false,
+            trace_draws: None,
+            trace_draws_box: Crop::FULL,
+            profile_drift: false,
+            profile_refresh: false,
+            dump_ram: None,
+            diff_ram: None,
+            diff_states: None,
+        }
+    }
+
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut options = Options::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--log-level" => {
+                    options.log_level = value(&mut args, &arg)?;
+                }
+                "--quiet" => {
+                    options.quiet = true;
+                }
+                "--frontend" => {
+                    options.frontend = value(&mut args, &arg)?;
+                }
+                "--headless" => {
+                    options.frontend = Frontend::Headless;
+                }
+                "--frames" => {
+                    options.frames = Some(int(&mut args, &arg)?);
+                }
+                "--max-cycles" => {
+                    options.max_cycles = Some(int(&mut args, &arg)?);
+                }
+                "--on-halt" => {
+                    options.on_halt = value(&mut args, &arg)?;
+                }
+                "--screenshot" => {
+                    options.screenshot = Some(value(&mut args, &arg)?);
+                }
+                "--fix-video" => {
+                    options.fix_video = true;
+                }
+                "--font" => {
+                    options.font = Some(value(&mut args, &arg)?);
+                }
+                "--capture-at-frame" => {
+                    options.capture_at_frame = Some(int(&mut args, &arg)?);
+                }
+                "--capture-file" => {
+                    options.capture_file = Some(value(&mut args, &arg)?);
+                }
+                "--capture-pause" => {
+                    options.capture_pause = true;
+                }
+                "--dump-ppm" => {
+                    options.dump_ppm = Some(value(&mut args, &arg)?);
+                }
+                "--ppm-size" => {
+                    options.ppm_size = value(&mut args, &arg)?;
+                }
+                "--rom" => {
+                    options.rom = Some(value(&mut args, &arg)?);
+                }
+                "--session" => {
+                    options.session = Some(value(&mut args, &arg)?);
+                }
+                "--rom-byteswap" => {
+                    options.rom_byteswap = true;
+                }
+                "--speed" => {
+                    let speed: f64 = value(&mut args, &arg)?;
+                    if !(speed > 0.0 && speed.is_finite()) {
+                        return Err("--speed must be a positive number".to_string());
+                    }
+                    options.speed = Some(speed);
+                }
+                "--cycles-per-frame" => {
+                    let n: u64 = value(&mut args, &arg)?;
+                    if n == 0 {
+                        return Err("--cycles-per-frame must be positive".to_string());
+                    }
+                    options.cycles_per_frame = Some(n);
+                }
+                "--renderer" => {
+                    options.renderer = value(&mut args, &arg)?;
+                }
+                "--render" => {
+                    options.render_size = value(&mut args, &arg)?;
+                }
+                "--palette" => {
+                    options.palette = value(&mut args, &arg)?;
+                }
+                "--border" => {
+                    let color: String = value(&mut args, &arg)?;
+                    options.border = match u32::from_str_radix(&color, 16) {
+                        Ok(rgb) if color.len() == 6 => rgb,
+                        _ => return Err(format!("Invalid value for {}: {}", arg, color)),
+                    };
+                }
+                "--crop" => {
+                    options.crop = value(&mut args, &arg)?;
+                }
+                "--render-ram" => {
+                    options.render_ram = Some(value(&mut args, &arg)?);
+                }
+                "--grid" => {
+                    let grid: usize = int(&mut args, &arg)?;
+                    if grid == 0 {
+                        return Err(format!("Invalid value for {}: 0", arg));
+                    }
+                    options.grid = Some(grid);
+                }
+                "--show-coords" => {
+                    options.show_coords = true;
+                }
+                "--show-pad" => {
+                    options.show_pad = true;
+                }
+                "--pixel-aspect" => {
+                    options.pixel_aspect = Some(value(&mut args, &arg)?);
+                }
+                "--crt" => {
+                    options.crt = true;
+                }
+                "--crt-bleed" => {
+                    options.crt_bleed = true;
+                }
+                "--flip-x" => {
+                    options.flip_x = true;
+                }
+                "--flip-y" => {
+                    options.flip_y = true;
+                }
+                "--watch" => {
+                    options.watch = true;
+                }
+                "--patch" => {
+                    options.patch = Some(value(&mut args, &arg)?);
+                }
+                "--diff-rom" => {
+                    options.diff_rom = Some(value(&mut args, &arg)?);
+                }
+                "--verify-determinism" => {
+                    options.verify_determinism = true;
+                }
+                "--diff-patch" => {
+                    options.diff_patch = Some(value(&mut args, &arg)?);
+                }
+                "--disasm-out" => {
+                    options.disasm_out = Some(value(&mut args, &arg)?);
+                }
+                "--batch" => {
+                    options.batch = Some(value(&mut args, &arg)?);
+                }
+                "--batch-csv" => {
+                    options.batch_csv = true;
+                }
+                "--selftest" => {
+                    options.selftest = true;
+                }
+                "--gt1" => {
+                    options.gt1 = Some(value(&mut args, &arg)?);
+                }
+                "--fast-load" => {
+                    options.fast_load = true;
+                }
+                "--vpc-addr" => {
+                    options.vpc_addr = Some(int(&mut args, &arg)?);
+                }
+                "--seed" => {
+                    options.seed = Some(int(&mut args, &arg)?);
+                }
+                "--cold-start" => {
+                    options.cold_start = int(&mut args, &arg)?;
+                }
+                "--reset-hold" => {
+                    options.reset_hold = Some(int(&mut args, &arg)?);
+                }
+                "--clean-boot" => {
+                    options.clean_boot = true;
+                }
+                "--ram-fill" => {
+                    options.ram_fill = Some(value(&mut args, &arg)?);
+                }
+                "--ram" => {
+                    options.ram_size = value(&mut args, &arg)?;
+                }
+                "--joystick-polarity" => {
+                    options.polarity = value(&mut args, &arg)?;
+                }
+                "--key-repeat" => {
+                    options.key_repeat = value(&mut args, &arg)?;
+                }
+                "--set-ac" => {
+                    options.set_ac = Some(int(&mut args, &arg)?);
+                }
+                "--set-x" => {
+                    options.set_x = Some(int(&mut args, &arg)?);
+                }
+                "--set-y" => {
+                    options.set_y = Some(int(&mut args, &arg)?);
+                }
+                "--set-pc" => {
+                    options.set_pc = Some(int(&mut args, &arg)?);
+                }
+                "--save-garble" => {
+                    options.save_garble = Some(value(&mut args, &arg)?);
+                }
+                "--load-garble" => {
+                    options.load_garble = Some(value(&mut args, &arg)?);
+                }
+                "--record-input" => {
+                    options.record_input = Some(value(&mut args, &arg)?);
+                }
+                "--replay-input" => {
+                    options.replay_input = Some(value(&mut args, &arg)?);
+                }
+                "--input-script" => {
+                    options.input_script = Some(value(&mut args, &arg)?);
+                }
+                "--record-delta" => {
+                    options.record_delta = Some(value(&mut args, &arg)?);
+                }
+                "--record-fps" => {
+                    let fps: u32 = int(&mut args, &arg)?;
+                    if fps == 0 {
+                        return Err(format!("Invalid value for {}: 0", arg));
+                    }
+                    options.record_fps = Some(fps);
+                }
+                "--play" => {
+                    options.play = Some(value(&mut args, &arg)?);
+                }
+                "--launch-game" => {
+                    options.launch_game = Some(value(&mut args, &arg)?);
+                }
+                "--input-socket" => {
+                    options.input_socket = Some(value(&mut args, &arg)?);
+                }
+                "--stick-map" => {
+                    options.stick_map = value(&mut args, &arg)?;
+                }
+                "--state-server" => {
+                    options.state_server = Some(value(&mut args, &arg)?);
+                }
+                "--shm-output" => {
+                    options.shm_output = Some(value(&mut args, &arg)?);
+                }
+                "--show-expansion" => {
+                    options.show_expansion = true;
+                }
+                "--show-ram" => {
+                    options.show_ram = true;
+                }
+                "--show-boot" => {
+                    options.show_boot = true;
+                }
+                "--boot-trace" => {
+                    options.boot_trace = true;
+                }
+                "--debug" => {
+                    options.debug = true;
+                }
+                "--debug-script" => {
+                    options.debug_script = Some(value(&mut args, &arg)?);
+                }
+                "--debug-record" => {
+                    options.debug_record = Some(value(&mut args, &arg)?);
+                }
+                "--history" => {
+                    options.history = Some(int(&mut args, &arg)?);
+                }
+                "--break" => {
+                    let condition: String = value(&mut args, &arg)?;
+                    options.breaks.push(condition.parse().map_err(|e| format!("Invalid value for {}: {}", arg, e))?);
+                }
+                "--break-on-write" => {
+                    options.break_on_write = Some(value(&mut args, &arg)?);
+                }
+                "--profile-sync" => {
+                    options.profile_sync = true;
+                }
+                "--profile-vblank" => {
+                    options.profile_vblank = true;
+                }
+                "--profile-render" => {
+                    options.profile_render = true;
+                }
+                "--profile-drift" => {
+                    options.profile_drift = true;
+                }
+                "--profile-refresh" => {
+                    options.profile_refresh = true;
+                }
+                "--trace-sys" => {
+                    options.trace_sys = true;
+                }
+                "--trace-draws" => {
+                    options.trace_draws = Some(value(&mut args, &arg)?);
+                }
+                "--trace-draws-box" => {
+                    options.trace_draws_box = value(&mut args, &arg)?;
+                }
+                "--check-vcpu-code" => {
+                    options.check_vcpu_code = true;
+                }
+                "--pc-profile" => {
+                    options.pc_profile = true;
+                }
+                "--dump-ram" => {
+                    options.dump_ram = Some(value(&mut args, &arg)?);
+                }
+                "--diff-ram" => {
+                    options.diff_ram = Some(value(&mut args, &arg)?);
+                }
+                "--diff-states" => {
+                    options.diff_states = Some((value(&mut args, &arg)?, value(&mut args, &arg)?));
+                }
+                "--rtc-addr" => {
+                    let addr: u16 = int(&mut args, &arg)?;
+                    if addr > 0xfffd {
+                        return Err("--rtc-addr must leave room for 3 bytes of RAM".to_string());
+                    }
+                    options.rtc_addr = Some(addr);
+                }
+                "--uart-out" => {
+                    options.uart_out = Some(value(&mut args, &arg)?);
+                }
+                "--uart-baud" => {
+                    options.uart_baud = int(&mut args, &arg)?;
+                    if options.uart_baud == 0 {
+                        return Err("--uart-baud must be at least 1".to_string());
+                    }
+                }
+                "--host-file" => {
+                    options.host_file = Some(value(&mut args, &arg)?);
+                }
+                "-h" | "--help" => {
+                    print!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => {
+                    return Err(format!("Unknown option: {}\n\n{}", arg, USAGE));
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /// Checks the options that only make sense together, once a session's
+    /// options have been merged in.
+    pub fn check(&self) -> Result<(), String> {
+        if self.frontend == Frontend::Headless && self.frames.is_none() {
+            return Err("--frontend headless needs --frames, or it would never stop".to_string());
+        }
+        if self.replay_input.is_some() && self.input_script.is_some() {
+            return Err("--replay-input and --input-script can't be combined".to_string());
+        }
+        // Addresses parse up to 0xffff; the RAM fitted decides which exist
+        let top = (self.ram_size.bytes() - 1) as u16;
+        let past = |option: &str, addr: u16| {
+            Err(format!("{} address {:#06x} is past the {}K of RAM fitted (see --ram)", option, addr, (top as usize + 1) >> 10))
+        };
+        if let Some(condition) = &self.break_on_write
+            && condition.addr > top
+        {
+            return past("--break-on-write", condition.addr);
+        }
+        if let Some(range) = &self.render_ram
+            && range.end > top
+        {
+            return past("--render-ram", range.end);
+        }
+        if let Some(addr) = self.rtc_addr
+            && addr > top - 2
+        {
+            return past("--rtc-addr", addr);
+        }
+        if let Some(addr) = self.breaks.iter().filter_map(Condition::highest_ram_addr).max()
+            && addr > top
+        {
+            return past("--break", addr);
+        }
+        Ok(())
+    }
+
+    /// Effective log level: `--quiet` overrides `--log-level`.
+    pub fn level(&self) -> LevelFilter {
+        if self.quiet { LevelFilter::Error } else { self.log_level }
+    }
+}
+
+/// Takes and parses the value following option `name`.
+fn value<T: FromStr, I: Iterator<Item = String>>(args: &mut I, name: &str) -> Result<T, String> {
+    let value = args.next().ok_or(format!("{} requires a value", name))?;
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+/// Takes and parses an integer value following option `name`, given in
+/// decimal or in hex with a `0x` or `$` prefix.
+fn int<T: TryFrom<u64>, I: Iterator<Item = String>>(args: &mut I, name: &str) -> Result<T, String> {
+    let value: String = value(args, name)?;
+    parse_int(&value).and_then(|n| T::try_from(n).ok())
+        .ok_or(format!("Invalid value for {}: {}", name, value))
+}
+
+/// An integer in decimal or in hex with a `0x` or `$` prefix.
+fn parse_int(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix('$')) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(args: &str) -> Result<(), String> {
+        Options::parse(args.split_whitespace().map(String::from))?.check()
+    }
+
+    /// RAM addresses reach the upper half only with 64K fitted.
+    #[test]
+    fn ram_addresses() {
+        for args in ["--render-ram 0x8000-0x80ff", "--break-on-write 0x8000=1", "--rtc-addr 0xfffd", "--break [0x8000]==1"] {
+            assert!(check(args).is_err(), "{} accepted with 32K", args);
+            assert_eq!(check(&format!("--ram 64 {}", args)), Ok(()), "{} with 64K", args);
+        }
+        assert!(check("--ram 64 --rtc-addr 0xfffe").is_err());
+    }
+}
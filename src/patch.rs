@@ -0,0 +1,73 @@
+use crate::error::GigatronError;
+use crate::{ROM6, ROM_V6};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// A ROM word replacement: `[instruction, data]` at a word address.
+pub type Patch = (u16, [u8; 2]);
+
+/// Reads a patch file with one `address: word` per line, both in hex. The word
+/// is written as the instruction byte followed by the data byte, so
+/// `0100: 0003` puts `ld $03` at ROM address 0x0100. `#` starts a comment.
+pub fn read(filename: &str) -> Result<Vec<Patch>, GigatronError> {
+    let reader = BufReader::new(File::open(filename)?);
+    let mut patches = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let malformed = || GigatronError::Patch { line: i + 1 };
+        let (addr, word) = line.split_once(':').ok_or_else(malformed)?;
+        let addr = u32::from_str_radix(addr.trim().trim_start_matches("0x"), 16).map_err(|_| malformed())?;
+        let word = u16::from_str_radix(word.trim().trim_start_matches("0x"), 16).map_err(|_| malformed())?;
+        let addr = u16::try_from(addr).map_err(|_| GigatronError::PatchAddress { line: i + 1, addr })?;
+        patches.push((addr, word.to_be_bytes()));
+    }
+    Ok(patches)
+}
+
+/// Where ROMv6 keeps its video kernel: the vertical blank in page 1, the
+/// visible lines and the pixel burst in page 2.
+const VIDEO_KERNEL: std::ops::Range<u16> = 0x0100..0x0300;
+
+/// The built-in ROMv6's video kernel, as patches for a ROM of `rom_type`
+/// (--fix-video), to let a dump whose kernel is damaged show a picture
+/// again. The kernel calls into the rest of the ROM and reads its
+/// variables, so it only fits a ROM of the same version; None for any other.
+pub fn video_kernel(rom_type: u8) -> Option<Vec<Patch>> {
+    (rom_type & 0xfc == ROM_V6).then(|| {
+        VIDEO_KERNEL.map(|addr| (addr, [ROM6[2 * addr as usize], ROM6[2 * addr as usize + 1]])).collect()
+    })
+}
+
+/// Columns in a glyph of the ROM font, each one byte with the top pixel in
+/// bit 7.
+pub const GLYPH_COLUMNS: usize = 5;
+
+/// Where ROMv6 keeps its font: `ld $xx` words, one per glyph column, in runs
+/// of glyphs from the start of a page up to the trampoline at its end.
+/// ' ' to 'Q' in page 7, 'R' on in page 8, the last four glyphs being the
+/// arrows.
+const ROM_V6_FONT: [(u16, usize); 2] = [(0x0700, 50), (0x0800, 50)];
+
+/// Font layouts by romType (its upper 6 bits).
+const FONTS: [(u8, &[(u16, usize)]); 1] = [(ROM_V6, &ROM_V6_FONT)];
+
+/// Reads a font file (--font) for a ROM of `rom_type`, as patches that put
+/// its glyphs in the ROM's font table. The file holds the glyphs in
+/// character order from ' ', [`GLYPH_COLUMNS`] bytes each, left to right,
+/// and must have exactly as many as the ROM's table.
+pub fn read_font(filename: &str, rom_type: u8) -> Result<Vec<Patch>, GigatronError> {
+    let Some(&(_, runs)) = FONTS.iter().find(|(version, _)| *version == rom_type & 0xfc) else {
+        return Err(GigatronError::FontRom { rom_type });
+    };
+    let data = std::fs::read(filename)?;
+    let expected = runs.iter().map(|&(_, glyphs)| glyphs * GLYPH_COLUMNS).sum();
+    if data.len() != expected {
+        return Err(GigatronError::FontSize { expected, got: data.len() });
+    }
+    let addrs = runs.iter().flat_map(|&(start, glyphs)| (start..).take(glyphs * GLYPH_COLUMNS));
+    Ok(addrs.zip(data).map(|(addr, column)| (addr, [0x00, column])).collect())
+}
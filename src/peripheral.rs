@@ -0,0 +1,13 @@
+use crate::Gigatron;
+
+/// A device clocked in lockstep with the CPU, such as an audio DAC or an SPI
+/// card on the expander.
+///
+/// The Gigatron executes one instruction per cycle, so a tick per cycle is
+/// the finest timing there is; models that sample XOUT or the expander lines
+/// here see every change exactly when it happens.
+pub trait Peripheral {
+    /// Called once per cycle after the CPU has executed it. `cycle` counts
+    /// from 0 at power-on, as [`Gigatron::cycles`] does.
+    fn tick(&mut self, cycle: u64, gt: &Gigatron);
+}
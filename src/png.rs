@@ -0,0 +1,90 @@
+//! Just enough of PNG to save screenshots: 8-bit RGB, no filtering, and the
+//! image data in stored (uncompressed) deflate blocks, which every decoder
+//! reads. A 640x480 shot comes out at about 900K.
+
+use crate::error::GigatronError;
+use std::fs::File;
+use std::io::Write;
+
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Largest stored deflate block.
+const BLOCK: usize = 0xffff;
+
+/// Encodes `width` x `height` pixels in 0x00RRGGBB, row by row, as a PNG
+/// file.
+///
+/// Panics if `rgb` doesn't hold exactly `width * height` pixels.
+pub fn encode(width: usize, height: usize, rgb: &[u32]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height, "pixel count doesn't match the size");
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bits, RGB, deflate, no filter, no interlace
+
+    // Every row starts with its filter type, 0 for none
+    let mut raw = Vec::with_capacity(height * (1 + 3 * width));
+    for row in rgb.chunks_exact(width.max(1)).take(height) {
+        raw.push(0);
+        for &pixel in row {
+            raw.extend_from_slice(&pixel.to_be_bytes()[1..]);
+        }
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Writes [`encode`]d pixels to `filename`.
+pub fn write(filename: &str, width: usize, height: usize, rgb: &[u32]) -> Result<(), GigatronError> {
+    File::create(filename)?.write_all(&encode(width, height, rgb))?;
+    Ok(())
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// A zlib stream holding `data` in stored blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01]; // Deflate, 32K window, no preset dictionary
+    let blocks: Vec<&[u8]> = if data.is_empty() { vec![&[]] } else { data.chunks(BLOCK).collect() };
+    let last = blocks.len() - 1;
+    for (i, block) in blocks.into_iter().enumerate() {
+        let len = block.len() as u16;
+        out.push((i == last) as u8); // BFINAL, BTYPE 00
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,26 @@
+//! Binary PPM (P6) images: a short text header and then the bytes of every
+//! pixel, which a shell script can read or `cmp` with nothing but the file.
+
+use crate::error::GigatronError;
+use std::fs::File;
+use std::io::Write;
+
+/// Encodes `width` x `height` pixels in 0x00RRGGBB, row by row, as a P6
+/// file with 8 bits per channel.
+///
+/// Panics if `rgb` doesn't hold exactly `width * height` pixels.
+pub fn encode(width: usize, height: usize, rgb: &[u32]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height, "pixel count doesn't match the size");
+    let mut ppm = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    ppm.reserve(3 * rgb.len());
+    for &pixel in rgb {
+        ppm.extend_from_slice(&pixel.to_be_bytes()[1..]);
+    }
+    ppm
+}
+
+/// Writes [`encode`]d pixels to `filename`.
+pub fn write(filename: &str, width: usize, height: usize, rgb: &[u32]) -> Result<(), GigatronError> {
+    File::create(filename)?.write_all(&encode(width, height, rgb))?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use minifb::{Window, WindowOptions};
+
+/// One pixel per byte: a page (256 bytes) per row, 128 or 256 pages.
+const WIDTH: usize = 256;
+const SCALE: usize = 3; // Window pixels per byte, stretched by minifb
+
+/// Secondary window showing all of RAM as a grayscale image, the value of
+/// each byte its brightness, so the screen memory, the zero page, the stack
+/// and the sound tables can be watched at work.
+pub struct RamWindow {
+    window: Window,
+    buffer: Vec<u32>,
+    height: usize, // Pages
+}
+
+impl RamWindow {
+    /// A map of `size` bytes of RAM.
+    pub fn new(size: usize) -> Result<Self, minifb::Error> {
+        let height = size / WIDTH;
+        let window = Window::new("Gigatron RAM", WIDTH * SCALE, height * SCALE, WindowOptions::default())?;
+        Ok(RamWindow { window, buffer: vec![0; WIDTH * height], height })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    /// Redraws the map from `ram`; byte `addr` is at
+    /// (`addr & 0xff`, `addr >> 8`).
+    pub fn draw(&mut self, ram: &[u8]) {
+        for (pixel, &byte) in self.buffer.iter_mut().zip(ram) {
+            let v = byte as u32;
+            *pixel = (v << 16) | (v << 8) | v;
+        }
+        self.window.update_with_buffer(&self.buffer, WIDTH, self.height).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use crate::error::GigatronError;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Host input as it is presented to the ROM.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    Key(u8),      // ASCII keyboard byte
+    Joystick(u8), // Active-low controller bitmask
+}
+
+/// Writes every input event with its cycle timestamp, one per line:
+/// `<t> key|joy <hex byte>`.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(filename: &str) -> Result<Self, GigatronError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        writeln!(writer, "# Gigatron input log: <cycle> key|joy <hex>")?;
+        Ok(Recorder { writer })
+    }
+
+    pub fn record(&mut self, t: u64, event: InputEvent) -> Result<(), GigatronError> {
+        match event {
+            InputEvent::Key(code) => writeln!(self.writer, "{} key {:02x}", t, code)?,
+            InputEvent::Joystick(mask) => writeln!(self.writer, "{} joy {:02x}", t, mask)?,
+        }
+        Ok(())
+    }
+}
+
+/// Feeds a recorded input log back at the cycles it was captured.
+pub struct Replay {
+    events: Vec<(u64, InputEvent)>,
+    next: usize,
+}
+
+impl Replay {
+    pub fn read(filename: &str) -> Result<Self, GigatronError> {
+        Replay::parse_log(&std::fs::read_to_string(filename)?)
+    }
+
+    /// An input log already in memory, in the format [`Recorder`] writes.
+    pub fn parse_log(text: &str) -> Result<Self, GigatronError> {
+        let mut events = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let event = Replay::parse(line).ok_or(GigatronError::InputLog { line: i + 1 })?;
+            events.push(event);
+        }
+        Ok(Replay { events, next: 0 })
+    }
+
+    /// Replays `events`, which need not be in cycle order.
+    pub fn from_events(mut events: Vec<(u64, InputEvent)>) -> Self {
+        events.sort_by_key(|&(t, _)| t);
+        Replay { events, next: 0 }
+    }
+
+    fn parse(line: &str) -> Option<(u64, InputEvent)> {
+        let mut fields = line.split_whitespace();
+        let t = fields.next()?.parse().ok()?;
+        let kind = fields.next()?;
+        let value = u8::from_str_radix(fields.next()?, 16).ok()?;
+        let event = match kind {
+            "key" => InputEvent::Key(value),
+            "joy" => InputEvent::Joystick(value),
+            _ => return None,
+        };
+        Some((t, event))
+    }
+
+    /// Next event due at cycle `t`, if any.
+    pub fn next(&mut self, t: u64) -> Option<InputEvent> {
+        match self.events.get(self.next) {
+            Some(&(at, event)) if at <= t => {
+                self.next += 1;
+                Some(event)
+            }
+            _ => None,
+        }
+    }
+}
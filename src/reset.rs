@@ -0,0 +1,35 @@
+/// Cycles the reset line is held after power-on unless configured otherwise,
+/// just enough for the first instruction fetch to come from address 0.
+pub const DEFAULT_HOLD: u64 = 2;
+
+/// The MCP100 power-on reset supervisor.
+///
+/// After power-up the MCP100 keeps reset asserted for a fixed time, and while
+/// it does the CPU keeps fetching from PC = 0. Once released, the CPU runs
+/// from there on its own.
+pub enum Supervisor {
+    Holding { remaining: u64 },
+    Released,
+}
+
+impl Supervisor {
+    /// Asserts reset for `hold` cycles from now.
+    pub fn power_on(hold: u64) -> Self {
+        if hold == 0 { Supervisor::Released } else { Supervisor::Holding { remaining: hold } }
+    }
+
+    /// Advances one cycle; returns whether reset is asserted during it.
+    pub fn clock(&mut self) -> bool {
+        match *self {
+            Supervisor::Holding { remaining } => {
+                *self = Supervisor::power_on(remaining - 1);
+                true
+            }
+            Supervisor::Released => false,
+        }
+    }
+
+    pub fn is_held(&self) -> bool {
+        matches!(self, Supervisor::Holding { .. })
+    }
+}
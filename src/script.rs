@@ -0,0 +1,123 @@
+//! Input scripts: controller presses and typing at exact cycles, written by
+//! hand to reproduce timing-sensitive behavior.
+//!
+//! ```text
+//! # Start the game, then jump
+//! at 1000000 press right for 60 frames
+//! at 1100000 press a+right
+//! at 2000000 type RUN\n
+//! ```
+//!
+//! `press` holds buttons (up, down, left, right, a, b, start, select, joined
+//! with `+`) for a number of `frames` or `cycles`, 3 frames if not given.
+//! Presses may overlap; the controller then shows every button held. `type`
+//! queues the rest of the line on the keyboard, with `\n` for Enter and `\\`
+//! for a backslash.
+
+use crate::error::GigatronError;
+use crate::input::{BUTTON_A, BUTTON_B, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_SELECT, BUTTON_START, BUTTON_UP};
+use crate::replay::InputEvent;
+use crate::FRAME_CYCLES;
+
+/// Long enough for the ROM to sample a press at least once.
+const DEFAULT_FRAMES: u64 = 3;
+
+/// Controller bits by name.
+const BUTTONS: [(&str, u8); 8] = [
+    ("right", BUTTON_RIGHT),
+    ("left", BUTTON_LEFT),
+    ("down", BUTTON_DOWN),
+    ("up", BUTTON_UP),
+    ("start", BUTTON_START),
+    ("select", BUTTON_SELECT),
+    ("b", BUTTON_B),
+    ("a", BUTTON_A),
+];
+
+/// Reads a script into timed input events, in cycle order.
+pub fn read(filename: &str) -> Result<Vec<(u64, InputEvent)>, GigatronError> {
+    parse(&std::fs::read_to_string(filename)?)
+}
+
+pub fn parse(text: &str) -> Result<Vec<(u64, InputEvent)>, GigatronError> {
+    let mut presses = Vec::new(); // (from, until, buttons)
+    let mut events = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let malformed = || GigatronError::InputScript { line: i + 1 };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (at, rest) = parse_at(line).ok_or_else(malformed)?;
+        if let Some(text) = rest.strip_prefix("type ") {
+            let codes = unescape(text).ok_or_else(malformed)?;
+            events.extend(codes.into_iter().map(|code| (at, InputEvent::Key(code))));
+        } else if let Some(press) = rest.strip_prefix("press ") {
+            let (buttons, cycles) = parse_press(press).ok_or_else(malformed)?;
+            presses.push((at, at + cycles, buttons));
+        } else {
+            return Err(malformed());
+        }
+    }
+
+    // The controller changes where a press starts or ends
+    let mut edges: Vec<u64> = presses.iter().flat_map(|&(from, until, _)| [from, until]).collect();
+    edges.sort_unstable();
+    edges.dedup();
+    for t in edges {
+        let held = presses
+            .iter()
+            .filter(|&&(from, until, _)| from <= t && t < until)
+            .fold(0, |held, &(_, _, buttons)| held | buttons);
+        events.push((t, InputEvent::Joystick(!held)));
+    }
+    events.sort_by_key(|&(t, _)| t); // Stable, so typing keeps its order
+    Ok(events)
+}
+
+/// Splits `at <cycle> <rest>`.
+fn parse_at(line: &str) -> Option<(u64, &str)> {
+    let rest = line.strip_prefix("at ")?.trim_start();
+    let (cycle, rest) = rest.split_once(' ')?;
+    Some((cycle.parse().ok()?, rest.trim_start()))
+}
+
+/// Parses `<buttons> [for <n> frames|cycles]` into the buttons' bits and
+/// the press's length in cycles.
+fn parse_press(press: &str) -> Option<(u8, u64)> {
+    let mut words = press.split_whitespace();
+    let buttons = parse_buttons(words.next()?)?;
+    let cycles = match (words.next(), words.next(), words.next(), words.next()) {
+        (None, ..) => DEFAULT_FRAMES * FRAME_CYCLES,
+        (Some("for"), Some(n), Some("frames" | "frame"), None) => n.parse::<u64>().ok()? * FRAME_CYCLES,
+        (Some("for"), Some(n), Some("cycles" | "cycle"), None) => n.parse().ok()?,
+        _ => return None,
+    };
+    Some((buttons, cycles))
+}
+
+/// The bits of the buttons in `names`, e.g. `a+right`, case-insensitive.
+pub fn parse_buttons(names: &str) -> Option<u8> {
+    names.split('+').try_fold(0, |buttons, name| {
+        let &(_, bit) = BUTTONS.iter().find(|(button, _)| button.eq_ignore_ascii_case(name))?;
+        Some(buttons | bit)
+    })
+}
+
+/// The bytes of `text`, with `\n` and `\\` escapes. Only ASCII can be typed.
+fn unescape(text: &str) -> Option<Vec<u8>> {
+    let mut codes = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                '\\' => '\\',
+                _ => return None,
+            },
+            c => c,
+        };
+        codes.push(u8::try_from(c).ok().filter(u8::is_ascii)?);
+    }
+    Some(codes)
+}
@@ -0,0 +1,424 @@
+//! Built-in self-test of the emulator core: runs every opcode once from a
+//! known state and checks the registers and RAM against a table worked out
+//! independently from the gtemu.c semantics. Run with `--selftest` before
+//! suspecting a ROM or a game.
+
+use crate::{decode, disasm, CpuState, Gigatron};
+
+/// Where each one-instruction ROM places its instruction.
+const INSTRUCTION: u16 = 0x0123;
+
+/// Starting state for every case, apart from AC which the table gives.
+const D: u8 = 0x37;
+const X: u8 = 0x2c;
+const Y: u8 = 0x03;
+const OUT: u8 = 0xc0; // Both syncs inactive
+const UNDEF: u8 = 0x99;
+
+/// RAM contents at the start, distinct enough that a wrong address shows.
+fn pattern(addr: usize) -> u8 {
+    ((addr * 31) ^ (addr >> 8)) as u8
+}
+
+/// Opcode, AC before, then PC, AC, X, Y and OUT after, and the RAM write
+/// (address, value) if any.
+type Case = (u8, u8, u16, u8, u8, u8, u8, Option<(u16, u8)>);
+
+/// Every opcode with IN reading 0xff. Jumps run three times, with AC zero,
+/// positive and negative, so each condition is seen taken and not.
+#[rustfmt::skip]
+const EXPECTED: [Case; 320] = [
+    (0x00, 0x5a, 0x0125, 0x37, 0x2c, 0x03, 0xc0, None),
+    (0x01, 0x5a, 0x0125, 0xa9, 0x2c, 0x03, 0xc0, None),
+    (0x02, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x03, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x04, 0x5a, 0x0125, 0x37, 0x2c, 0x03, 0xc0, None),
+    (0x05, 0x5a, 0x0125, 0x54, 0x2c, 0x03, 0xc0, None),
+    (0x06, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x07, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x08, 0x5a, 0x0125, 0x37, 0x2c, 0x03, 0xc0, None),
+    (0x09, 0x5a, 0x0125, 0xaa, 0x2c, 0x03, 0xc0, None),
+    (0x0a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x0b, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x0c, 0x5a, 0x0125, 0x37, 0x2c, 0x03, 0xc0, None),
+    (0x0d, 0x5a, 0x0125, 0x57, 0x2c, 0x03, 0xc0, None),
+    (0x0e, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x0f, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x10, 0x5a, 0x0125, 0x5a, 0x37, 0x03, 0xc0, None),
+    (0x11, 0x5a, 0x0125, 0x5a, 0xa9, 0x03, 0xc0, None),
+    (0x12, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, None),
+    (0x13, 0x5a, 0x0125, 0x5a, 0xff, 0x03, 0xc0, None),
+    (0x14, 0x5a, 0x0125, 0x5a, 0x2c, 0x37, 0xc0, None),
+    (0x15, 0x5a, 0x0125, 0x5a, 0x2c, 0xa9, 0xc0, None),
+    (0x16, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, None),
+    (0x17, 0x5a, 0x0125, 0x5a, 0x2c, 0xff, 0xc0, None),
+    (0x18, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x37, None),
+    (0x19, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xa9, None),
+    (0x1a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x5a, None),
+    (0x1b, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xff, None),
+    (0x1c, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x37, None),
+    (0x1d, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x57, None),
+    (0x1e, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x5a, None),
+    (0x1f, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xff, None),
+    (0x20, 0x5a, 0x0125, 0x12, 0x2c, 0x03, 0xc0, None),
+    (0x21, 0x5a, 0x0125, 0x08, 0x2c, 0x03, 0xc0, None),
+    (0x22, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x23, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x24, 0x5a, 0x0125, 0x12, 0x2c, 0x03, 0xc0, None),
+    (0x25, 0x5a, 0x0125, 0x50, 0x2c, 0x03, 0xc0, None),
+    (0x26, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x27, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x28, 0x5a, 0x0125, 0x12, 0x2c, 0x03, 0xc0, None),
+    (0x29, 0x5a, 0x0125, 0x0a, 0x2c, 0x03, 0xc0, None),
+    (0x2a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x2b, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x2c, 0x5a, 0x0125, 0x12, 0x2c, 0x03, 0xc0, None),
+    (0x2d, 0x5a, 0x0125, 0x52, 0x2c, 0x03, 0xc0, None),
+    (0x2e, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x2f, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x30, 0x5a, 0x0125, 0x5a, 0x12, 0x03, 0xc0, None),
+    (0x31, 0x5a, 0x0125, 0x5a, 0x08, 0x03, 0xc0, None),
+    (0x32, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, None),
+    (0x33, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, None),
+    (0x34, 0x5a, 0x0125, 0x5a, 0x2c, 0x12, 0xc0, None),
+    (0x35, 0x5a, 0x0125, 0x5a, 0x2c, 0x08, 0xc0, None),
+    (0x36, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, None),
+    (0x37, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, None),
+    (0x38, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x12, None),
+    (0x39, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x08, None),
+    (0x3a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x5a, None),
+    (0x3b, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x5a, None),
+    (0x3c, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x12, None),
+    (0x3d, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x52, None),
+    (0x3e, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x5a, None),
+    (0x3f, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x5a, None),
+    (0x40, 0x5a, 0x0125, 0x7f, 0x2c, 0x03, 0xc0, None),
+    (0x41, 0x5a, 0x0125, 0xfb, 0x2c, 0x03, 0xc0, None),
+    (0x42, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x43, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x44, 0x5a, 0x0125, 0x7f, 0x2c, 0x03, 0xc0, None),
+    (0x45, 0x5a, 0x0125, 0x5e, 0x2c, 0x03, 0xc0, None),
+    (0x46, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x47, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x48, 0x5a, 0x0125, 0x7f, 0x2c, 0x03, 0xc0, None),
+    (0x49, 0x5a, 0x0125, 0xfa, 0x2c, 0x03, 0xc0, None),
+    (0x4a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x4b, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x4c, 0x5a, 0x0125, 0x7f, 0x2c, 0x03, 0xc0, None),
+    (0x4d, 0x5a, 0x0125, 0x5f, 0x2c, 0x03, 0xc0, None),
+    (0x4e, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x4f, 0x5a, 0x0125, 0xff, 0x2c, 0x03, 0xc0, None),
+    (0x50, 0x5a, 0x0125, 0x5a, 0x7f, 0x03, 0xc0, None),
+    (0x51, 0x5a, 0x0125, 0x5a, 0xfb, 0x03, 0xc0, None),
+    (0x52, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, None),
+    (0x53, 0x5a, 0x0125, 0x5a, 0xff, 0x03, 0xc0, None),
+    (0x54, 0x5a, 0x0125, 0x5a, 0x2c, 0x7f, 0xc0, None),
+    (0x55, 0x5a, 0x0125, 0x5a, 0x2c, 0xfb, 0xc0, None),
+    (0x56, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, None),
+    (0x57, 0x5a, 0x0125, 0x5a, 0x2c, 0xff, 0xc0, None),
+    (0x58, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x7f, None),
+    (0x59, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xfb, None),
+    (0x5a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x5a, None),
+    (0x5b, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xff, None),
+    (0x5c, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x7f, None),
+    (0x5d, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x5f, None),
+    (0x5e, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x5a, None),
+    (0x5f, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xff, None),
+    (0x60, 0x5a, 0x0125, 0x6d, 0x2c, 0x03, 0xc0, None),
+    (0x61, 0x5a, 0x0125, 0xf3, 0x2c, 0x03, 0xc0, None),
+    (0x62, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0x63, 0x5a, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0x64, 0x5a, 0x0125, 0x6d, 0x2c, 0x03, 0xc0, None),
+    (0x65, 0x5a, 0x0125, 0x0e, 0x2c, 0x03, 0xc0, None),
+    (0x66, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0x67, 0x5a, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0x68, 0x5a, 0x0125, 0x6d, 0x2c, 0x03, 0xc0, None),
+    (0x69, 0x5a, 0x0125, 0xf0, 0x2c, 0x03, 0xc0, None),
+    (0x6a, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0x6b, 0x5a, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0x6c, 0x5a, 0x0125, 0x6d, 0x2c, 0x03, 0xc0, None),
+    (0x6d, 0x5a, 0x0125, 0x0d, 0x2c, 0x03, 0xc0, None),
+    (0x6e, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0x6f, 0x5a, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0x70, 0x5a, 0x0125, 0x5a, 0x6d, 0x03, 0xc0, None),
+    (0x71, 0x5a, 0x0125, 0x5a, 0xf3, 0x03, 0xc0, None),
+    (0x72, 0x5a, 0x0125, 0x5a, 0x00, 0x03, 0xc0, None),
+    (0x73, 0x5a, 0x0125, 0x5a, 0xa5, 0x03, 0xc0, None),
+    (0x74, 0x5a, 0x0125, 0x5a, 0x2c, 0x6d, 0xc0, None),
+    (0x75, 0x5a, 0x0125, 0x5a, 0x2c, 0xf3, 0xc0, None),
+    (0x76, 0x5a, 0x0125, 0x5a, 0x2c, 0x00, 0xc0, None),
+    (0x77, 0x5a, 0x0125, 0x5a, 0x2c, 0xa5, 0xc0, None),
+    (0x78, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x6d, None),
+    (0x79, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xf3, None),
+    (0x7a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x00, None),
+    (0x7b, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xa5, None),
+    (0x7c, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x6d, None),
+    (0x7d, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x0d, None),
+    (0x7e, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x00, None),
+    (0x7f, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xa5, None),
+    (0x80, 0x5a, 0x0125, 0x91, 0x2c, 0x03, 0xc0, None),
+    (0x81, 0x5a, 0x0125, 0x03, 0x2c, 0x03, 0xc0, None),
+    (0x82, 0x5a, 0x0125, 0xb4, 0x2c, 0x03, 0xc0, None),
+    (0x83, 0x5a, 0x0125, 0x59, 0x2c, 0x03, 0xc0, None),
+    (0x84, 0x5a, 0x0125, 0x91, 0x2c, 0x03, 0xc0, None),
+    (0x85, 0x5a, 0x0125, 0xae, 0x2c, 0x03, 0xc0, None),
+    (0x86, 0x5a, 0x0125, 0xb4, 0x2c, 0x03, 0xc0, None),
+    (0x87, 0x5a, 0x0125, 0x59, 0x2c, 0x03, 0xc0, None),
+    (0x88, 0x5a, 0x0125, 0x91, 0x2c, 0x03, 0xc0, None),
+    (0x89, 0x5a, 0x0125, 0x04, 0x2c, 0x03, 0xc0, None),
+    (0x8a, 0x5a, 0x0125, 0xb4, 0x2c, 0x03, 0xc0, None),
+    (0x8b, 0x5a, 0x0125, 0x59, 0x2c, 0x03, 0xc0, None),
+    (0x8c, 0x5a, 0x0125, 0x91, 0x2c, 0x03, 0xc0, None),
+    (0x8d, 0x5a, 0x0125, 0xb1, 0x2c, 0x03, 0xc0, None),
+    (0x8e, 0x5a, 0x0125, 0xb4, 0x2c, 0x03, 0xc0, None),
+    (0x8f, 0x5a, 0x0125, 0x59, 0x2c, 0x03, 0xc0, None),
+    (0x90, 0x5a, 0x0125, 0x5a, 0x91, 0x03, 0xc0, None),
+    (0x91, 0x5a, 0x0125, 0x5a, 0x03, 0x03, 0xc0, None),
+    (0x92, 0x5a, 0x0125, 0x5a, 0xb4, 0x03, 0xc0, None),
+    (0x93, 0x5a, 0x0125, 0x5a, 0x59, 0x03, 0xc0, None),
+    (0x94, 0x5a, 0x0125, 0x5a, 0x2c, 0x91, 0xc0, None),
+    (0x95, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0x96, 0x5a, 0x0125, 0x5a, 0x2c, 0xb4, 0xc0, None),
+    (0x97, 0x5a, 0x0125, 0x5a, 0x2c, 0x59, 0xc0, None),
+    (0x98, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x91, None),
+    (0x99, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x03, None),
+    (0x9a, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xb4, None),
+    (0x9b, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x59, None),
+    (0x9c, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x91, None),
+    (0x9d, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xb1, None),
+    (0x9e, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xb4, None),
+    (0x9f, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x59, None),
+    (0xa0, 0x5a, 0x0125, 0x23, 0x2c, 0x03, 0xc0, None),
+    (0xa1, 0x5a, 0x0125, 0xb1, 0x2c, 0x03, 0xc0, None),
+    (0xa2, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xa3, 0x5a, 0x0125, 0x5b, 0x2c, 0x03, 0xc0, None),
+    (0xa4, 0x5a, 0x0125, 0x23, 0x2c, 0x03, 0xc0, None),
+    (0xa5, 0x5a, 0x0125, 0x06, 0x2c, 0x03, 0xc0, None),
+    (0xa6, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xa7, 0x5a, 0x0125, 0x5b, 0x2c, 0x03, 0xc0, None),
+    (0xa8, 0x5a, 0x0125, 0x23, 0x2c, 0x03, 0xc0, None),
+    (0xa9, 0x5a, 0x0125, 0xb0, 0x2c, 0x03, 0xc0, None),
+    (0xaa, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xab, 0x5a, 0x0125, 0x5b, 0x2c, 0x03, 0xc0, None),
+    (0xac, 0x5a, 0x0125, 0x23, 0x2c, 0x03, 0xc0, None),
+    (0xad, 0x5a, 0x0125, 0x03, 0x2c, 0x03, 0xc0, None),
+    (0xae, 0x5a, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xaf, 0x5a, 0x0125, 0x5b, 0x2c, 0x03, 0xc0, None),
+    (0xb0, 0x5a, 0x0125, 0x5a, 0x23, 0x03, 0xc0, None),
+    (0xb1, 0x5a, 0x0125, 0x5a, 0xb1, 0x03, 0xc0, None),
+    (0xb2, 0x5a, 0x0125, 0x5a, 0x00, 0x03, 0xc0, None),
+    (0xb3, 0x5a, 0x0125, 0x5a, 0x5b, 0x03, 0xc0, None),
+    (0xb4, 0x5a, 0x0125, 0x5a, 0x2c, 0x23, 0xc0, None),
+    (0xb5, 0x5a, 0x0125, 0x5a, 0x2c, 0xb1, 0xc0, None),
+    (0xb6, 0x5a, 0x0125, 0x5a, 0x2c, 0x00, 0xc0, None),
+    (0xb7, 0x5a, 0x0125, 0x5a, 0x2c, 0x5b, 0xc0, None),
+    (0xb8, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x23, None),
+    (0xb9, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xb1, None),
+    (0xba, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x00, None),
+    (0xbb, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0x5b, None),
+    (0xbc, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x23, None),
+    (0xbd, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x03, None),
+    (0xbe, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x00, None),
+    (0xbf, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0x5b, None),
+    (0xc0, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0x37))),
+    (0xc1, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0x99))),
+    (0xc2, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0x5a))),
+    (0xc3, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0xff))),
+    (0xc4, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x002c, 0x37))),
+    (0xc5, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x002c, 0x99))),
+    (0xc6, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x002c, 0x5a))),
+    (0xc7, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x002c, 0xff))),
+    (0xc8, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0337, 0x37))),
+    (0xc9, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0337, 0x99))),
+    (0xca, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0337, 0x5a))),
+    (0xcb, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0337, 0xff))),
+    (0xcc, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x032c, 0x37))),
+    (0xcd, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x032c, 0x99))),
+    (0xce, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x032c, 0x5a))),
+    (0xcf, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x032c, 0xff))),
+    (0xd0, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, Some((0x0037, 0x37))),
+    (0xd1, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, Some((0x0037, 0x99))),
+    (0xd2, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, Some((0x0037, 0x5a))),
+    (0xd3, 0x5a, 0x0125, 0x5a, 0x5a, 0x03, 0xc0, Some((0x0037, 0xff))),
+    (0xd4, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, Some((0x0037, 0x37))),
+    (0xd5, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, Some((0x0037, 0x99))),
+    (0xd6, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, Some((0x0037, 0x5a))),
+    (0xd7, 0x5a, 0x0125, 0x5a, 0x2c, 0x5a, 0xc0, Some((0x0037, 0xff))),
+    (0xd8, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0x37))),
+    (0xd9, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0x99))),
+    (0xda, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0x5a))),
+    (0xdb, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, Some((0x0037, 0xff))),
+    (0xdc, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xc0, Some((0x032c, 0x37))),
+    (0xdd, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xc0, Some((0x032c, 0x99))),
+    (0xde, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xc0, Some((0x032c, 0x5a))),
+    (0xdf, 0x5a, 0x0125, 0x5a, 0x2d, 0x03, 0xc0, Some((0x032c, 0xff))),
+    (0xe0, 0x00, 0x0337, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe0, 0x5a, 0x0337, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe0, 0xa5, 0x0337, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe1, 0x00, 0x03a9, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe1, 0x5a, 0x03a9, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe1, 0xa5, 0x03a9, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe2, 0x00, 0x0300, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe2, 0x5a, 0x035a, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe2, 0xa5, 0x03a5, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe3, 0x00, 0x03ff, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe3, 0x5a, 0x03ff, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe3, 0xa5, 0x03ff, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe4, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe4, 0x5a, 0x0137, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe4, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe5, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe5, 0x5a, 0x01a9, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe5, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe6, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe6, 0x5a, 0x015a, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe6, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe7, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe7, 0x5a, 0x01ff, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe7, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe8, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe8, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe8, 0xa5, 0x0137, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xe9, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xe9, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xe9, 0xa5, 0x01a9, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xea, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xea, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xea, 0xa5, 0x01a5, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xeb, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xeb, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xeb, 0xa5, 0x01ff, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xec, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xec, 0x5a, 0x0137, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xec, 0xa5, 0x0137, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xed, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xed, 0x5a, 0x01a9, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xed, 0xa5, 0x01a9, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xee, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xee, 0x5a, 0x015a, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xee, 0xa5, 0x01a5, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xef, 0x00, 0x0125, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xef, 0x5a, 0x01ff, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xef, 0xa5, 0x01ff, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf0, 0x00, 0x0137, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf0, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf0, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf1, 0x00, 0x01a9, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf1, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf1, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf2, 0x00, 0x0100, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf2, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf2, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf3, 0x00, 0x01ff, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf3, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf3, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf4, 0x00, 0x0137, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf4, 0x5a, 0x0137, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf4, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf5, 0x00, 0x01a9, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf5, 0x5a, 0x01a9, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf5, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf6, 0x00, 0x0100, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf6, 0x5a, 0x015a, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf6, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf7, 0x00, 0x01ff, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf7, 0x5a, 0x01ff, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf7, 0xa5, 0x0125, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf8, 0x00, 0x0137, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf8, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf8, 0xa5, 0x0137, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xf9, 0x00, 0x01a9, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xf9, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xf9, 0xa5, 0x01a9, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xfa, 0x00, 0x0100, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xfa, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xfa, 0xa5, 0x01a5, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xfb, 0x00, 0x01ff, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xfb, 0x5a, 0x0125, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xfb, 0xa5, 0x01ff, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xfc, 0x00, 0x0137, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xfc, 0x5a, 0x0137, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xfc, 0xa5, 0x0137, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xfd, 0x00, 0x01a9, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xfd, 0x5a, 0x01a9, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xfd, 0xa5, 0x01a9, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xfe, 0x00, 0x0100, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xfe, 0x5a, 0x015a, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xfe, 0xa5, 0x01a5, 0xa5, 0x2c, 0x03, 0xc0, None),
+    (0xff, 0x00, 0x01ff, 0x00, 0x2c, 0x03, 0xc0, None),
+    (0xff, 0x5a, 0x01ff, 0x5a, 0x2c, 0x03, 0xc0, None),
+    (0xff, 0xa5, 0x01ff, 0xa5, 0x2c, 0x03, 0xc0, None),
+];
+
+/// Result of one case.
+pub struct Outcome {
+    pub name: String,
+    pub failure: Option<String>, // None when the case passed
+}
+
+/// Runs the whole suite, one outcome per case.
+pub fn run() -> Vec<Outcome> {
+    let mut gt = Gigatron::new();
+    let mut rom = vec![0u8; 65536 * 2];
+    EXPECTED.iter().map(|case| instruction(&mut gt, &mut rom, case)).collect()
+}
+
+/// Loads a ROM holding just `opcode d` at INSTRUCTION and powers on into
+/// the RAM pattern, with the CPU left for the caller to set up.
+fn load(gt: &mut Gigatron, rom: &mut [u8], opcode: u8, d: u8) -> Result<(), String> {
+    let at = INSTRUCTION as usize * 2;
+    rom[at] = opcode;
+    rom[at + 1] = d;
+    let loaded = gt.read_rom_bytes(rom);
+    rom[at] = 0;
+    rom[at + 1] = 0;
+    loaded.map_err(|e| e.to_string())?;
+    gt.clean_boot = true;
+    gt.reset_hold = 0;
+    gt.init();
+    for (addr, b) in gt.RAM.iter_mut().enumerate() {
+        *b = pattern(addr);
+    }
+    Ok(())
+}
+
+/// Runs the loaded instruction from `state`: a nop in the pipeline fetches
+/// it, the next cycle executes it.
+fn execute(gt: &mut Gigatron, state: CpuState) {
+    gt.S = CpuState { PC: INSTRUCTION, IR: 0x02, D: 0, ..state };
+    gt.step();
+    gt.step();
+}
+
+fn instruction(gt: &mut Gigatron, rom: &mut [u8], case: &Case) -> Outcome {
+    let &(opcode, ac, pc, ac_after, x, y, out, store) = case;
+    let mut name = format!("{:02x}{:02x} {}", opcode, D, disasm::disassemble(opcode, D));
+    if decode(opcode).is_jump {
+        name += &format!(" (AC=${:02x})", ac);
+    }
+
+    if let Err(e) = load(gt, rom, opcode, D) {
+        return Outcome { name, failure: Some(e) };
+    }
+    execute(gt, CpuState { AC: ac, X, Y, OUT, undef: UNDEF, ..CpuState::new() });
+
+    let mut failures = Vec::new();
+    let S = &gt.S;
+    let got = [S.PC, S.AC as u16, S.X as u16, S.Y as u16, S.OUT as u16];
+    let expected = [pc, ac_after as u16, x as u16, y as u16, out as u16];
+    for (i, register) in ["PC", "AC", "X", "Y", "OUT"].iter().enumerate() {
+        if got[i] != expected[i] {
+            failures.push(format!("{} is ${:02x}, expected ${:02x}", register, got[i], expected[i]));
+        }
+    }
+    for (addr, &b) in gt.RAM.iter().enumerate() {
+        let expected = match store {
+            Some((at, value)) if at as usize == addr => value,
+            _ => pattern(addr),
+        };
+        if b != expected {
+            failures.push(format!("[${:04x}] is ${:02x}, expected ${:02x}", addr, b, expected));
+        }
+    }
+    Outcome { name, failure: (!failures.is_empty()).then(|| failures.join(", ")) }
+}
@@ -0,0 +1,60 @@
+use gigatron_example::error::GigatronError;
+use std::path::Path;
+
+/// Files a session directory may hold, all of them optional.
+const ROM: &str = "rom"; // ROM image, optionally gzipped
+const RAM: &str = "ram"; // RAM image, loaded once the machine is powered on
+const OPTIONS: &str = "options"; // More command line options, e.g. `--set-pc 0x0200`
+
+/// A reproduction packed into one directory (--session), to be shared in
+/// bug reports. The options file holds whitespace-separated options, with
+/// `#` starting a comment line; the real command line comes after them, so
+/// it can override any of them.
+pub struct Session {
+    pub rom: Option<String>,
+    pub ram: Option<String>,
+    pub args: Vec<String>,
+}
+
+impl Session {
+    pub fn read(dir: &str) -> Result<Self, GigatronError> {
+        let dir = Path::new(dir);
+        if !std::fs::metadata(dir)?.is_dir() {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotADirectory, "not a directory").into());
+        }
+        let file = |name: &str| {
+            let path = dir.join(name);
+            path.is_file().then(|| path.to_string_lossy().into_owned())
+        };
+        let args = match file(OPTIONS) {
+            Some(path) => std::fs::read_to_string(path)?
+                .lines()
+                .filter(|line| !line.trim_start().starts_with('#'))
+                .flat_map(str::split_whitespace)
+                .map(str::to_string)
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(Session { rom: file(ROM), ram: file(RAM), args })
+    }
+
+    /// Which parts the session has, e.g. `ROM, 4 options (no RAM image)`.
+    pub fn describe(&self) -> String {
+        let mut present = Vec::new();
+        let mut missing = Vec::new();
+        match &self.rom {
+            Some(_) => present.push("ROM".to_string()),
+            None => missing.push("ROM"),
+        }
+        match &self.ram {
+            Some(_) => present.push("RAM image".to_string()),
+            None => missing.push("RAM image"),
+        }
+        match self.args.len() {
+            0 => missing.push("options"),
+            n => present.push(format!("{} option(s)", n)),
+        }
+        let present = if present.is_empty() { "nothing".to_string() } else { present.join(", ") };
+        if missing.is_empty() { present } else { format!("{} (no {})", present, missing.join(", no ")) }
+    }
+}
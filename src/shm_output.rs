@@ -0,0 +1,77 @@
+use gigatron_example::error::GigatronError;
+use gigatron_example::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use log::info;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"GTFB";
+const HEADER: usize = 32;
+const SEQUENCE: u64 = 16; // Offset of the sequence counter
+
+/// The screen published to a file in shared memory every frame
+/// (--shm-output), for a capture or analysis tool in another process to
+/// map and read as it changes. The file is a fixed 32-byte header, all
+/// little-endian, then the pixels:
+///
+/// ```text
+/// 0   "GTFB"
+/// 4   u32 width (160)
+/// 8   u32 height (120)
+/// 12  u32 bytes per pixel (4): a u32 0x00RRGGBB each, rows top to bottom
+/// 16  u64 sequence, odd while the pixels are being written
+/// 24  u64 frames completed when they were taken
+/// 32  the pixels
+/// ```
+///
+/// A reader takes the sequence, copies the pixels, and keeps the copy if
+/// the sequence was even and is still the same.
+pub struct ShmOutput {
+    file: File,
+    sequence: u64,
+}
+
+impl ShmOutput {
+    /// Creates the file `name`, a path, or with no `/` in it a name in
+    /// /dev/shm (the temporary directory where there is none).
+    pub fn create(name: &str) -> Result<Self, GigatronError> {
+        let path = path(name);
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+        let mut header = Vec::with_capacity(HEADER);
+        header.extend_from_slice(MAGIC);
+        for n in [SCREEN_WIDTH, SCREEN_HEIGHT, 4] {
+            header.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        header.resize(HEADER, 0);
+        file.write_all(&header)?;
+        file.set_len((HEADER + 4 * SCREEN_WIDTH * SCREEN_HEIGHT) as u64)?;
+        info!("Publishing the screen to {}", path.display());
+        Ok(ShmOutput { file, sequence: 0 })
+    }
+
+    /// Writes `pixels`, SCREEN_WIDTH x SCREEN_HEIGHT in 0x00RRGGBB, as the
+    /// screen after `frames` frames.
+    pub fn publish(&mut self, pixels: &[u32], frames: u64) -> Result<(), GigatronError> {
+        let bytes: Vec<u8> = pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+        self.set_sequence(self.sequence + 1)?;
+        self.file.seek(SeekFrom::Start(SEQUENCE + 8))?;
+        self.file.write_all(&frames.to_le_bytes())?;
+        self.file.write_all(&bytes)?;
+        self.set_sequence(self.sequence + 1)
+    }
+
+    fn set_sequence(&mut self, sequence: u64) -> Result<(), GigatronError> {
+        self.sequence = sequence;
+        self.file.seek(SeekFrom::Start(SEQUENCE))?;
+        self.file.write_all(&sequence.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+fn path(name: &str) -> PathBuf {
+    if name.contains('/') {
+        return PathBuf::from(name);
+    }
+    let shm = Path::new("/dev/shm");
+    if shm.is_dir() { shm.join(name) } else { std::env::temp_dir().join(name) }
+}
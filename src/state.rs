@@ -0,0 +1,178 @@
+use crate::error::GigatronError;
+use crate::{CpuState, RamSize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 3] = b"GTS";
+/// The layout's version, the ASCII digit after MAGIC. Version 1 had no
+/// cycle count.
+const VERSION: u8 = 2;
+const HEADER: usize = MAGIC.len() + 1 + 21;
+
+/// Machine state as saved to a file: the registers, the I/O latches, the
+/// cycle count and all of RAM. The ROM isn't included; a state is restored
+/// into a machine running the same ROM.
+///
+/// The file is `GTS2`, then PC (little-endian), IR, D, AC, X, Y, OUT,
+/// undef, IN, XOUT, the expander's ctrl and the cycle count (both
+/// little-endian), then all of RAM, 32K or 64K.
+#[derive(Clone, Debug)]
+pub struct State {
+    pub cpu: CpuState,
+    pub input: u8, // IN as the ROM last read it
+    pub xout: u8,
+    pub ctrl: u16,
+    pub cycles: u64, // When it was saved; restoring leaves the count alone
+    pub ram: Vec<u8>,
+}
+
+impl State {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let S = &self.cpu;
+        let mut bytes = Vec::with_capacity(HEADER + self.ram.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(b'0' + VERSION);
+        bytes.extend_from_slice(&S.PC.to_le_bytes());
+        bytes.extend_from_slice(&[S.IR, S.D, S.AC, S.X, S.Y, S.OUT, S.undef, self.input, self.xout]);
+        bytes.extend_from_slice(&self.ctrl.to_le_bytes());
+        bytes.extend_from_slice(&self.cycles.to_le_bytes());
+        bytes.extend_from_slice(&self.ram);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GigatronError> {
+        if bytes.len() <= MAGIC.len() || !bytes.starts_with(MAGIC) {
+            return Err(GigatronError::State);
+        }
+        let version = bytes[MAGIC.len()].wrapping_sub(b'0');
+        if version != VERSION {
+            return Err(GigatronError::SaveStateVersion { found: version, expected: VERSION });
+        }
+        let sizes = [RamSize::K32, RamSize::K64].map(|size| HEADER + size.bytes());
+        if !sizes.contains(&bytes.len()) {
+            return Err(GigatronError::State);
+        }
+        let b = &bytes[MAGIC.len() + 1..HEADER];
+        let cpu = CpuState {
+            PC: u16::from_le_bytes([b[0], b[1]]),
+            IR: b[2],
+            D: b[3],
+            AC: b[4],
+            X: b[5],
+            Y: b[6],
+            OUT: b[7],
+            undef: b[8],
+        };
+        Ok(State {
+            cpu,
+            input: b[9],
+            xout: b[10],
+            ctrl: u16::from_le_bytes([b[11], b[12]]),
+            cycles: u64::from_le_bytes(b[13..21].try_into().unwrap()),
+            ram: bytes[HEADER..].to_vec(),
+        })
+    }
+
+    pub fn write(&self, filename: &str) -> Result<(), GigatronError> {
+        File::create(filename)?.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn read(filename: &str) -> Result<Self, GigatronError> {
+        let mut bytes = Vec::new();
+        File::open(filename)?.read_to_end(&mut bytes)?;
+        State::from_bytes(&bytes)
+    }
+}
+
+/// Just the registers and the cycle count, a few bytes against a
+/// [`State`]'s 32K, cheap enough to take every frame or every instruction.
+/// Putting one back doesn't undo anything done to RAM since.
+#[derive(Clone, Debug)]
+pub struct CpuSnapshot {
+    pub cpu: CpuState,
+    pub cycles: u64,
+}
+
+/// One cycle as [`Gigatron::step_back`](crate::Gigatron::step_back)
+/// undoes it: the registers and cycle count before it, and the RAM byte it
+/// stored over, if it stored.
+struct Undo {
+    before: CpuSnapshot,
+    overwritten: Option<(u16, u8)>,
+}
+
+/// The last cycles run, up to a depth, oldest dropped first.
+pub(crate) struct History {
+    depth: usize,
+    cycles: VecDeque<Undo>,
+}
+
+impl History {
+    pub(crate) fn new(depth: usize) -> Self {
+        History { depth, cycles: VecDeque::with_capacity(depth) }
+    }
+
+    /// Starts a cycle, from `before`.
+    pub(crate) fn push(&mut self, before: CpuSnapshot) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.cycles.len() == self.depth {
+            self.cycles.pop_front();
+        }
+        self.cycles.push_back(Undo { before, overwritten: None });
+    }
+
+    /// Notes that the cycle started last stores over `old` at `addr`.
+    pub(crate) fn note_store(&mut self, addr: u16, old: u8) {
+        if let Some(undo) = self.cycles.back_mut() {
+            undo.overwritten = Some((addr, old));
+        }
+    }
+
+    /// The last cycle's registers before it and the RAM byte to put back.
+    pub(crate) fn pop(&mut self) -> Option<(CpuSnapshot, Option<(u16, u8)>)> {
+        self.cycles.pop_back().map(|undo| (undo.before, undo.overwritten))
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.cycles.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> State {
+        State {
+            cpu: CpuState { PC: 0x1234, IR: 1, D: 2, AC: 3, X: 4, Y: 5, OUT: 6, undef: 7 },
+            input: 8,
+            xout: 9,
+            ctrl: 0xabcd,
+            cycles: 0x0123_4567_89ab_cdef,
+            ram: (0..RamSize::K32.bytes()).map(|addr| addr as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let saved = state();
+        let restored = State::from_bytes(&saved.to_bytes()).unwrap();
+        assert_eq!(restored.to_bytes(), saved.to_bytes());
+        assert_eq!(restored.cycles, saved.cycles);
+    }
+
+    /// A version 1 file, with no cycle count, is refused rather than read
+    /// with its first RAM bytes taken for the count.
+    #[test]
+    fn old_version() {
+        let mut bytes = state().to_bytes();
+        bytes.drain(HEADER - 8..HEADER);
+        bytes[3] = b'1';
+        assert!(matches!(State::from_bytes(&bytes), Err(GigatronError::SaveStateVersion { found: 1, expected: 2 })));
+        assert!(matches!(State::from_bytes(b"PNG"), Err(GigatronError::State)));
+    }
+}
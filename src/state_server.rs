@@ -0,0 +1,69 @@
+use gigatron_example::error::GigatronError;
+use log::info;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a client gets to send its request, and to take the answer,
+/// before the next one is served.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The machine's state as JSON (--state-server), for a dashboard or test
+/// in another process: any HTTP GET on the address answers with what
+/// [`Gigatron::state_json`](gigatron_example::Gigatron::state_json) gave at
+/// the last vSync:
+///
+/// ```text
+/// curl http://127.0.0.1:8080/
+/// ```
+///
+/// A thread answers the requests, one at a time, so a slow client never
+/// holds up the emulator; it only ever swaps in the newest state. One
+/// that connects and sends nothing holds up the other clients for at most
+/// TIMEOUT.
+pub struct StateServer {
+    latest: Arc<Mutex<String>>,
+}
+
+impl StateServer {
+    pub fn open(addr: &str) -> Result<Self, GigatronError> {
+        let listener = TcpListener::bind(addr)?;
+        info!("Serving the machine state on http://{}/", listener.local_addr()?);
+        let latest = Arc::new(Mutex::new("{}".to_string()));
+        let shared = Arc::clone(&latest);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let json = shared.lock().map_or_else(|_| "{}".to_string(), |json| json.clone());
+                // A client that hangs up early only loses its own answer
+                let _ = answer(stream, &json);
+            }
+        });
+        Ok(StateServer { latest })
+    }
+
+    /// Makes `json` what the next requests get.
+    pub fn publish(&self, json: String) {
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = json;
+        }
+    }
+}
+
+/// Reads the request up to its blank line, whatever it asks for, and sends
+/// `json` back.
+fn answer(mut stream: TcpStream, json: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line.trim_end() != "" {
+        line.clear();
+    }
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        json.len(),
+        json
+    )
+}
@@ -0,0 +1,49 @@
+//! Names of the ROM's native SYS functions, the routines vCPU programs call
+//! through `sysFn` for the work that is too slow in vCPU code. Names are
+//! the ROM's own, with the cycles each needs as the suffix.
+
+/// ROMv6. Most of these addresses go back to earlier ROMs, but only this
+/// table has been checked against a ROM image.
+const ROM_V6: [(u16, &str); 31] = [
+    (0x005f, "SYS_Reset_88"),
+    (0x00ad, "SYS_Exec_88"),
+    (0x00f4, "SYS_Out_22"),
+    (0x00f9, "SYS_In_24"),
+    (0x04a7, "SYS_Random_34"),
+    (0x04b9, "SYS_LSRW7_30"),
+    (0x04c6, "SYS_LSRW8_24"),
+    (0x04cd, "SYS_LSLW8_24"),
+    (0x04d4, "SYS_Draw4_30"),
+    (0x04e1, "SYS_VDrawBits_134"),
+    (0x0600, "SYS_LSRW1_48"),
+    (0x0619, "SYS_LSRW2_52"),
+    (0x0636, "SYS_LSRW3_52"),
+    (0x0652, "SYS_LSRW4_50"),
+    (0x066d, "SYS_LSRW5_50"),
+    (0x0687, "SYS_LSRW6_48"),
+    (0x06a0, "SYS_LSLW4_46"),
+    (0x06b9, "SYS_Read3_40"),
+    (0x06c0, "SYS_Unpack_56"),
+    (0x0b00, "SYS_SetMode_v2_80"),
+    (0x0b03, "SYS_SetMemory_v2_54"),
+    (0x0b06, "SYS_SendSerial1_v3_80"),
+    (0x0b09, "SYS_ExpanderControl_v4_40"),
+    (0x0b0c, "SYS_Run6502_v4_80"),
+    (0x0b0f, "SYS_ResetWaveforms_v4_50"),
+    (0x0b12, "SYS_ShuffleNoise_v4_46"),
+    (0x0b15, "SYS_SpiExchangeBytes_v4_134"),
+    (0x0c00, "SYS_Sprite6_v3_64"),
+    (0x0c40, "SYS_Sprite6x_v3_64"),
+    (0x0c80, "SYS_Sprite6y_v3_64"),
+    (0x0cc0, "SYS_Sprite6xy_v3_64"),
+];
+
+/// Tables by romType (its upper 6 bits, as in RAM at 0x0021).
+const TABLES: [(u8, &[(u16, &str)]); 1] = [(crate::ROM_V6, &ROM_V6)];
+
+/// Name of the SYS function at ROM address `addr`, for a ROM of `rom_type`.
+/// None for versions without a table and for addresses not in one.
+pub fn name(rom_type: u8, addr: u16) -> Option<&'static str> {
+    let (_, table) = TABLES.iter().find(|(version, _)| *version == rom_type & 0xfc)?;
+    table.iter().find(|(at, _)| *at == addr).map(|(_, name)| *name)
+}
@@ -0,0 +1,77 @@
+use std::io::Write;
+use std::time::Duration;
+
+const COLUMNS: usize = 80;
+const ROWS: usize = 30; // Each character cell shows two pixel rows
+const INTERVAL: Duration = Duration::from_millis(1000 / 15);
+
+/// Draws frames on an ANSI terminal with 24-bit colored half blocks: the
+/// foreground paints the upper pixel and the background the lower one.
+/// The 160x120 screen is shown at half resolution, 80x60 pixels.
+pub struct Tty {
+    last: Option<Duration>, // Clock time of the last frame drawn
+    out: String,
+}
+
+impl Tty {
+    pub fn new() -> Self {
+        print!("\x1b[2J"); // Clear once, frames then redraw from the home position
+        Tty { last: None, out: String::new() }
+    }
+
+    /// Draws `buffer` (0RGB, `width` x `height`), at most 15 times a second
+    /// of `now` on the frontend's clock.
+    pub fn draw(&mut self, buffer: &[u32], width: usize, height: usize, now: Duration) {
+        if !self.due(now) {
+            return;
+        }
+        let sample = |col: usize, row: usize| {
+            let p = buffer[row * height / (2 * ROWS) * width + col * width / COLUMNS];
+            ((p >> 16) as u8, (p >> 8) as u8, p as u8)
+        };
+        self.out.clear();
+        self.out.push_str("\x1b[H");
+        for row in 0..ROWS {
+            for col in 0..COLUMNS {
+                let (r, g, b) = sample(col, 2 * row);
+                let (br, bg, bb) = sample(col, 2 * row + 1);
+                self.out.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}", r, g, b, br, bg, bb));
+            }
+            self.out.push_str("\x1b[0m\n");
+        }
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(self.out.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    /// Whether a frame at `now` is drawn rather than skipped, noting it
+    /// when it is.
+    fn due(&mut self, now: Duration) -> bool {
+        if self.last.is_some_and(|last| now - last < INTERVAL) {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gigatron_example::clock::{Clock, ManualClock};
+
+    /// At 60 frames a second, every fourth is drawn.
+    #[test]
+    fn skips_frames_down_to_15_a_second() {
+        let clock = ManualClock::new();
+        let mut tty = Tty { last: None, out: String::new() };
+        let mut drawn = Vec::new();
+        for frame in 0..60 {
+            if tty.due(clock.now()) {
+                drawn.push(frame);
+            }
+            clock.advance(Duration::from_secs_f64(1.0 / 60.0));
+        }
+        assert_eq!(drawn, (0..60).step_by(4).collect::<Vec<_>>());
+    }
+}
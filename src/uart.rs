@@ -0,0 +1,77 @@
+use crate::peripheral::Peripheral;
+use crate::{Gigatron, CLOCK_HZ};
+use log::{debug, trace, warn};
+use std::io::Write;
+
+/// XOUT bit the transmit line is taken from.
+pub const TX_BIT: u8 = 0;
+
+pub const DEFAULT_BAUD: u32 = 9600;
+
+/// Output-only serial port on an expansion, receiving 8N1 frames that the
+/// ROM bit-bangs on XOUT bit TX_BIT (idle high, start bit low, 8 data bits
+/// LSB first, stop bit high). XOUT only changes once per scanline, so rates
+/// up to a few thousand baud leave enough lines per bit to sample reliably.
+/// The ROM's LED sequencer drives the same bit, so programs using the port
+/// stop it first; until then its patterns show up as framing errors.
+pub struct Uart {
+    cycles_per_bit: f64,
+    out: Box<dyn Write>,
+    frame_start: Option<u64>, // Cycle the current start bit began
+    bits: u16,                // Data bits sampled so far, LSB first, then the stop bit
+    sampled: u32,             // Bits of the frame sampled, the start bit included
+    broken: bool,             // Framing error seen, waiting for the line to go idle
+}
+
+impl Uart {
+    pub fn new(baud: u32, out: Box<dyn Write>) -> Self {
+        Uart { cycles_per_bit: CLOCK_HZ / baud as f64, out, frame_start: None, bits: 0, sampled: 0, broken: false }
+    }
+
+    fn receive(&mut self, byte: u8) {
+        trace!("UART {:02x}", byte);
+        let written = self.out.write_all(&[byte]).and_then(|()| self.out.flush());
+        if let Err(e) = written {
+            warn!("UART output failed: {}", e);
+        }
+    }
+}
+
+impl Peripheral for Uart {
+    fn tick(&mut self, cycle: u64, gt: &Gigatron) {
+        let line = gt.xout() >> TX_BIT & 1;
+        let Some(start) = self.frame_start else {
+            if self.broken {
+                self.broken = line == 0;
+            } else if line == 0 {
+                self.frame_start = Some(cycle);
+                self.bits = 0;
+                self.sampled = 0;
+            }
+            return;
+        };
+        // Sample the start bit, 8 data bits and the stop bit in their middle
+        let middle = start + ((self.sampled as f64 + 0.5) * self.cycles_per_bit) as u64;
+        if cycle < middle {
+            return;
+        }
+        if self.sampled == 0 {
+            if line != 0 {
+                self.frame_start = None; // A glitch, not a start bit
+            }
+            self.sampled = 1;
+            return;
+        }
+        self.bits |= (line as u16) << (self.sampled - 1);
+        self.sampled += 1;
+        if self.sampled == 10 {
+            self.frame_start = None;
+            if self.bits & 0x100 == 0 {
+                debug!("UART framing error: no stop bit");
+                self.broken = true;
+                return;
+            }
+            self.receive(self.bits as u8);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use gigatron_example::patch::Patch;
+use gigatron_example::Gigatron;
+use log::{info, warn};
+use std::time::{Duration, SystemTime};
+
+/// How often the ROM file's modification time is looked at.
+const POLL: Duration = Duration::from_millis(500);
+
+/// Reloads the ROM file when it changes on disk (--watch), so a rebuilt ROM
+/// shows up without restarting the emulator.
+pub struct RomWatch {
+    filename: String,
+    patches: Vec<Patch>, // Applied again after every reload
+    seed: u64,           // Power-on garble stays the same across reloads
+    modified: Option<SystemTime>,
+    size: Option<u64>, // Of the file last loaded
+    checked: Duration, // Clock time of the last look
+}
+
+impl RomWatch {
+    pub fn new(filename: &str, patches: Vec<Patch>, seed: u64, now: Duration) -> Self {
+        RomWatch {
+            filename: filename.to_string(),
+            patches,
+            seed,
+            modified: modified(filename),
+            size: size(filename),
+            checked: now,
+        }
+    }
+
+    /// Reloads the ROM and powers the machine on again if the file has
+    /// changed since last time. Meant to be called often; it only looks at
+    /// the file every POLL of `now`, the frontend's clock.
+    pub fn check(&mut self, gt: &mut Gigatron, now: Duration) {
+        if now - self.checked < POLL {
+            return;
+        }
+        self.checked = now;
+        let modified = modified(&self.filename);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        let size = size(&self.filename);
+        let resized = match (self.size, size) {
+            (Some(old), Some(new)) if old != new => format!(" (was {} bytes, now {})", old, new),
+            _ => String::new(),
+        };
+        // Every ROM has the one 128K layout, so an image of another size
+        // (such as one still being written) fails the size check and leaves
+        // the old one in place; the next change retries
+        match gt.read_rom(&self.filename) {
+            Ok(()) => {
+                gt.apply_patches(&self.patches);
+                gt.seed(self.seed);
+                gt.init();
+                self.size = size;
+                info!("ROM {} changed{}: reloaded and reset", self.filename, resized);
+            }
+            Err(e) => warn!("ROM {} changed{} but can't be loaded: {}", self.filename, resized, e),
+        }
+    }
+}
+
+fn modified(filename: &str) -> Option<SystemTime> {
+    std::fs::metadata(filename).and_then(|m| m.modified()).ok()
+}
+
+fn size(filename: &str) -> Option<u64> {
+    std::fs::metadata(filename).map(|m| m.len()).ok()
+}
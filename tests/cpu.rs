@@ -0,0 +1,156 @@
+//! The CPU one cycle at a time, on ROMs holding little more than the
+//! instruction under test.
+
+use gigatron_example::{disasm, selftest, CpuState, Gigatron};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Where each ROM places the instruction under test.
+const INSTRUCTION: u16 = 0x0123;
+
+/// Registers the instruction starts from.
+const D: u8 = 0x37;
+const X: u8 = 0x2c;
+const Y: u8 = 0x03;
+const AC: u8 = 0x5a;
+const OUT: u8 = 0xc0; // Both syncs inactive
+const UNDEF: u8 = 0x99;
+
+/// RAM contents at the start, distinct enough that a wrong address shows.
+fn pattern(addr: usize) -> u8 {
+    ((addr * 31) ^ (addr >> 8)) as u8
+}
+
+fn addr(hi: u8, lo: u8) -> u16 {
+    (hi as u16) << 8 | lo as u16
+}
+
+/// A machine powered on into the RAM pattern, its ROM holding just
+/// `opcode d` at INSTRUCTION.
+fn machine(opcode: u8, d: u8) -> Gigatron {
+    let mut rom = vec![0u8; 1 << 17];
+    rom[2 * INSTRUCTION as usize..][..2].copy_from_slice(&[opcode, d]);
+    let mut gt = Gigatron::new();
+    gt.read_rom_bytes(&rom).unwrap();
+    gt.clean_boot = true;
+    gt.reset_hold = 0;
+    gt.init();
+    for (addr, b) in gt.RAM.iter_mut().enumerate() {
+        *b = pattern(addr);
+    }
+    gt
+}
+
+/// Runs the instruction from the registers above: a nop in the pipeline
+/// fetches it, the next cycle executes it.
+fn execute(gt: &mut Gigatron) {
+    gt.S = CpuState { PC: INSTRUCTION, IR: 0x02, D: 0, AC, X, Y, OUT, undef: UNDEF };
+    gt.step();
+    gt.step();
+}
+
+/// The opcode table --selftest checks a build against.
+#[test]
+fn selftest() {
+    let failures: Vec<String> = selftest::run()
+        .into_iter()
+        .filter_map(|outcome| outcome.failure.map(|failure| format!("{}: {}", outcome.name, failure)))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// The CPU keeps fetching from address 0 for as long as reset is held, and
+/// runs on from there once released.
+#[test]
+fn reset_hold() {
+    const HOLD: u64 = 5;
+    let mut rom = vec![0u8; 1 << 17];
+    rom[0] = 0x02; // A nop at 0, so the image isn't blank
+    let mut gt = Gigatron::new();
+    gt.read_rom_bytes(&rom).unwrap();
+    gt.seed(0); // Start from a garbled PC
+    gt.reset_hold = HOLD;
+    gt.init();
+    for cycle in 0..HOLD {
+        assert!(gt.in_reset(), "released after {} cycles", cycle);
+        gt.step();
+        assert_eq!(gt.S.PC, 1, "cycle {} fetched from ${:04x}", cycle, gt.S.PC - 1);
+    }
+    assert!(!gt.in_reset(), "still held");
+    gt.step();
+    assert_eq!(gt.S.PC, 2, "PC after release");
+}
+
+/// A store whose bus names RAM as the source, `ctrl` in every addressing
+/// mode: the cycle writes, and never reads. The RAM doesn't drive the bus,
+/// so it stores the floating value, the expander latches the address,
+/// and a read watch on all of RAM isn't asked for anything.
+#[test]
+fn store_from_ram_doesnt_read() {
+    for mode in 0..8 {
+        let opcode = 0xc1 | mode << 2;
+        let name = disasm::disassemble(opcode, D);
+        let to = match mode {
+            1 => X as u16,
+            2 => addr(Y, D),
+            3 | 7 => addr(Y, X),
+            _ => D as u16,
+        };
+        let mut gt = machine(opcode, D);
+        let reads = Rc::new(RefCell::new(Vec::new()));
+        let seen = reads.clone();
+        gt.add_read_watch_range(0x0000..=0x7fff, Box::new(move |addr| {
+            seen.borrow_mut().push(addr);
+            0
+        }));
+        execute(&mut gt);
+        assert_eq!(reads.borrow()[..], [], "{} read", name);
+        assert_eq!(gt.RAM[to as usize], UNDEF, "{} stored", name);
+        assert_eq!(gt.expansion().ctrl, to, "{} left ctrl", name);
+        assert_eq!((gt.S.AC, gt.S.OUT), (AC, OUT), "{} changed AC or OUT", name);
+    }
+}
+
+/// A jump's mode bits are its condition, so a `[$dd]` operand always reads
+/// the zero page: the mode decoder that would bring in X and Y (and
+/// increment X for mode 7) is bypassed, as on the hardware and in gtemu.c.
+/// The machine has X and Y where either would pick up a different byte.
+#[test]
+fn jumps_read_the_zero_page() {
+    for (opcode, target) in [(0xe1, 0x7f42), (0xfd, 0x0142)] { // jmp y,[$ff] and bra [$ff]
+        let name = disasm::disassemble(opcode, 0xff);
+        let mut gt = machine(opcode, 0xff);
+        gt.RAM[0x00ff] = 0x42;
+        gt.RAM[0x7fff] = 0x24; // What [y,$ff] and [y,x] would read
+        gt.S = CpuState { PC: INSTRUCTION, IR: 0x02, X: 0xff, Y: 0x7f, OUT, ..CpuState::new() };
+        gt.step();
+        gt.step();
+        assert_eq!(gt.S.PC, target, "{} jumped", name);
+        assert_eq!(gt.S.X, 0xff, "{} changed X", name);
+    }
+}
+
+/// A write watch sees the store into its range, with the stored value, and
+/// nothing else.
+#[test]
+fn write_watch() {
+    let mut gt = machine(0xce, D); // st [y,x]
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = seen.clone();
+    gt.add_write_watch_range(0x0320..=0x032f, Box::new(move |addr, value| log.borrow_mut().push((addr, value))));
+    let log = seen.clone();
+    gt.add_write_watch(0x0337, Box::new(move |addr, value| log.borrow_mut().push((addr, value))));
+    execute(&mut gt);
+    assert_eq!(seen.borrow()[..], [(addr(Y, X), AC)]);
+}
+
+/// A read watch supplies the loaded value, and RAM keeps its own.
+#[test]
+fn read_watch() {
+    let mut gt = machine(0x0d, D); // ld [y,x]
+    let at = addr(Y, X);
+    gt.add_read_watch(at, Box::new(|addr| addr as u8 ^ 0xff));
+    execute(&mut gt);
+    assert_eq!(gt.S.AC, X ^ 0xff);
+    assert_eq!(gt.RAM[at as usize], pattern(at as usize), "RAM changed");
+}
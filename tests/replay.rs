@@ -0,0 +1,29 @@
+//! A recorded session, replayed from power-on with a fixed seed, ends on
+//! the screen it ended on when it was recorded: CPU, input and rendering
+//! together. A change that moves this hash changes what a ROM does or
+//! shows; if that is intended, set the new value from the failure.
+
+use gigatron_example::replay::{InputEvent, Replay};
+use gigatron_example::{GigatronBuilder, FRAME_CYCLES, ROM6};
+
+const SESSION: &str = include_str!("../fixtures/launch_basic.log");
+const SESSION_FRAMES: u64 = 300;
+const SESSION_HASH: u64 = 0x35f9b05a20800633;
+
+#[test]
+fn launch_basic() {
+    let mut replay = Replay::parse_log(SESSION).unwrap();
+    let mut gt = GigatronBuilder::new().rom_bytes(ROM6).seed(0).build().unwrap();
+    let max_cycles = 2 * SESSION_FRAMES * FRAME_CYCLES;
+    while gt.frames() < SESSION_FRAMES {
+        assert!(gt.cycles() <= max_cycles, "only {} frames in {} cycles", gt.frames(), max_cycles);
+        while let Some(event) = replay.next(gt.cycles()) {
+            match event {
+                InputEvent::Key(code) => gt.port.key(code, gt.cycles()),
+                InputEvent::Joystick(mask) => gt.port.joystick(mask),
+            }
+        }
+        gt.step();
+    }
+    assert_eq!(gt.frame_hash(), SESSION_HASH, "frame hash {:016x}", gt.frame_hash());
+}
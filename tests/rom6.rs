@@ -0,0 +1,165 @@
+//! ROMv6 run from power-on with a fixed seed, for what only a real ROM
+//! shows.
+
+use gigatron_example::{Gigatron, GigatronBuilder, RamSize, CLOCK_HZ, FRAME_CYCLES, RASTER_WIDTH, ROM6, SCREEN_WIDTH, VISIBLE_X, VISIBLE_Y};
+
+fn rom6() -> Gigatron {
+    GigatronBuilder::new().rom_bytes(ROM6).seed(0).build().unwrap()
+}
+
+/// Where ROMv6's reset code is done counting RAM: it stores a marker at
+/// page 0, then at pages 1, 2, 4 and so on, each time checking whether the
+/// first marker changed with it, and leaves the page count it reached in
+/// memSize.
+const PROBE_DONE: u16 = 0x0016;
+const MEM_SIZE: usize = 0x0001;
+
+/// The pages ROMv6 counts with `size` fitted, 0 for all 256.
+fn probed_pages(size: RamSize) -> u8 {
+    let mut gt = GigatronBuilder::new().rom_bytes(ROM6).seed(0).ram_size(size).build().unwrap();
+    while gt.S.PC != PROBE_DONE {
+        assert!(gt.cycles() <= 1000, "the probe didn't finish, PC=${:04x}", gt.S.PC);
+        gt.step();
+    }
+    gt.RAM[MEM_SIZE]
+}
+
+/// With 32K address line 15 isn't connected, so the store at page 0x80
+/// comes back at page 0 and the probe stops there; with 64K it doesn't,
+/// and the count runs through every page.
+#[test]
+fn ram_size_probe() {
+    assert_eq!(probed_pages(RamSize::K32), 0x80);
+    assert_eq!(probed_pages(RamSize::K64), 0x00);
+}
+
+/// ROMv6's blinkenLights, lit one at a time from the right while it tests
+/// RAM before the first frame, all four until frame 19, then stepping
+/// through the scanner every 10 frames.
+const RAM_TEST_LEDS: [u8; 4] = [0b0001, 0b0011, 0b0111, 0b1111];
+const SCANNER_START: u64 = 19;
+const SCANNER_STEP: u64 = 10;
+#[rustfmt::skip]
+const SCANNER: [u8; 24] = [
+    0b0111, 0b0011, 0b0001, 0b0010, 0b0100, 0b1000, 0b0100, 0b0010, 0b0001, 0b0011, 0b0111, 0b1111,
+    0b1110, 0b1100, 0b1000, 0b0100, 0b0010, 0b0001, 0b0010, 0b0100, 0b1000, 0b1100, 0b1110, 0b1111,
+];
+const LED_FRAMES: u64 = SCANNER_START + SCANNER_STEP * SCANNER.len() as u64 + SCANNER_STEP;
+
+/// The LEDs go through the boot pattern above, as seen at every vSync the
+/// way a frontend shows them, and XOUT only ever takes AC on the cycle
+/// hSync rises: a latch anywhere else in the line would move the steps
+/// or let the audio bits through mid-line.
+#[test]
+fn blinken_lights() {
+    let mut gt = rom6();
+    let mut ram_test = Vec::new();
+    let max_cycles = 2 * LED_FRAMES * FRAME_CYCLES;
+    while gt.frames() < LED_FRAMES {
+        assert!(gt.cycles() <= max_cycles, "only {} frames in {} cycles", gt.frames(), max_cycles);
+        let (out, xout) = (gt.S.OUT, gt.xout());
+        let edges = gt.step();
+        let rising = out & 0x40 == 0 && gt.S.OUT & 0x40 != 0;
+        assert!(gt.xout() == xout || rising, "XOUT changed at cycle {} without a rising hSync", gt.cycles());
+        let leds = gt.xout() & 0x0f;
+        if gt.frames() == 0 && leds != xout & 0x0f {
+            ram_test.push(leds);
+        }
+        if !edges.vSync {
+            continue;
+        }
+        let frame = gt.frames();
+        let expected = match frame.checked_sub(SCANNER_START) {
+            Some(n) => SCANNER[(n / SCANNER_STEP) as usize % SCANNER.len()],
+            None => 0b1111,
+        };
+        assert_eq!(leds, expected, "LEDs at frame {}", frame);
+    }
+    assert_eq!(ram_test, RAM_TEST_LEDS, "LEDs during the RAM test");
+}
+
+/// Logical rows a store changes halfway down the frame: one the beam has
+/// already drawn, one it has yet to reach.
+const DRAWN_ROW: usize = 10;
+const UNDRAWN_ROW: usize = 100;
+const RECOLOR: u8 = 0x15;
+
+/// Stores made while the beam is halfway down the screen: the beam's
+/// picture (the raster, as --renderer beam shows it) keeps what the kernel
+/// sent for the rows above, while the snapshot of RAM at vSync (--renderer
+/// table) already has the new colors there; rows below show them in both.
+/// So a raster effect, one row recolored between two others being drawn,
+/// only renders following the beam.
+#[test]
+fn mid_frame_stores() {
+    let mut gt = rom6();
+    gt.run_frames(60, 2 * CLOCK_HZ as u64).unwrap();
+    for _ in 0..FRAME_CYCLES / 2 {
+        gt.step();
+    }
+    let before = gt.frame();
+    let lines = gt.scanlines();
+    for row in [DRAWN_ROW, UNDRAWN_ROW] {
+        let (page, dx) = lines[row];
+        assert!(!before[row * SCREEN_WIDTH..][..SCREEN_WIDTH].contains(&RECOLOR), "row {} already has the color", row);
+        for x in 0..SCREEN_WIDTH {
+            gt.RAM[(page as usize) << 8 | dx.wrapping_add(x as u8) as usize] = RECOLOR;
+        }
+    }
+    assert!(gt.run_frame(), "no vSync after the stores");
+    let (frame, raster) = (gt.frame(), gt.raster());
+    // The second of a row's four lines: ROMv6 leaves the first black
+    let beam = |row: usize, x: usize| raster[(VISIBLE_Y + 4 * row + 1) * RASTER_WIDTH + VISIBLE_X + x];
+    for x in 0..SCREEN_WIDTH {
+        let (drawn, undrawn) = (DRAWN_ROW * SCREEN_WIDTH + x, UNDRAWN_ROW * SCREEN_WIDTH + x);
+        assert_eq!((frame[drawn], frame[undrawn]), (RECOLOR, RECOLOR), "the snapshot at x {}", x);
+        assert_eq!(beam(DRAWN_ROW, x), before[drawn], "the beam at ({}, {}), drawn before the store", x, DRAWN_ROW);
+        assert_eq!(beam(UNDRAWN_ROW, x), RECOLOR, "the beam at ({}, {}), drawn after the store", x, UNDRAWN_ROW);
+    }
+}
+
+/// The ROM accessors read what was loaded, and a slice running past the
+/// last word stops there.
+#[test]
+fn rom_access() {
+    let gt = rom6();
+    let at = |addr: usize| [ROM6[2 * addr], ROM6[2 * addr + 1]];
+    assert_eq!(gt.rom_word(0x1234), at(0x1234));
+    assert_eq!(gt.rom_slice(0xfffe, 4), [at(0xfffe), at(0xffff)]);
+}
+
+/// Rolling back to a CPU snapshot puts the registers and the cycle count
+/// back, while RAM keeps what the ROM stored since.
+#[test]
+fn cpu_snapshot() {
+    let mut gt = rom6();
+    gt.run_frames(2, CLOCK_HZ as u64).unwrap();
+    let (snapshot, ram) = (gt.save_cpu_state(), gt.RAM.clone());
+    for _ in 0..1000 {
+        gt.step();
+    }
+    assert_ne!(gt.RAM, ram, "the ROM stored nothing");
+    gt.restore_cpu_state(&snapshot);
+    assert_eq!((&gt.S, gt.cycles()), (&snapshot.cpu, snapshot.cycles));
+    assert_ne!(gt.RAM, ram, "RAM was rolled back too");
+}
+
+/// Stepping back over every cycle the history kept lands on the registers,
+/// cycle count and RAM from before them, and stops there.
+#[test]
+fn step_back() {
+    let mut gt = rom6();
+    gt.run_frames(2, CLOCK_HZ as u64).unwrap();
+    gt.enable_history(1000);
+    let (snapshot, ram) = (gt.save_cpu_state(), gt.RAM.clone());
+    for _ in 0..1000 {
+        gt.step();
+    }
+    assert_ne!(gt.RAM, ram, "the ROM stored nothing to undo");
+    for i in 0..1000 {
+        assert!(gt.step_back(), "history ran out after {} cycles", i);
+    }
+    assert_eq!((&gt.S, gt.cycles()), (&snapshot.cpu, snapshot.cycles));
+    assert!(gt.RAM == ram, "RAM not put back");
+    assert!(!gt.step_back(), "stepped back past the start of the history");
+}